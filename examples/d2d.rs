@@ -1,7 +1,11 @@
+#[cfg(windows)]
 use raw_window_handle::HasRawWindowHandle;
+#[cfg(windows)]
 use std::path::Path;
+#[cfg(windows)]
 use winit::{dpi::*, event::*, event_loop::*, window::*};
 
+#[cfg(windows)]
 fn main() -> anyhow::Result<()> {
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
@@ -98,3 +102,6 @@ fn main() -> anyhow::Result<()> {
         }
     });
 }
+
+#[cfg(not(windows))]
+fn main() {}
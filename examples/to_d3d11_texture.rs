@@ -1,22 +1,28 @@
+#[cfg(windows)]
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+#[cfg(windows)]
 use windows::Win32::{
     Foundation::{HINSTANCE, HWND},
     Graphics::{Direct3D::Fxc::*, Direct3D::*, Direct3D11::*, Dxgi::Common::*, Dxgi::*},
 };
+#[cfg(windows)]
 use winit::{dpi::*, event::*, event_loop::*, window::*};
 
+#[cfg(windows)]
 #[repr(C)]
 struct Vertex {
     position: [f32; 3],
     tex: [f32; 2],
 }
 
+#[cfg(windows)]
 impl Vertex {
     const fn new(position: [f32; 3], tex: [f32; 2]) -> Self {
         Self { position, tex }
     }
 }
 
+#[cfg(windows)]
 fn main() -> anyhow::Result<()> {
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
@@ -43,7 +49,9 @@ fn main() -> anyhow::Result<()> {
     let dxgi_factory = unsafe { CreateDXGIFactory1::<IDXGIFactory2>()? };
     let window_size = window.inner_size();
     let swap_chain = unsafe {
-        let RawWindowHandle::Win32(handle) = window.raw_window_handle() else { panic!() };
+        let RawWindowHandle::Win32(handle) = window.raw_window_handle() else {
+            panic!()
+        };
         dxgi_factory.CreateSwapChainForHwnd(
             &device,
             HWND(handle.hwnd as _),
@@ -144,8 +152,8 @@ fn main() -> anyhow::Result<()> {
             ps_blob.GetBufferPointer() as *const u8,
             ps_blob.GetBufferSize(),
         );
-        let vs = device.CreateVertexShader(&vs_blob, None)?;
-        let ps = device.CreatePixelShader(&ps_blob, None)?;
+        let vs = device.CreateVertexShader(vs_blob, None)?;
+        let ps = device.CreatePixelShader(ps_blob, None)?;
         let descs = [
             D3D11_INPUT_ELEMENT_DESC {
                 SemanticName: windows::s!("POSITION"),
@@ -279,3 +287,6 @@ fn main() -> anyhow::Result<()> {
         }
     });
 }
+
+#[cfg(not(windows))]
+fn main() {}
@@ -1,24 +1,31 @@
+#[cfg(windows)]
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+#[cfg(windows)]
 use windows::core::Interface;
+#[cfg(windows)]
 use windows::Win32::{
     Foundation::*,
     Graphics::{Direct3D::Dxc::*, Direct3D::*, Direct3D12::*, Dxgi::Common::*, Dxgi::*},
     System::{Threading::*, WindowsProgramming::*},
 };
+#[cfg(windows)]
 use winit::{dpi::*, event::*, event_loop::*, window::*};
 
+#[cfg(windows)]
 #[repr(C)]
 struct Vertex {
     position: [f32; 3],
     uv: [f32; 2],
 }
 
+#[cfg(windows)]
 impl Vertex {
     const fn new(position: [f32; 3], uv: [f32; 2]) -> Self {
         Self { position, uv }
     }
 }
 
+#[cfg(windows)]
 fn resource_barrier(
     command_list: &ID3D12GraphicsCommandList,
     resource: &ID3D12Resource,
@@ -43,6 +50,7 @@ fn resource_barrier(
     }
 }
 
+#[cfg(windows)]
 fn wait_gpu(command_queue: &ID3D12CommandQueue, fence: &ID3D12Fence, fence_value: &mut u64) {
     unsafe {
         let fv = *fence_value;
@@ -57,6 +65,7 @@ fn wait_gpu(command_queue: &ID3D12CommandQueue, fence: &ID3D12Fence, fence_value
     }
 }
 
+#[cfg(windows)]
 fn main() -> anyhow::Result<()> {
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
@@ -88,7 +97,9 @@ fn main() -> anyhow::Result<()> {
     };
     let window_size = window.inner_size();
     let swap_chain: IDXGISwapChain4 = unsafe {
-        let RawWindowHandle::Win32(window_handle) = window.raw_window_handle() else { panic!() };
+        let RawWindowHandle::Win32(window_handle) = window.raw_window_handle() else {
+            panic!()
+        };
         let dxgi_factory: IDXGIFactory4 = CreateDXGIFactory1()?;
         dxgi_factory
             .CreateSwapChainForHwnd(
@@ -494,13 +505,8 @@ fn main() -> anyhow::Result<()> {
                     &*[0.0, 0.0, 0.3, 0.0].as_ptr(),
                     &[],
                 );
-                command_list.OMSetRenderTargets(
-                    1,
-                    Some(&*[rtv_handle.clone()].as_ptr()),
-                    false,
-                    None,
-                );
-                command_list.IASetVertexBuffers(0, Some(&[vbv.clone()]));
+                command_list.OMSetRenderTargets(1, Some(&*[rtv_handle].as_ptr()), false, None);
+                command_list.IASetVertexBuffers(0, Some(&[vbv]));
                 command_list.IASetIndexBuffer(Some(&ibv));
                 command_list.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
                 command_list.DrawIndexedInstanced(6, 1, 0, 0, 0);
@@ -533,3 +539,6 @@ fn main() -> anyhow::Result<()> {
         }
     });
 }
+
+#[cfg(not(windows))]
+fn main() {}
@@ -1,8 +1,11 @@
+#[cfg(windows)]
 use windows::Win32::System::Com::{
     CoInitializeEx, CoUninitialize, COINIT_DISABLE_OLE1DDE, COINIT_MULTITHREADED,
 };
+#[cfg(windows)]
 use witas::ToLogical;
 
+#[cfg(windows)]
 async fn main_loop() -> anyhow::Result<()> {
     let (window, mut rx) = witas::Window::builder()
         .title("mltg witas")
@@ -19,10 +22,12 @@ async fn main_loop() -> anyhow::Result<()> {
         let event = rx.recv().await;
         match event {
             witas::Event::Draw(_) => {
-                let Some(window_size) = window
-                    .inner_size()
-                    .await else { continue };
-                let Some(dpi) = window.dpi().await else { continue };
+                let Some(window_size) = window.inner_size().await else {
+                    continue;
+                };
+                let Some(dpi) = window.dpi().await else {
+                    continue;
+                };
                 let window_size = window_size.to_logical(dpi as _);
                 ctx.set_dpi(dpi as _);
                 ctx.draw(&render_target, |cmd| {
@@ -46,6 +51,7 @@ async fn main_loop() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[cfg(windows)]
 fn main() -> anyhow::Result<()> {
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -58,3 +64,6 @@ fn main() -> anyhow::Result<()> {
         .build()?
         .block_on(main_loop())
 }
+
+#[cfg(not(windows))]
+fn main() {}
@@ -1,11 +1,8 @@
-use crate::utility::*;
 use crate::*;
+#[cfg(windows)]
 use windows::Win32::Graphics::Direct2D::*;
 
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct SolidColorBrush(ID2D1SolidColorBrush);
-
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct GradientStop {
     pub position: f32,
     pub color: Rgba<f32>,
@@ -21,16 +18,6 @@ impl GradientStop {
     }
 }
 
-impl From<GradientStop> for D2D1_GRADIENT_STOP {
-    #[inline]
-    fn from(src: GradientStop) -> Self {
-        Self {
-            position: src.position,
-            color: Wrapper(src.color).into(),
-        }
-    }
-}
-
 impl<T> From<(f32, T)> for GradientStop
 where
     T: Into<Rgba<f32>>,
@@ -47,38 +34,138 @@ where
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(u32)]
 pub enum GradientMode {
-    Clamp = D2D1_EXTEND_MODE_CLAMP.0,
-    Mirror = D2D1_EXTEND_MODE_MIRROR.0,
-    Wrap = D2D1_EXTEND_MODE_WRAP.0,
+    Clamp = 0,
+    Mirror = 2,
+    Wrap = 1,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct GradientStopCollection(ID2D1GradientStopCollection);
+#[derive(Clone, Debug)]
+pub struct GradientStopCollection {
+    pub(crate) mode: GradientMode,
+    pub(crate) stops: Vec<GradientStop>,
+    #[cfg(windows)]
+    pub(crate) handle: crate::d2d1::DeviceCache<ID2D1GradientStopCollection>,
+}
 
 impl GradientStopCollection {
-    pub(crate) fn new<T>(dc: &ID2D1DeviceContext5, mode: GradientMode, stops: &[T]) -> Result<Self>
+    pub(crate) fn new<T>(mode: GradientMode, stops: &[T]) -> Self
     where
         T: Into<GradientStop> + Clone,
     {
-        let stops = stops
-            .iter()
-            .cloned()
-            .map(|stop| stop.into().into())
-            .collect::<Vec<_>>();
-        let collection = unsafe {
-            dc.CreateGradientStopCollection(&stops, D2D1_GAMMA_2_2, D2D1_EXTEND_MODE(mode as u32))?
-        };
-        Ok(Self(collection))
+        Self {
+            mode,
+            stops: stops.iter().cloned().map(|stop| stop.into()).collect(),
+            #[cfg(windows)]
+            handle: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn mode(&self) -> GradientMode {
+        self.mode
+    }
+
+    #[inline]
+    pub fn stops(&self) -> &[GradientStop] {
+        &self.stops
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct LinearGradientBrush(ID2D1LinearGradientBrush);
+impl PartialEq for GradientStopCollection {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.mode == other.mode && self.stops == other.stops
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SolidColorBrush {
+    pub(crate) color: Rgba<f32>,
+    #[cfg(windows)]
+    pub(crate) handle: crate::d2d1::DeviceCache<ID2D1Brush>,
+}
+
+impl SolidColorBrush {
+    #[inline]
+    pub fn color(&self) -> Rgba<f32> {
+        self.color
+    }
+}
 
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct RadialGradientBrush(ID2D1RadialGradientBrush);
+impl PartialEq for SolidColorBrush {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.color == other.color
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LinearGradientBrush {
+    pub(crate) start: Point<f32>,
+    pub(crate) end: Point<f32>,
+    pub(crate) stops: GradientStopCollection,
+    #[cfg(windows)]
+    pub(crate) handle: crate::d2d1::DeviceCache<ID2D1Brush>,
+}
+
+impl LinearGradientBrush {
+    #[inline]
+    pub fn start(&self) -> Point<f32> {
+        self.start
+    }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+    #[inline]
+    pub fn end(&self) -> Point<f32> {
+        self.end
+    }
+
+    #[inline]
+    pub fn stops(&self) -> &GradientStopCollection {
+        &self.stops
+    }
+}
+
+impl PartialEq for LinearGradientBrush {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start && self.end == other.end && self.stops == other.stops
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RadialGradientBrush {
+    pub(crate) ellipse: Ellipse,
+    pub(crate) offset: Point<f32>,
+    pub(crate) stops: GradientStopCollection,
+    #[cfg(windows)]
+    pub(crate) handle: crate::d2d1::DeviceCache<ID2D1Brush>,
+}
+
+impl RadialGradientBrush {
+    #[inline]
+    pub fn ellipse(&self) -> Ellipse {
+        self.ellipse
+    }
+
+    #[inline]
+    pub fn offset(&self) -> Point<f32> {
+        self.offset
+    }
+
+    #[inline]
+    pub fn stops(&self) -> &GradientStopCollection {
+        &self.stops
+    }
+}
+
+impl PartialEq for RadialGradientBrush {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.ellipse == other.ellipse && self.offset == other.offset && self.stops == other.stops
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
 pub enum Brush {
     SolidColor(SolidColorBrush),
     LinearGradient(LinearGradientBrush),
@@ -86,65 +173,39 @@ pub enum Brush {
 }
 
 impl Brush {
-    pub(crate) fn solid_color(
-        dc: &ID2D1DeviceContext5,
-        color: impl Into<Rgba<f32>>,
-    ) -> Result<Self> {
-        let color = Wrapper(color.into()).into();
-        let brush = unsafe { dc.CreateSolidColorBrush(&color, None)? };
-        Ok(Self::SolidColor(SolidColorBrush(brush)))
+    pub(crate) fn solid_color(color: impl Into<Rgba<f32>>) -> Self {
+        Self::SolidColor(SolidColorBrush {
+            color: color.into(),
+            #[cfg(windows)]
+            handle: Default::default(),
+        })
     }
 
     pub(crate) fn linear_gradient(
-        dc: &ID2D1DeviceContext5,
         start: impl Into<Point<f32>>,
         end: impl Into<Point<f32>>,
         stops: &GradientStopCollection,
-    ) -> Result<Self> {
-        let start = start.into();
-        let end = end.into();
-        let brush = unsafe {
-            dc.CreateLinearGradientBrush(
-                &D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES {
-                    startPoint: Wrapper(start).into(),
-                    endPoint: Wrapper(end).into(),
-                },
-                None,
-                &stops.0,
-            )?
-        };
-        Ok(Self::LinearGradient(LinearGradientBrush(brush)))
+    ) -> Self {
+        Self::LinearGradient(LinearGradientBrush {
+            start: start.into(),
+            end: end.into(),
+            stops: stops.clone(),
+            #[cfg(windows)]
+            handle: Default::default(),
+        })
     }
 
     pub(crate) fn radial_gradient(
-        dc: &ID2D1DeviceContext5,
         ellipse: impl Into<Ellipse>,
         offset: impl Into<Point<f32>>,
         stops: &GradientStopCollection,
-    ) -> Result<Self> {
-        let ellipse = ellipse.into();
-        let offset = offset.into();
-        let brush = unsafe {
-            dc.CreateRadialGradientBrush(
-                &D2D1_RADIAL_GRADIENT_BRUSH_PROPERTIES {
-                    center: Wrapper(ellipse.center).into(),
-                    radiusX: ellipse.radius.x,
-                    radiusY: ellipse.radius.y,
-                    gradientOriginOffset: Wrapper(offset).into(),
-                },
-                None,
-                &stops.0,
-            )?
-        };
-        Ok(Self::RadialGradient(RadialGradientBrush(brush)))
-    }
-
-    #[inline]
-    pub(crate) fn handle(&self) -> ID2D1Brush {
-        match self {
-            Self::SolidColor(b) => b.0.clone().into(),
-            Self::LinearGradient(b) => b.0.clone().into(),
-            Self::RadialGradient(b) => b.0.clone().into(),
-        }
+    ) -> Self {
+        Self::RadialGradient(RadialGradientBrush {
+            ellipse: ellipse.into(),
+            offset: offset.into(),
+            stops: stops.clone(),
+            #[cfg(windows)]
+            handle: Default::default(),
+        })
     }
 }
@@ -0,0 +1,530 @@
+use crate::flatten::{flatten, Polyline};
+use crate::geometry::polygon_figure;
+use crate::*;
+use std::collections::HashMap;
+
+type Vec2 = (f64, f64);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CombineMode {
+    Union,
+    Intersection,
+    Difference,
+    Xor,
+}
+
+impl CombineMode {
+    #[inline]
+    fn apply(self, a: bool, b: bool) -> bool {
+        match self {
+            Self::Union => a || b,
+            Self::Intersection => a && b,
+            Self::Difference => a && !b,
+            Self::Xor => a != b,
+        }
+    }
+}
+
+#[inline]
+fn sub(a: Vec2, b: Vec2) -> Vec2 {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+#[inline]
+fn dot(a: Vec2, b: Vec2) -> f64 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+#[inline]
+fn cross(a: Vec2, b: Vec2) -> f64 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+#[inline]
+fn lerp(a: Vec2, b: Vec2, t: f64) -> Vec2 {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+struct Operand {
+    polygons: Vec<Vec<Vec2>>,
+    fill_rule: FillRule,
+}
+
+impl Operand {
+    fn new(polylines: Vec<Polyline>, fill_rule: FillRule) -> Self {
+        let polygons = polylines
+            .into_iter()
+            .map(|polyline| {
+                let mut polygon = polyline
+                    .points
+                    .iter()
+                    .map(|p| (p.x as f64, p.y as f64))
+                    .collect::<Vec<_>>();
+                polygon.dedup();
+                if polygon.len() > 1 && polygon.first() == polygon.last() {
+                    polygon.pop();
+                }
+                polygon
+            })
+            .filter(|polygon| polygon.len() > 1)
+            .collect();
+        Self {
+            polygons,
+            fill_rule,
+        }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        self.polygons.iter().flat_map(|polygon| {
+            (0..polygon.len()).map(|i| (polygon[i], polygon[(i + 1) % polygon.len()]))
+        })
+    }
+
+    #[inline]
+    fn inside(&self, winding: i32) -> bool {
+        match self.fill_rule {
+            FillRule::EvenOdd => winding % 2 != 0,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+}
+
+struct Vertices {
+    points: Vec<Vec2>,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    grid: f64,
+}
+
+impl Vertices {
+    fn new(grid: f64) -> Self {
+        Self {
+            points: vec![],
+            cells: HashMap::new(),
+            grid,
+        }
+    }
+
+    fn insert(&mut self, p: Vec2) -> usize {
+        let cell = (
+            (p.0 / self.grid).floor() as i64,
+            (p.1 / self.grid).floor() as i64,
+        );
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let Some(indices) = self.cells.get(&(cell.0 + dx, cell.1 + dy)) else {
+                    continue;
+                };
+                for &i in indices {
+                    let d = sub(self.points[i], p);
+                    if dot(d, d) <= self.grid * self.grid {
+                        return i;
+                    }
+                }
+            }
+        }
+        self.points.push(p);
+        let index = self.points.len() - 1;
+        self.cells.entry(cell).or_default().push(index);
+        index
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Aabb {
+    min: Vec2,
+    max: Vec2,
+}
+
+impl Aabb {
+    #[inline]
+    fn new((a, b): (Vec2, Vec2), margin: f64) -> Self {
+        Self {
+            min: (a.0.min(b.0) - margin, a.1.min(b.1) - margin),
+            max: (a.0.max(b.0) + margin, a.1.max(b.1) + margin),
+        }
+    }
+
+    #[inline]
+    fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.0 <= other.max.0
+            && other.min.0 <= self.max.0
+            && self.min.1 <= other.max.1
+            && other.min.1 <= self.max.1
+    }
+}
+
+// Candidate pairs come from sweeping the boxes from left to right while keeping the boxes that
+// still reach the sweep line, so only edges whose boxes overlap are tested against each other.
+struct EdgeIndex {
+    candidates: Vec<Vec<usize>>,
+}
+
+impl EdgeIndex {
+    fn new(edges: &[(Vec2, Vec2)], margin: f64) -> Self {
+        let boxes = edges
+            .iter()
+            .map(|&edge| Aabb::new(edge, margin))
+            .collect::<Vec<_>>();
+        let mut order = (0..edges.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| boxes[a].min.0.total_cmp(&boxes[b].min.0));
+        let mut candidates = vec![vec![]; edges.len()];
+        let mut active: Vec<usize> = vec![];
+        for i in order {
+            let aabb = boxes[i];
+            active.retain(|&j| boxes[j].max.0 >= aabb.min.0);
+            for &j in &active {
+                if aabb.overlaps(&boxes[j]) {
+                    candidates[i].push(j);
+                    candidates[j].push(i);
+                }
+            }
+            active.push(i);
+        }
+        Self { candidates }
+    }
+
+    #[inline]
+    fn candidates(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        self.candidates[i].iter().copied()
+    }
+}
+
+// The split edges, merged when they join the same vertices. Each segment runs from its smaller
+// to its larger end point and counts how often each operand runs along it in that direction.
+#[derive(Default)]
+struct Segments {
+    ends: Vec<(usize, usize)>,
+    counts: Vec<(i32, i32)>,
+    lookup: HashMap<(usize, usize), usize>,
+}
+
+impl Segments {
+    fn add(&mut self, points: &[Vec2], u: usize, v: usize, first: bool) {
+        let (key, sign) = if points[u] < points[v] {
+            ((u, v), 1)
+        } else {
+            ((v, u), -1)
+        };
+        let index = *self.lookup.entry(key).or_insert_with(|| {
+            self.ends.push(key);
+            self.counts.push((0, 0));
+            self.ends.len() - 1
+        });
+        let count = &mut self.counts[index];
+        if first {
+            count.0 += sign;
+        } else {
+            count.1 += sign;
+        }
+    }
+
+    // Winding numbers of both operands on the left and the right of each segment. They are
+    // propagated through a left-to-right sweep over the segments spanning each slab between
+    // vertices, ordered bottom to top: the winding below a segment is the one above its
+    // neighbour, and a segment adds its counts to it. Segments starting at the same vertex are
+    // inserted bottom to top for that reason. Vertical segments take the winding on their left
+    // from the slab before them.
+    fn windings(&self, points: &[Vec2]) -> Vec<((i32, i32), (i32, i32))> {
+        let start = |s: usize| points[self.ends[s].0];
+        let end = |s: usize| points[self.ends[s].1];
+        let y_at = |s: usize, x: f64| {
+            let (p, q) = (start(s), end(s));
+            p.1 + (q.1 - p.1) * (x - p.0) / (q.0 - p.0)
+        };
+        let add = |a: (i32, i32), b: (i32, i32)| (a.0 + b.0, a.1 + b.1);
+        let mut order = (0..self.ends.len()).collect::<Vec<_>>();
+        order.sort_by(|&s, &t| start(s).0.total_cmp(&start(t).0));
+        let mut xs = self
+            .ends
+            .iter()
+            .flat_map(|&(u, v)| [points[u].0, points[v].0])
+            .collect::<Vec<_>>();
+        xs.sort_by(f64::total_cmp);
+        xs.dedup();
+        let mut sides = vec![((0, 0), (0, 0)); self.ends.len()];
+        let mut active: Vec<usize> = vec![];
+        let mut next = 0;
+        let mut prev = None;
+        for x in xs {
+            let first = next;
+            while next < order.len() && start(order[next]).0 == x {
+                next += 1;
+            }
+            let starting = &order[first..next];
+            for &s in starting.iter().filter(|&&s| end(s).0 == x) {
+                let left = prev.map_or((0, 0), |prev: f64| {
+                    let (xm, y) = ((prev + x) * 0.5, (start(s).1 + end(s).1) * 0.5);
+                    let pos = active.partition_point(|&t| y_at(t, xm) < y);
+                    pos.checked_sub(1).map_or((0, 0), |i| sides[active[i]].0)
+                });
+                let count = self.counts[s];
+                sides[s] = (left, add(left, (-count.0, -count.1)));
+            }
+            active.retain(|&t| end(t).0 > x);
+            let below = |s: usize, t: usize| {
+                let xm = (x + end(s).0.min(end(t).0)) * 0.5;
+                y_at(t, xm) < y_at(s, xm)
+            };
+            let mut starting = starting
+                .iter()
+                .copied()
+                .filter(|&s| end(s).0 > x)
+                .collect::<Vec<_>>();
+            starting.sort_by(|&s, &t| match (below(s, t), below(t, s)) {
+                (true, _) => std::cmp::Ordering::Greater,
+                (_, true) => std::cmp::Ordering::Less,
+                _ => std::cmp::Ordering::Equal,
+            });
+            for s in starting {
+                let pos = active.partition_point(|&t| below(s, t));
+                let below = pos.checked_sub(1).map_or((0, 0), |i| sides[active[i]].0);
+                sides[s] = (add(below, self.counts[s]), below);
+                active.insert(pos, s);
+            }
+            prev = Some(x);
+        }
+        sides
+    }
+}
+
+fn split_parameters(edges: &[(Vec2, Vec2)], index: &EdgeIndex, i: usize, grid: f64) -> Vec<f64> {
+    let (a, b) = edges[i];
+    let d1 = sub(b, a);
+    let len1 = dot(d1, d1).sqrt();
+    let mut params = vec![0.0, 1.0];
+    let mut push = |t: f64| {
+        if t * len1 > grid && (1.0 - t) * len1 > grid {
+            params.push(t);
+        }
+    };
+    for j in index.candidates(i) {
+        let (c, d) = edges[j];
+        let d2 = sub(d, c);
+        let len2 = dot(d2, d2).sqrt();
+        let denom = cross(d1, d2);
+        let ac = sub(c, a);
+        if denom.abs() > 1e-9 * len1 * len2 {
+            let t = cross(ac, d2) / denom;
+            let u = cross(ac, d1) / denom;
+            let (et, eu) = (grid / len1, grid / len2);
+            if (-et..=1.0 + et).contains(&t) && (-eu..=1.0 + eu).contains(&u) {
+                push(t);
+            }
+        } else if cross(ac, d1).abs() <= grid * len1 {
+            for p in [c, d] {
+                push(dot(sub(p, a), d1) / (len1 * len1));
+            }
+        }
+    }
+    params.sort_by(|x, y| x.total_cmp(y));
+    params
+}
+
+fn simplify(points: &mut Vec<Vec2>) {
+    let mut i = 0;
+    while points.len() > 2 && i < points.len() {
+        let n = points.len();
+        let prev = points[(i + n - 1) % n];
+        let next = points[(i + 1) % n];
+        let d1 = sub(points[i], prev);
+        let d2 = sub(next, points[i]);
+        let scale = dot(d1, d1).sqrt() * dot(d2, d2).sqrt();
+        if cross(d1, d2).abs() <= 1e-9 * scale && dot(d1, d2) >= 0.0 {
+            points.remove(i);
+            i = i.saturating_sub(1);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+pub(crate) fn combine(
+    a: (&[PathFigure], FillRule),
+    b: (&[PathFigure], FillRule),
+    mode: CombineMode,
+    tolerance: f32,
+) -> PathData {
+    let a = Operand::new(flatten(a.0, tolerance), a.1);
+    let b = Operand::new(flatten(b.0, tolerance), b.1);
+    let grid = (tolerance as f64 / 64.0).max(1e-6);
+    let edges = a.edges().chain(b.edges()).collect::<Vec<_>>();
+    let a_edges = a.edges().count();
+    let index = EdgeIndex::new(&edges, grid);
+    let mut vertices = Vertices::new(grid);
+    let mut segments = Segments::default();
+    for (i, &(p, q)) in edges.iter().enumerate() {
+        let params = split_parameters(&edges, &index, i, grid);
+        let indices = params
+            .iter()
+            .map(|&t| vertices.insert(lerp(p, q, t)))
+            .collect::<Vec<_>>();
+        for pair in indices.windows(2) {
+            if pair[0] != pair[1] {
+                segments.add(&vertices.points, pair[0], pair[1], i < a_edges);
+            }
+        }
+    }
+    let mut outgoing = HashMap::<usize, Vec<usize>>::new();
+    let windings = segments.windings(&vertices.points);
+    for (&(u, v), &(left, right)) in segments.ends.iter().zip(&windings) {
+        let inside_left = mode.apply(a.inside(left.0), b.inside(left.1));
+        let inside_right = mode.apply(a.inside(right.0), b.inside(right.1));
+        let (from, to) = match (inside_left, inside_right) {
+            (true, false) => (u, v),
+            (false, true) => (v, u),
+            _ => continue,
+        };
+        outgoing.entry(from).or_default().push(to);
+    }
+    let mut starts = outgoing.keys().copied().collect::<Vec<_>>();
+    starts.sort_unstable();
+    let mut figures = vec![];
+    for start in starts {
+        while let Some(next) = outgoing.get_mut(&start).and_then(|v| v.pop()) {
+            let mut points = vec![vertices.points[start]];
+            let mut current = next;
+            while current != start {
+                points.push(vertices.points[current]);
+                match outgoing.get_mut(&current).and_then(|v| v.pop()) {
+                    Some(next) => current = next,
+                    None => break,
+                }
+            }
+            simplify(&mut points);
+            if points.len() < 3 {
+                continue;
+            }
+            let points = points
+                .iter()
+                .map(|p| Point::new(p.0 as f32, p.1 as f32))
+                .collect::<Vec<_>>();
+            figures.push(polygon_figure(&points));
+        }
+    }
+    let mut data = PathData::from(figures);
+    data.set_fill_rule(FillRule::NonZero);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(path: &FilledPath) -> f32 {
+        path.path_data()
+            .figures()
+            .iter()
+            .map(|figure| {
+                let mut points = vec![figure.begin];
+                points.extend(figure.segments.iter().map(|s| s.end_point()));
+                (0..points.len())
+                    .map(|i| {
+                        let (p, q) = (points[i], points[(i + 1) % points.len()]);
+                        p.x * q.y - q.x * p.y
+                    })
+                    .sum::<f32>()
+                    / 2.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn rects() {
+        let a = Rect::new((0.0, 0.0), (10.0, 10.0));
+        let b = Rect::new((5.0, 5.0), (10.0, 10.0));
+        let tests = [
+            (CombineMode::Union, 175.0),
+            (CombineMode::Intersection, 25.0),
+            (CombineMode::Difference, 75.0),
+            (CombineMode::Xor, 150.0),
+        ];
+        for (mode, expected) in tests {
+            let path = a.combine(&b, mode, 0.02);
+            assert!(
+                (area(&path).abs() - expected).abs() < 1e-3,
+                "{:?}: {}",
+                mode,
+                area(&path)
+            );
+        }
+        let xor = a.combine(&b, CombineMode::Xor, 0.02);
+        assert!(xor.fill_contains((2.0, 2.0), 0.02));
+        assert!(!xor.fill_contains((7.0, 7.0), 0.02));
+        assert!(xor.fill_contains((12.0, 12.0), 0.02));
+    }
+
+    #[test]
+    fn shared_edges() {
+        let a = Rect::new((0.0, 0.0), (10.0, 10.0));
+        let b = Rect::new((10.0, 0.0), (10.0, 10.0));
+        let union = a.combine(&b, CombineMode::Union, 0.02);
+        let figures = union.path_data().figures();
+        assert_eq!(figures.len(), 1);
+        assert_eq!(figures[0].segments.len(), 3);
+        assert!((area(&union).abs() - 200.0).abs() < 1e-3);
+        let inner = Rect::new((0.0, 0.0), (10.0, 5.0));
+        let difference = a.combine(&inner, CombineMode::Difference, 0.02);
+        assert_eq!(difference.path_data().figures().len(), 1);
+        assert!((area(&difference).abs() - 50.0).abs() < 1e-3);
+        assert!(a.combine(&a, CombineMode::Xor, 0.02).path_data().is_empty());
+    }
+
+    #[test]
+    fn cut_out() {
+        let badge = Circle::new((0.0, 0.0), 10.0);
+        let hole = Circle::new((0.0, 0.0), 5.0);
+        let ring = badge.combine(&hole, CombineMode::Difference, 0.01);
+        assert_eq!(ring.path_data().figures().len(), 2);
+        let expected = std::f32::consts::PI * 75.0;
+        assert!((area(&ring).abs() - expected).abs() / expected < 0.01);
+        assert!(ring.fill_contains((7.5, 0.0), 0.01));
+        assert!(!ring.fill_contains((0.0, 0.0), 0.01));
+        assert!(!ring.fill_contains((11.0, 0.0), 0.01));
+    }
+
+    #[test]
+    fn many_edges() {
+        let (r, d) = (50.0f32, 40.0f32);
+        let a = Circle::new((0.0, 0.0), r);
+        let b = Circle::new((d, 0.0), r);
+        let union = a.combine(&b, CombineMode::Union, 0.002);
+        let lens = 2.0 * r * r * (d / (2.0 * r)).acos() - d / 2.0 * (4.0 * r * r - d * d).sqrt();
+        let expected = 2.0 * std::f32::consts::PI * r * r - lens;
+        assert!(a.flatten(0.002)[0].points.len() > 300);
+        assert_eq!(union.path_data().figures().len(), 1);
+        assert!((area(&union).abs() - expected).abs() / expected < 1e-3);
+        let intersection = a.combine(&b, CombineMode::Intersection, 0.002);
+        assert!((area(&intersection).abs() - lens).abs() / lens < 1e-3);
+    }
+
+    #[test]
+    fn fill_rules() {
+        let square = |builder: PathBuilder<PathData>, x: f32, y: f32| {
+            builder
+                .begin((x, y))
+                .line_to((x + 10.0, y))
+                .line_to((x + 10.0, y + 10.0))
+                .line_to((x, y + 10.0))
+                .end(FigureEnd::Closed)
+                .unwrap()
+        };
+        let far = Rect::new((100.0, 0.0), (10.0, 10.0));
+        for (fill_rule, expected) in [(FillRule::NonZero, 275.0), (FillRule::EvenOdd, 250.0)] {
+            let mut data = square(square(PathData::builder(), 0.0, 0.0), 5.0, 5.0)
+                .close()
+                .unwrap();
+            data.set_fill_rule(fill_rule);
+            let path = FilledPath::from(data).combine(&far, CombineMode::Union, 0.02);
+            assert!(
+                (area(&path).abs() - expected).abs() < 1e-3,
+                "{:?}: {}",
+                fill_rule,
+                area(&path)
+            );
+            assert_eq!(
+                path.fill_contains((7.0, 7.0), 0.02),
+                fill_rule == FillRule::NonZero
+            );
+        }
+    }
+}
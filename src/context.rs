@@ -1,42 +1,78 @@
 use crate::*;
-use std::sync::Arc;
-use windows::Foundation::Numerics::Matrix3x2;
-use windows::core::Interface;
-use windows::Win32::Graphics::{
-    Direct2D::Common::*, Direct2D::*, DirectWrite::*, Imaging::D2D::*, Imaging::*,
-};
-use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+use std::cell::{Cell, RefCell};
+
+pub trait Renderer {
+    fn clear(&self, color: Rgba<f32>);
+    fn fill(&self, shape: &Shape, brush: &Brush);
+    fn stroke(&self, shape: &Shape, brush: &Brush, width: f32, style: Option<&StrokeStyle>);
+    #[cfg(windows)]
+    fn draw_text(&self, layout: &TextLayout, origin: Point<f32>, brush: &Brush);
+    fn draw_image(
+        &self,
+        image: &Image,
+        dest_rect: Rect<f32>,
+        src_rect: Option<Rect<f32>>,
+        interpolation: Interpolation,
+    );
+    fn push_clip(&self, rect: Rect<f32>);
+    fn pop_clip(&self);
+    fn translate(&self, point: Point<f32>);
+    fn scale(&self, size: Size<f32>);
+    fn concat(&self, transform: &Transform);
+    fn set_transform(&self, transform: &Transform);
+    fn transform(&self) -> Transform;
+    fn reset_transform(&self);
+}
 
 pub trait Fill {
-    fn fill(&self, dc: &ID2D1DeviceContext5, brush: &ID2D1Brush);
+    fn fill(&self, renderer: &dyn Renderer, brush: &Brush);
 }
 
 pub trait Stroke {
     fn stroke(
         &self,
-        dc: &ID2D1DeviceContext5,
-        brush: &ID2D1Brush,
+        renderer: &dyn Renderer,
+        brush: &Brush,
         width: f32,
-        style: Option<&ID2D1StrokeStyle>,
+        style: Option<&StrokeStyle>,
     );
 }
 
-pub struct DrawCommand {
-    dc: ID2D1DeviceContext5,
+pub struct DrawCommand<'a> {
+    pub(crate) renderer: &'a dyn Renderer,
+    transforms: RefCell<Vec<Transform>>,
+    unmatched_pops: Cell<usize>,
 }
 
-impl DrawCommand {
+impl<'a> DrawCommand<'a> {
     #[inline]
-    pub fn clear(&self, color: impl Into<Rgba<f32>>) {
-        unsafe {
-            let color = Wrapper(color.into()).into();
-            self.dc.Clear(Some(&color));
+    pub(crate) fn new(renderer: &'a dyn Renderer) -> Self {
+        Self {
+            renderer,
+            transforms: RefCell::new(vec![]),
+            unmatched_pops: Cell::new(0),
+        }
+    }
+
+    // Restores the transform from before any outstanding `push_transform` and reports whether
+    // the pushes and pops matched.
+    pub(crate) fn finish(&self) -> bool {
+        let balanced = self.transforms.borrow().is_empty() && self.unmatched_pops.get() == 0;
+        let saved = self.transforms.borrow_mut().drain(..).next();
+        if let Some(transform) = saved {
+            self.renderer.set_transform(&transform);
         }
+        balanced
+    }
+
+    #[inline]
+    pub fn clear(&self, color: impl Into<Rgba<f32>>) {
+        self.renderer.clear(color.into());
     }
 
     #[inline]
     pub fn fill(&self, object: &impl Fill, brush: &Brush) {
-        object.fill(&self.dc, &brush.handle());
+        object.fill(self.renderer, brush);
     }
 
     #[inline]
@@ -47,12 +83,7 @@ impl DrawCommand {
         width: f32,
         stroke_style: Option<&StrokeStyle>,
     ) {
-        object.stroke(
-            &self.dc,
-            &brush.handle(),
-            width,
-            stroke_style.map(|ss| ss.handle()),
-        );
+        object.stroke(self.renderer, brush, width, stroke_style);
     }
 
     #[inline]
@@ -63,35 +94,18 @@ impl DrawCommand {
         src_rect: Option<Rect<f32>>,
         interpolation: Interpolation,
     ) {
-        let dest: D2D_RECT_F = Wrapper(dest_rect.into()).into();
-        let src: Option<D2D_RECT_F> = src_rect.map(|src| Wrapper(src).into());
-        unsafe {
-            self.dc.DrawBitmap2(
-                image.handle(),
-                Some(&dest),
-                1.0,
-                D2D1_INTERPOLATION_MODE(interpolation as _),
-                src.as_ref().map(|src| src as _),
-                None,
-            );
-        }
+        self.renderer
+            .draw_image(image, dest_rect.into(), src_rect, interpolation);
     }
 
     #[inline]
     pub fn push_clip(&self, rect: impl Into<Rect<f32>>) {
-        unsafe {
-            self.dc.PushAxisAlignedClip(
-                &Wrapper(rect.into()).into(),
-                D2D1_ANTIALIAS_MODE_PER_PRIMITIVE,
-            );
-        }
+        self.renderer.push_clip(rect.into());
     }
 
     #[inline]
     pub fn pop_clip(&self) {
-        unsafe {
-            self.dc.PopAxisAlignedClip();
-        }
+        self.renderer.pop_clip();
     }
 
     #[inline]
@@ -107,45 +121,72 @@ impl DrawCommand {
 
     #[inline]
     pub fn translate(&self, point: impl Into<Point<f32>>) {
-        let point = point.into();
-
-        let mut old_transform = Matrix3x2::identity();
-        unsafe { self.dc.GetTransform(&mut old_transform) }
+        self.renderer.translate(point.into());
+    }
 
-        let transform = Matrix3x2::translation(point.x, point.y) * old_transform;
+    #[inline]
+    pub fn scale(&self, size: impl Into<Size<f32>>) {
+        self.renderer.scale(size.into());
+    }
 
-        unsafe {
-            self.dc.SetTransform(&transform);
-        }
+    #[inline]
+    pub fn rotate(&self, angle: f32, center: impl Into<Point<f32>>) {
+        self.concat(&Transform::rotation(angle, center));
     }
 
     #[inline]
-    pub fn scale(&self, size: impl Into<Size<f32>>) {
-        let size = size.into();
+    pub fn skew(&self, angle_x: f32, angle_y: f32, center: impl Into<Point<f32>>) {
+        self.concat(&Transform::skew(angle_x, angle_y, center));
+    }
 
-        let mut old_transform = Matrix3x2::identity();
-        unsafe { self.dc.GetTransform(&mut old_transform) }
+    #[inline]
+    pub fn concat(&self, transform: &Transform) {
+        self.renderer.concat(transform);
+    }
 
-        let transform = Matrix3x2 {
-            M11: size.width,
-            M22: size.height,
-            ..Default::default()
-        } * old_transform;
+    #[inline]
+    pub fn set_transform(&self, transform: &Transform) {
+        self.renderer.set_transform(transform);
+    }
 
-        unsafe {
-            self.dc.SetTransform(&transform);
-        }
+    #[inline]
+    pub fn transform(&self) -> Transform {
+        self.renderer.transform()
     }
 
+    #[inline]
     pub fn reset_transform(&self) {
-        unsafe {
-            self.dc.SetTransform(&Matrix3x2::identity());
+        self.renderer.reset_transform();
+    }
+
+    #[inline]
+    pub fn push_transform(&self, transform: &Transform) {
+        self.transforms.borrow_mut().push(self.renderer.transform());
+        self.renderer.concat(transform);
+    }
+
+    #[inline]
+    pub fn pop_transform(&self) {
+        let saved = self.transforms.borrow_mut().pop();
+        match saved {
+            Some(transform) => self.renderer.set_transform(&transform),
+            None => self.unmatched_pops.set(self.unmatched_pops.get() + 1),
         }
     }
+
+    #[inline]
+    pub fn with_transform<F, R>(&self, transform: &Transform, f: F) -> R
+    where
+        F: FnOnce(&Self) -> R,
+    {
+        self.push_transform(transform);
+        let ret = f(self);
+        self.pop_transform();
+        ret
+    }
 }
 
 pub trait Target {
-    fn bitmap(&self) -> &ID2D1Bitmap1;
     fn size(&self) -> Size<f32>;
     fn physical_size(&self) -> Size<u32>;
 }
@@ -153,55 +194,54 @@ pub trait Target {
 pub trait Backend {
     type RenderTarget: Target;
 
-    fn d2d1_factory(&self) -> &ID2D1Factory6;
-    fn d2d1_device(&self) -> &ID2D1Device5;
-    fn begin_draw(&self, target: &Self::RenderTarget);
-    fn end_draw(&self, target: &Self::RenderTarget, ret: Result<()>) -> Result<()>;
-}
-
-#[derive(Clone, Debug)]
-struct FontFileLoader {
-    factory: IDWriteFactory6,
-    loader: IDWriteInMemoryFontFileLoader,
-}
-
-impl Drop for FontFileLoader {
-    fn drop(&mut self) {
-        unsafe {
-            self.factory
-                .UnregisterFontFileLoader(&self.loader)
-                .unwrap_or(());
-        }
-    }
+    fn create_factory(&self) -> Factory;
+    fn set_dpi(&self, dpi: f32);
+    fn create_offscreen_target(
+        &self,
+        size: Size<u32>,
+        format: PixelFormat,
+        alpha_mode: AlphaMode,
+    ) -> Result<Self::RenderTarget>;
+    fn draw<R>(&self, target: &Self::RenderTarget, f: impl FnOnce(&dyn Renderer) -> R)
+        -> Result<R>;
+    fn read_pixels(&self, target: &Self::RenderTarget) -> Result<ImageData>;
 }
 
+#[cfg(windows)]
 pub struct LockGuard<'a> {
-    multithread: &'a ID2D1Multithread,
+    multithread: Option<&'a windows::Win32::Graphics::Direct2D::ID2D1Multithread>,
 }
 
+#[cfg(windows)]
 impl<'a> Drop for LockGuard<'a> {
     #[inline]
     fn drop(&mut self) {
-        unsafe {
-            self.multithread.Leave();
+        if let Some(multithread) = self.multithread {
+            unsafe {
+                multithread.Leave();
+            }
         }
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct Factory {
-    d2d1_factory: ID2D1Factory6,
-    d2d1_device_context: ID2D1DeviceContext5,
-    dwrite_factory: IDWriteFactory6,
-    wic_imaging_factory: IWICImagingFactory2,
-    font_loader: Arc<FontFileLoader>,
-    multithread: ID2D1Multithread,
+    #[cfg(windows)]
+    pub(crate) device: Option<d2d1::Device>,
+}
+
+impl Factory {
+    #[cfg(windows)]
+    #[inline]
+    fn device(&self) -> Result<&d2d1::Device> {
+        self.device.as_ref().ok_or(Error::Unsupported)
+    }
 }
 
 impl Factory {
     #[inline]
     pub fn create_solid_color_brush(&self, color: impl Into<Rgba<f32>>) -> Result<Brush> {
-        Brush::solid_color(&self.d2d1_device_context, color)
+        Ok(Brush::solid_color(color))
     }
 
     #[inline]
@@ -213,7 +253,7 @@ impl Factory {
     where
         T: Into<GradientStop> + Clone,
     {
-        GradientStopCollection::new(&self.d2d1_device_context, mode, stops)
+        Ok(GradientStopCollection::new(mode, stops))
     }
 
     #[inline]
@@ -223,7 +263,7 @@ impl Factory {
         end: impl Into<Point<f32>>,
         stops: &GradientStopCollection,
     ) -> Result<Brush> {
-        Brush::linear_gradient(&self.d2d1_device_context, start, end, stops)
+        Ok(Brush::linear_gradient(start, end, stops))
     }
 
     #[inline]
@@ -233,9 +273,10 @@ impl Factory {
         offset: impl Into<Point<f32>>,
         stops: &GradientStopCollection,
     ) -> Result<Brush> {
-        Brush::radial_gradient(&self.d2d1_device_context, ellipse, offset, stops)
+        Ok(Brush::radial_gradient(ellipse, offset, stops))
     }
 
+    #[cfg(windows)]
     #[inline]
     pub fn create_text_format(
         &self,
@@ -244,9 +285,10 @@ impl Factory {
         style: Option<&TextStyle>,
         locale: Option<&str>,
     ) -> Result<TextFormat> {
+        let device = self.device()?;
         TextFormat::new(
-            &self.dwrite_factory,
-            &self.font_loader.loader,
+            &device.dwrite_factory,
+            &device.font_loader.loader,
             font,
             size.into(),
             style,
@@ -254,6 +296,7 @@ impl Factory {
         )
     }
 
+    #[cfg(windows)]
     #[inline]
     pub fn create_text_layout(
         &self,
@@ -262,98 +305,76 @@ impl Factory {
         alignment: TextAlignment,
         size: Option<Size<f32>>,
     ) -> Result<TextLayout> {
-        TextLayout::new(&self.dwrite_factory, text.as_ref(), format, alignment, size)
+        TextLayout::new(
+            &self.device()?.dwrite_factory,
+            text.as_ref(),
+            format,
+            alignment,
+            size,
+        )
     }
 
     #[inline]
     pub fn create_stroke_style(&self, props: &StrokeStyleProperties) -> Result<StrokeStyle> {
-        StrokeStyle::new(&self.d2d1_factory, props)
+        Ok(StrokeStyle::new(props))
+    }
+
+    #[inline]
+    pub fn create_image(&self, data: ImageData) -> Result<Image> {
+        Ok(Image::new(data))
     }
 
+    #[cfg(windows)]
     #[inline]
     pub fn create_image_from_file(&self, path: impl AsRef<std::path::Path>) -> Result<Image> {
-        Image::from_file(&self.d2d1_device_context, &self.wic_imaging_factory, path)
+        Image::from_file(&self.device()?.wic_imaging_factory, path)
     }
 
     #[inline]
     pub fn create_filled_path(&self) -> Result<PathBuilder<FilledPath>> {
-        let geometry = unsafe { self.d2d1_factory.CreatePathGeometry()? };
-        PathBuilder::new(geometry)
+        Ok(PathBuilder::new())
     }
 
     #[inline]
     pub fn create_hollow_path(&self) -> Result<PathBuilder<HollowPath>> {
-        let geometry = unsafe { self.d2d1_factory.CreatePathGeometry()? };
-        PathBuilder::new(geometry)
+        Ok(PathBuilder::new())
     }
 
+    #[cfg(windows)]
     #[inline]
-    pub fn lock(&self) -> LockGuard {
-        unsafe {
-            self.multithread.Enter();
-        }
-        LockGuard {
-            multithread: &self.multithread,
+    pub fn lock(&self) -> LockGuard<'_> {
+        let multithread = self.device.as_ref().map(|device| &device.multithread);
+        if let Some(multithread) = multithread {
+            unsafe {
+                multithread.Enter();
+            }
         }
+        LockGuard { multithread }
     }
 }
 
 #[derive(Debug)]
 pub struct Context<T> {
     pub(crate) backend: T,
-    pub(crate) d2d1_device_context: ID2D1DeviceContext5,
-    dwrite_factory: IDWriteFactory6,
-    wic_imaging_factory: IWICImagingFactory2,
-    font_loader: Arc<FontFileLoader>,
 }
 
 impl<T> Context<T>
 where
     T: Backend,
 {
+    #[inline]
     pub fn new(backend: T) -> Result<Self> {
-        unsafe {
-            let d2d1_device_context = backend
-                .d2d1_device()
-                .CreateDeviceContext6(D2D1_DEVICE_CONTEXT_OPTIONS_NONE)?;
-            let dwrite_factory: IDWriteFactory6 = DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED)?;
-            let wic_imaging_factory =
-                CoCreateInstance(&CLSID_WICImagingFactory2, None, CLSCTX_INPROC_SERVER)?;
-            let font_loader = {
-                let loader = dwrite_factory.CreateInMemoryFontFileLoader()?;
-                dwrite_factory.RegisterFontFileLoader(&loader)?;
-                Arc::new(FontFileLoader {
-                    factory: dwrite_factory.clone(),
-                    loader,
-                })
-            };
-            Ok(Self {
-                backend,
-                d2d1_device_context,
-                dwrite_factory,
-                wic_imaging_factory,
-                font_loader,
-            })
-        }
+        Ok(Self { backend })
     }
 
     #[inline]
     pub fn create_factory(&self) -> Factory {
-        Factory {
-            d2d1_factory: self.backend.d2d1_factory().clone(),
-            d2d1_device_context: self.d2d1_device_context.clone(),
-            dwrite_factory: self.dwrite_factory.clone(),
-            wic_imaging_factory: self.wic_imaging_factory.clone(),
-            font_loader: self.font_loader.clone(),
-            multithread: self.backend.d2d1_factory().cast().unwrap(),
-        }
+        self.backend.create_factory()
     }
 
     #[inline]
     pub fn set_dpi(&self, dpi: f32) {
-        unsafe {
-            self.d2d1_device_context.SetDpi(dpi, dpi);
-        }
+        self.backend.set_dpi(dpi);
     }
 
     #[inline]
@@ -361,22 +382,38 @@ where
         self.set_dpi(scale * 96.0)
     }
 
+    // An unbalanced transform stack is reported as `Error::UnbalancedTransform`, but the frame
+    // is still committed: outstanding pushes are undone before the backend finishes it.
     #[inline]
     pub fn draw<R>(
         &self,
         target: &T::RenderTarget,
         f: impl FnOnce(&DrawCommand) -> R,
     ) -> Result<R> {
-        let ctx = &self.d2d1_device_context;
-        unsafe {
-            self.backend.begin_draw(target);
-            ctx.SetTarget(target.bitmap());
-            ctx.BeginDraw();
-            let ret = f(&DrawCommand { dc: ctx.clone() });
-            let e = ctx.EndDraw(None, None);
-            ctx.SetTarget(None);
-            self.backend.end_draw(target, e.map_err(|e| e.into()))?;
-            Ok(ret)
+        let (ret, balanced) = self.backend.draw(target, |renderer| {
+            let cmd = DrawCommand::new(renderer);
+            let ret = f(&cmd);
+            (ret, cmd.finish())
+        })?;
+        if !balanced {
+            return Err(Error::UnbalancedTransform);
         }
+        Ok(ret)
+    }
+
+    #[inline]
+    pub fn create_offscreen_target(
+        &self,
+        size: impl Into<Size<u32>>,
+        format: PixelFormat,
+        alpha_mode: AlphaMode,
+    ) -> Result<T::RenderTarget> {
+        self.backend
+            .create_offscreen_target(size.into(), format, alpha_mode)
+    }
+
+    #[inline]
+    pub fn read_pixels(&self, target: &T::RenderTarget) -> Result<ImageData> {
+        self.backend.read_pixels(target)
     }
 }
@@ -1,11 +1,13 @@
 use crate::*;
 use windows::Win32::Foundation::*;
-use windows::Win32::Graphics::{Direct2D::*, Direct3D::*, Direct3D11::*, Dxgi::Common::*, Dxgi::*};
+use windows::Win32::Graphics::{Direct3D::*, Direct3D11::*, Dxgi::Common::*, Dxgi::*};
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct RenderTarget {
-    swap_chain: IDXGISwapChain1,
+    swap_chain: Option<IDXGISwapChain1>,
     render_target: Option<d3d11::RenderTarget>,
+    format: PixelFormat,
+    alpha_mode: AlphaMode,
     interval: u32,
 }
 
@@ -17,10 +19,6 @@ impl RenderTarget {
 }
 
 impl Target for RenderTarget {
-    fn bitmap(&self) -> &ID2D1Bitmap1 {
-        self.render_target.as_ref().unwrap().bitmap()
-    }
-
     fn size(&self) -> Size<f32> {
         self.render_target.as_ref().unwrap().size()
     }
@@ -98,10 +96,12 @@ impl Context<Direct2D> {
             let render_target = self
                 .backend
                 .d3d11
-                .create_render_target_from_swap_chain(&self.d2d1_device_context, &swap_chain)?;
+                .create_render_target_from_swap_chain(&swap_chain)?;
             Ok(RenderTarget {
-                swap_chain,
+                swap_chain: Some(swap_chain),
                 render_target: Some(render_target),
+                format: PixelFormat::Bgra8,
+                alpha_mode: AlphaMode::Premultiplied,
                 interval: 1,
             })
         }
@@ -114,16 +114,22 @@ impl Context<Direct2D> {
     ) -> Result<()> {
         let size = size.into();
         target.render_target = None;
+        let Some(swap_chain) = target.swap_chain.as_ref() else {
+            target.render_target = Some(self.backend.d3d11.create_offscreen_target(
+                size,
+                target.format,
+                target.alpha_mode,
+            )?);
+            return Ok(());
+        };
         unsafe {
-            target
-                .swap_chain
-                .ResizeBuffers(0, size.width, size.height, DXGI_FORMAT_UNKNOWN, 0)?;
+            swap_chain.ResizeBuffers(0, size.width, size.height, DXGI_FORMAT_UNKNOWN, 0)?;
         }
-        target.render_target =
-            Some(self.backend.d3d11.create_render_target_from_swap_chain(
-                &self.d2d1_device_context,
-                &target.swap_chain,
-            )?);
+        target.render_target = Some(
+            self.backend
+                .d3d11
+                .create_render_target_from_swap_chain(swap_chain)?,
+        );
         Ok(())
     }
 }
@@ -131,33 +137,53 @@ impl Context<Direct2D> {
 impl Backend for Direct2D {
     type RenderTarget = RenderTarget;
 
-    fn d2d1_device(&self) -> &ID2D1Device5 {
-        self.d3d11.d2d1_device()
+    #[inline]
+    fn create_factory(&self) -> Factory {
+        self.d3d11.create_factory()
     }
 
-    fn d2d1_factory(&self) -> &ID2D1Factory6 {
-        self.d3d11.d2d1_factory()
+    #[inline]
+    fn set_dpi(&self, dpi: f32) {
+        self.d3d11.set_dpi(dpi);
     }
 
-    fn begin_draw(&self, target: &Self::RenderTarget) {
-        self.d3d11
-            .begin_draw(target.render_target.as_ref().unwrap())
+    #[inline]
+    fn create_offscreen_target(
+        &self,
+        size: Size<u32>,
+        format: PixelFormat,
+        alpha_mode: AlphaMode,
+    ) -> Result<Self::RenderTarget> {
+        Ok(RenderTarget {
+            swap_chain: None,
+            render_target: Some(
+                self.d3d11
+                    .create_offscreen_target(size, format, alpha_mode)?,
+            ),
+            format,
+            alpha_mode,
+            interval: 1,
+        })
     }
 
-    fn end_draw(&self, target: &Self::RenderTarget, ret: Result<()>) -> Result<()> {
-        let ret = self
-            .d3d11
-            .end_draw(target.render_target.as_ref().unwrap(), ret);
-        match ret {
-            Ok(_) => unsafe {
+    fn draw<R>(
+        &self,
+        target: &Self::RenderTarget,
+        f: impl FnOnce(&dyn Renderer) -> R,
+    ) -> Result<R> {
+        let ret = self.d3d11.draw(target.render_target.as_ref().unwrap(), f)?;
+        if let Some(swap_chain) = target.swap_chain.as_ref() {
+            unsafe {
                 let params = DXGI_PRESENT_PARAMETERS::default();
-                target
-                    .swap_chain
-                    .Present1(target.interval, 0, &params)
-                    .ok()?;
-                Ok(())
-            },
-            Err(e) => Err(e),
+                swap_chain.Present1(target.interval, 0, &params).ok()?;
+            }
         }
+        Ok(ret)
+    }
+
+    #[inline]
+    fn read_pixels(&self, target: &Self::RenderTarget) -> Result<ImageData> {
+        self.d3d11
+            .read_pixels(target.render_target.as_ref().unwrap())
     }
 }
@@ -0,0 +1,852 @@
+use crate::*;
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+use windows::core::{IUnknown, Interface};
+use windows::Foundation::Numerics::Matrix3x2;
+use windows::Win32::Graphics::{
+    Direct2D::Common::*, Direct2D::*, DirectWrite::*, Dxgi::Common::*, Imaging::D2D::*, Imaging::*,
+};
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+
+#[derive(Clone, Copy, Debug)]
+#[repr(transparent)]
+pub(crate) struct Wrapper<T>(pub(crate) T);
+
+impl From<Wrapper<Rgba<f32>>> for D2D1_COLOR_F {
+    #[inline]
+    fn from(src: Wrapper<Rgba<f32>>) -> Self {
+        Self {
+            r: src.0.r,
+            g: src.0.g,
+            b: src.0.b,
+            a: src.0.a,
+        }
+    }
+}
+
+impl From<Wrapper<Point<f32>>> for D2D_POINT_2F {
+    #[inline]
+    fn from(src: Wrapper<Point<f32>>) -> Self {
+        Self {
+            x: src.0.x,
+            y: src.0.y,
+        }
+    }
+}
+
+impl From<Wrapper<D2D_POINT_2F>> for Point<f32> {
+    #[inline]
+    fn from(src: Wrapper<D2D_POINT_2F>) -> Self {
+        Self {
+            x: src.0.x,
+            y: src.0.y,
+        }
+    }
+}
+
+impl From<Wrapper<Size<f32>>> for D2D_SIZE_F {
+    #[inline]
+    fn from(src: Wrapper<Size<f32>>) -> Self {
+        Self {
+            width: src.0.width,
+            height: src.0.height,
+        }
+    }
+}
+
+impl From<Wrapper<D2D_SIZE_F>> for Size<f32> {
+    #[inline]
+    fn from(src: Wrapper<D2D_SIZE_F>) -> Self {
+        Self {
+            width: src.0.width,
+            height: src.0.height,
+        }
+    }
+}
+
+impl From<Wrapper<Size<u32>>> for D2D_SIZE_U {
+    #[inline]
+    fn from(src: Wrapper<Size<u32>>) -> Self {
+        Self {
+            width: src.0.width,
+            height: src.0.height,
+        }
+    }
+}
+
+impl From<Wrapper<D2D_SIZE_U>> for Size<u32> {
+    #[inline]
+    fn from(src: Wrapper<D2D_SIZE_U>) -> Self {
+        Self {
+            width: src.0.width,
+            height: src.0.height,
+        }
+    }
+}
+
+impl From<Wrapper<Vector<f32>>> for D2D_POINT_2F {
+    #[inline]
+    fn from(src: Wrapper<Vector<f32>>) -> Self {
+        Self {
+            x: src.0.x,
+            y: src.0.y,
+        }
+    }
+}
+
+impl From<Wrapper<Rect<f32>>> for D2D_RECT_F {
+    #[inline]
+    fn from(src: Wrapper<Rect<f32>>) -> Self {
+        let ep = src.0.endpoint();
+        Self {
+            left: src.0.origin.x,
+            top: src.0.origin.y,
+            right: ep.x,
+            bottom: ep.y,
+        }
+    }
+}
+
+impl From<Wrapper<Circle>> for D2D1_ELLIPSE {
+    #[inline]
+    fn from(src: Wrapper<Circle>) -> Self {
+        Self {
+            point: Wrapper(src.0.center).into(),
+            radiusX: src.0.radius,
+            radiusY: src.0.radius,
+        }
+    }
+}
+
+impl From<Wrapper<Ellipse>> for D2D1_ELLIPSE {
+    #[inline]
+    fn from(src: Wrapper<Ellipse>) -> Self {
+        Self {
+            point: Wrapper(src.0.center).into(),
+            radiusX: src.0.radius.x,
+            radiusY: src.0.radius.y,
+        }
+    }
+}
+
+impl From<Wrapper<RoundedRect>> for D2D1_ROUNDED_RECT {
+    #[inline]
+    fn from(src: Wrapper<RoundedRect>) -> Self {
+        Self {
+            rect: Wrapper(src.0.rect).into(),
+            radiusX: src.0.radius.x,
+            radiusY: src.0.radius.y,
+        }
+    }
+}
+
+impl From<GradientStop> for D2D1_GRADIENT_STOP {
+    #[inline]
+    fn from(src: GradientStop) -> Self {
+        Self {
+            position: src.position,
+            color: Wrapper(src.color).into(),
+        }
+    }
+}
+
+// A D2D resource cached together with the device context or factory that created it,
+// so that a value used with another device gets a resource from the right domain.
+#[derive(Clone, PartialEq, Debug)]
+struct Owner(IUnknown);
+
+// Only used for identity and released on drop; the factories are created multithreaded.
+unsafe impl Send for Owner {}
+unsafe impl Sync for Owner {}
+
+#[derive(Debug)]
+pub(crate) struct DeviceCache<T>(Mutex<Option<(Owner, T)>>);
+
+impl<T> Default for DeviceCache<T> {
+    #[inline]
+    fn default() -> Self {
+        Self(Mutex::new(None))
+    }
+}
+
+impl<T: Clone> Clone for DeviceCache<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(Mutex::new(
+            self.0.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        ))
+    }
+}
+
+fn cached<T: Clone>(
+    cache: &DeviceCache<T>,
+    owner: &impl Interface,
+    f: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    let owner = Owner(owner.cast::<IUnknown>()?);
+    let mut cache = cache.0.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((key, value)) = cache.as_ref() {
+        if *key == owner {
+            return Ok(value.clone());
+        }
+    }
+    let value = f()?;
+    *cache = Some((owner, value.clone()));
+    Ok(value)
+}
+
+impl From<Transform> for Matrix3x2 {
+    #[inline]
+    fn from(src: Transform) -> Self {
+        Self {
+            M11: src.m11,
+            M12: src.m12,
+            M21: src.m21,
+            M22: src.m22,
+            M31: src.m31,
+            M32: src.m32,
+        }
+    }
+}
+
+impl From<Matrix3x2> for Transform {
+    #[inline]
+    fn from(src: Matrix3x2) -> Self {
+        Self {
+            m11: src.M11,
+            m12: src.M12,
+            m21: src.M21,
+            m22: src.M22,
+            m31: src.M31,
+            m32: src.M32,
+        }
+    }
+}
+
+impl From<PixelFormat> for DXGI_FORMAT {
+    #[inline]
+    fn from(src: PixelFormat) -> Self {
+        match src {
+            PixelFormat::Rgba8 => DXGI_FORMAT_R8G8B8A8_UNORM,
+            PixelFormat::Bgra8 => DXGI_FORMAT_B8G8R8A8_UNORM,
+            PixelFormat::Rgba16F => DXGI_FORMAT_R16G16B16A16_FLOAT,
+            PixelFormat::A8 => DXGI_FORMAT_A8_UNORM,
+        }
+    }
+}
+
+// Direct2D can only draw into color bitmaps with premultiplied (or ignored) alpha, so straight
+// targets are rendered premultiplied and converted in `read_pixels`. A8 targets have no color to
+// premultiply and are created straight.
+fn target_alpha_mode(format: PixelFormat, alpha_mode: AlphaMode) -> D2D1_ALPHA_MODE {
+    match (format, alpha_mode) {
+        (PixelFormat::A8, _) => D2D1_ALPHA_MODE_STRAIGHT,
+        (_, AlphaMode::Premultiplied | AlphaMode::Straight) => D2D1_ALPHA_MODE_PREMULTIPLIED,
+    }
+}
+
+impl GradientStopCollection {
+    fn handle(&self, dc: &ID2D1DeviceContext5) -> Result<ID2D1GradientStopCollection> {
+        cached(&self.handle, dc, || {
+            let stops = self
+                .stops
+                .iter()
+                .map(|&stop| stop.into())
+                .collect::<Vec<_>>();
+            unsafe {
+                Ok(dc.CreateGradientStopCollection(
+                    &stops,
+                    D2D1_GAMMA_2_2,
+                    D2D1_EXTEND_MODE(self.mode as u32),
+                )?)
+            }
+        })
+    }
+}
+
+impl Brush {
+    fn handle(&self, dc: &ID2D1DeviceContext5) -> Result<ID2D1Brush> {
+        match self {
+            Self::SolidColor(b) => cached(&b.handle, dc, || unsafe {
+                let color = Wrapper(b.color).into();
+                Ok(dc.CreateSolidColorBrush(&color, None)?.into())
+            }),
+            Self::LinearGradient(b) => cached(&b.handle, dc, || unsafe {
+                Ok(dc
+                    .CreateLinearGradientBrush(
+                        &D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES {
+                            startPoint: Wrapper(b.start).into(),
+                            endPoint: Wrapper(b.end).into(),
+                        },
+                        None,
+                        &b.stops.handle(dc)?,
+                    )?
+                    .into())
+            }),
+            Self::RadialGradient(b) => cached(&b.handle, dc, || unsafe {
+                Ok(dc
+                    .CreateRadialGradientBrush(
+                        &D2D1_RADIAL_GRADIENT_BRUSH_PROPERTIES {
+                            center: Wrapper(b.ellipse.center).into(),
+                            radiusX: b.ellipse.radius.x,
+                            radiusY: b.ellipse.radius.y,
+                            gradientOriginOffset: Wrapper(b.offset).into(),
+                        },
+                        None,
+                        &b.stops.handle(dc)?,
+                    )?
+                    .into())
+            }),
+        }
+    }
+}
+
+impl StrokeStyle {
+    fn handle(&self, factory: &ID2D1Factory) -> Result<ID2D1StrokeStyle> {
+        cached(&self.handle, factory, || {
+            let props = self.properties();
+            let line_join = match props.line_join {
+                LineJoin::Miter => D2D1_LINE_JOIN_MITER,
+                LineJoin::Bevel => D2D1_LINE_JOIN_BEVEL,
+                LineJoin::Round => D2D1_LINE_JOIN_ROUND,
+                LineJoin::MiterOrBevel(_) => D2D1_LINE_JOIN_MITER_OR_BEVEL,
+            };
+            let (dash_cap, dash_style, dash_offset, dashes) = match props.dash.as_ref() {
+                Some(dash) => {
+                    let cap = D2D1_CAP_STYLE(dash.cap as u32);
+                    let (style, dashes): (_, Option<&[f32]>) = match dash.style {
+                        DashStyle::Solid => (D2D1_DASH_STYLE_SOLID, None),
+                        DashStyle::Dash => (D2D1_DASH_STYLE_DASH, None),
+                        DashStyle::Dot => (D2D1_DASH_STYLE_DOT, None),
+                        DashStyle::DashDot => (D2D1_DASH_STYLE_DASH_DOT, None),
+                        DashStyle::DashDotDot => (D2D1_DASH_STYLE_DASH_DOT_DOT, None),
+                        DashStyle::Custom(dashes) => (D2D1_DASH_STYLE_CUSTOM, Some(dashes)),
+                    };
+                    (cap, style, dash.offset, dashes)
+                }
+                None => (D2D1_CAP_STYLE_FLAT, D2D1_DASH_STYLE_SOLID, 0.0, None),
+            };
+            let props = D2D1_STROKE_STYLE_PROPERTIES {
+                startCap: D2D1_CAP_STYLE(props.start_cap as _),
+                endCap: D2D1_CAP_STYLE(props.end_cap as _),
+                dashCap: dash_cap,
+                lineJoin: line_join,
+                miterLimit: props.line_join.miter_limit(),
+                dashStyle: dash_style,
+                dashOffset: dash_offset,
+            };
+            unsafe { Ok(factory.CreateStrokeStyle(&props, dashes)?) }
+        })
+    }
+}
+
+impl Image {
+    fn handle(&self, dc: &ID2D1DeviceContext5) -> Result<ID2D1Bitmap1> {
+        cached(&self.0.bitmap, dc, || {
+            let data = self
+                .data()
+                .convert(PixelFormat::Bgra8, AlphaMode::Premultiplied);
+            unsafe {
+                Ok(dc.CreateBitmap2(
+                    Wrapper(self.size()).into(),
+                    Some(data.bytes.as_ptr() as _),
+                    data.stride,
+                    &D2D1_BITMAP_PROPERTIES1 {
+                        pixelFormat: D2D1_PIXEL_FORMAT {
+                            format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                            alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+                        },
+                        dpiX: 96.0,
+                        dpiY: 96.0,
+                        ..Default::default()
+                    },
+                )?)
+            }
+        })
+    }
+}
+
+impl PathGeometry {
+    fn handle(
+        &self,
+        factory: &ID2D1Factory,
+        begin: D2D1_FIGURE_BEGIN,
+    ) -> Result<ID2D1PathGeometry> {
+        cached(&self.geometry, factory, || unsafe {
+            let geometry = factory.CreatePathGeometry()?;
+            let sink = geometry.Open()?;
+            sink.SetFillMode(D2D1_FILL_MODE(self.data.fill_rule() as _));
+            for figure in &self.data {
+                sink.BeginFigure(Wrapper(figure.begin).into(), begin);
+                for segment in &figure.segments {
+                    match segment {
+                        PathSegment::Line(point) => sink.AddLine(Wrapper(*point).into()),
+                        PathSegment::QuadraticBezier(segment) => {
+                            sink.AddQuadraticBezier(&D2D1_QUADRATIC_BEZIER_SEGMENT {
+                                point1: Wrapper(segment.ctrl).into(),
+                                point2: Wrapper(segment.to).into(),
+                            })
+                        }
+                        PathSegment::CubicBezier(segment) => sink.AddBezier(&D2D1_BEZIER_SEGMENT {
+                            point1: Wrapper(segment.c0).into(),
+                            point2: Wrapper(segment.c1).into(),
+                            point3: Wrapper(segment.to).into(),
+                        }),
+                        PathSegment::Arc(segment) => sink.AddArc(&D2D1_ARC_SEGMENT {
+                            point: Wrapper(segment.to).into(),
+                            size: D2D_SIZE_F {
+                                width: segment.radius.x,
+                                height: segment.radius.y,
+                            },
+                            rotationAngle: segment.rotation,
+                            sweepDirection: D2D1_SWEEP_DIRECTION(segment.sweep as _),
+                            arcSize: if segment.large_arc {
+                                D2D1_ARC_SIZE_LARGE
+                            } else {
+                                D2D1_ARC_SIZE_SMALL
+                            },
+                        }),
+                    }
+                }
+                sink.EndFigure(D2D1_FIGURE_END(figure.end as _));
+            }
+            sink.Close()?;
+            Ok(geometry)
+        })
+    }
+}
+
+impl FilledPath {
+    #[inline]
+    fn handle(&self, factory: &ID2D1Factory) -> Result<ID2D1PathGeometry> {
+        self.0.handle(factory, D2D1_FIGURE_BEGIN_FILLED)
+    }
+}
+
+impl HollowPath {
+    #[inline]
+    fn handle(&self, factory: &ID2D1Factory) -> Result<ID2D1PathGeometry> {
+        self.0.handle(factory, D2D1_FIGURE_BEGIN_HOLLOW)
+    }
+}
+
+#[inline]
+fn get_factory(dc: &ID2D1DeviceContext5) -> Option<ID2D1Factory> {
+    let mut factory = None;
+    unsafe {
+        dc.GetFactory(&mut factory);
+    }
+    factory
+}
+
+pub(crate) struct D2DRenderer<'a> {
+    dc: &'a ID2D1DeviceContext5,
+    error: RefCell<Option<Error>>,
+}
+
+impl<'a> D2DRenderer<'a> {
+    #[inline]
+    fn new(dc: &'a ID2D1DeviceContext5) -> Self {
+        Self {
+            dc,
+            error: RefCell::new(None),
+        }
+    }
+
+    fn check<T>(&self, result: Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.error.borrow_mut().get_or_insert(e);
+                None
+            }
+        }
+    }
+
+    fn factory(&self) -> Option<ID2D1Factory> {
+        let factory = get_factory(self.dc);
+        if factory.is_none() {
+            self.error.borrow_mut().get_or_insert(Error::Unsupported);
+        }
+        factory
+    }
+
+    #[inline]
+    fn finish(self) -> Option<Error> {
+        self.error.into_inner()
+    }
+}
+
+impl std::ops::Deref for D2DRenderer<'_> {
+    type Target = ID2D1DeviceContext5;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.dc
+    }
+}
+
+impl Renderer for D2DRenderer<'_> {
+    #[inline]
+    fn clear(&self, color: Rgba<f32>) {
+        unsafe {
+            let color = Wrapper(color).into();
+            self.Clear(Some(&color));
+        }
+    }
+
+    fn fill(&self, shape: &Shape, brush: &Brush) {
+        let Some(brush) = self.check(brush.handle(self)) else {
+            return;
+        };
+        unsafe {
+            match shape {
+                Shape::Rect(rect) => self.FillRectangle(&Wrapper(*rect).into(), &brush),
+                Shape::Circle(circle) => self.FillEllipse(&Wrapper(*circle).into(), &brush),
+                Shape::Ellipse(ellipse) => self.FillEllipse(&Wrapper(*ellipse).into(), &brush),
+                Shape::RoundedRect(rect) => {
+                    self.FillRoundedRectangle(&Wrapper(*rect).into(), &brush)
+                }
+                Shape::FilledPath(path) => {
+                    let Some(factory) = self.factory() else {
+                        return;
+                    };
+                    let Some(geometry) = self.check(path.handle(&factory)) else {
+                        return;
+                    };
+                    self.FillGeometry(&geometry, &brush, None);
+                }
+                Shape::Line(_) | Shape::HollowPath(_) => {}
+            }
+        }
+    }
+
+    fn stroke(&self, shape: &Shape, brush: &Brush, width: f32, style: Option<&StrokeStyle>) {
+        let Some(brush) = self.check(brush.handle(self)) else {
+            return;
+        };
+        let Some(factory) = self.factory() else {
+            return;
+        };
+        let Some(style) = self.check(style.map(|style| style.handle(&factory)).transpose()) else {
+            return;
+        };
+        let style = style.as_ref();
+        unsafe {
+            match shape {
+                Shape::Rect(rect) => {
+                    self.DrawRectangle(&Wrapper(*rect).into(), &brush, width, style)
+                }
+                Shape::Circle(circle) => {
+                    self.DrawEllipse(&Wrapper(*circle).into(), &brush, width, style)
+                }
+                Shape::Ellipse(ellipse) => {
+                    self.DrawEllipse(&Wrapper(*ellipse).into(), &brush, width, style)
+                }
+                Shape::RoundedRect(rect) => {
+                    self.DrawRoundedRectangle(&Wrapper(*rect).into(), &brush, width, style)
+                }
+                Shape::Line(line) => self.DrawLine(
+                    Wrapper(line.0).into(),
+                    Wrapper(line.1).into(),
+                    &brush,
+                    width,
+                    style,
+                ),
+                Shape::FilledPath(path) => {
+                    let Some(geometry) = self.check(path.handle(&factory)) else {
+                        return;
+                    };
+                    self.DrawGeometry(&geometry, &brush, width, style);
+                }
+                Shape::HollowPath(path) => {
+                    let Some(geometry) = self.check(path.handle(&factory)) else {
+                        return;
+                    };
+                    self.DrawGeometry(&geometry, &brush, width, style);
+                }
+            }
+        }
+    }
+
+    fn draw_text(&self, layout: &TextLayout, origin: Point<f32>, brush: &Brush) {
+        let Some(brush) = self.check(brush.handle(self)) else {
+            return;
+        };
+        unsafe {
+            self.DrawTextLayout(
+                Wrapper(origin).into(),
+                layout.handle(),
+                &brush,
+                D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT | D2D1_DRAW_TEXT_OPTIONS_CLIP,
+            );
+        }
+    }
+
+    fn draw_image(
+        &self,
+        image: &Image,
+        dest_rect: Rect<f32>,
+        src_rect: Option<Rect<f32>>,
+        interpolation: Interpolation,
+    ) {
+        let Some(bitmap) = self.check(image.handle(self)) else {
+            return;
+        };
+        let dest: D2D_RECT_F = Wrapper(dest_rect).into();
+        let src: Option<D2D_RECT_F> = src_rect.map(|src| Wrapper(src).into());
+        unsafe {
+            self.DrawBitmap2(
+                &bitmap,
+                Some(&dest),
+                1.0,
+                D2D1_INTERPOLATION_MODE(interpolation as _),
+                src.as_ref().map(|src| src as _),
+                None,
+            );
+        }
+    }
+
+    #[inline]
+    fn push_clip(&self, rect: Rect<f32>) {
+        unsafe {
+            self.PushAxisAlignedClip(&Wrapper(rect).into(), D2D1_ANTIALIAS_MODE_PER_PRIMITIVE);
+        }
+    }
+
+    #[inline]
+    fn pop_clip(&self) {
+        unsafe {
+            self.PopAxisAlignedClip();
+        }
+    }
+
+    fn translate(&self, point: Point<f32>) {
+        let mut old_transform = Matrix3x2::identity();
+        unsafe { self.GetTransform(&mut old_transform) }
+
+        let transform = Matrix3x2::translation(point.x, point.y) * old_transform;
+
+        unsafe {
+            self.SetTransform(&transform);
+        }
+    }
+
+    fn scale(&self, size: Size<f32>) {
+        let mut old_transform = Matrix3x2::identity();
+        unsafe { self.GetTransform(&mut old_transform) }
+
+        let transform = Matrix3x2 {
+            M11: size.width,
+            M22: size.height,
+            ..Default::default()
+        } * old_transform;
+
+        unsafe {
+            self.SetTransform(&transform);
+        }
+    }
+
+    fn concat(&self, transform: &Transform) {
+        let mut old_transform = Matrix3x2::identity();
+        unsafe { self.GetTransform(&mut old_transform) }
+
+        let transform = Matrix3x2::from(*transform) * old_transform;
+
+        unsafe {
+            self.SetTransform(&transform);
+        }
+    }
+
+    #[inline]
+    fn set_transform(&self, transform: &Transform) {
+        unsafe {
+            self.SetTransform(&(*transform).into());
+        }
+    }
+
+    #[inline]
+    fn transform(&self) -> Transform {
+        let mut transform = Matrix3x2::identity();
+        unsafe { self.GetTransform(&mut transform) }
+        transform.into()
+    }
+
+    #[inline]
+    fn reset_transform(&self) {
+        unsafe {
+            self.SetTransform(&Matrix3x2::identity());
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct FontFileLoader {
+    factory: IDWriteFactory6,
+    pub(crate) loader: IDWriteInMemoryFontFileLoader,
+}
+
+impl Drop for FontFileLoader {
+    fn drop(&mut self) {
+        unsafe {
+            self.factory
+                .UnregisterFontFileLoader(&self.loader)
+                .unwrap_or(());
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Device {
+    pub(crate) d2d1_device_context: ID2D1DeviceContext5,
+    pub(crate) dwrite_factory: IDWriteFactory6,
+    pub(crate) wic_imaging_factory: IWICImagingFactory2,
+    pub(crate) font_loader: Arc<FontFileLoader>,
+    pub(crate) multithread: ID2D1Multithread,
+}
+
+impl Device {
+    pub(crate) fn new(d2d1_factory: &ID2D1Factory6, d2d1_device: &ID2D1Device5) -> Result<Self> {
+        unsafe {
+            let d2d1_device_context =
+                d2d1_device.CreateDeviceContext6(D2D1_DEVICE_CONTEXT_OPTIONS_NONE)?;
+            let dwrite_factory: IDWriteFactory6 = DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED)?;
+            let wic_imaging_factory =
+                CoCreateInstance(&CLSID_WICImagingFactory2, None, CLSCTX_INPROC_SERVER)?;
+            let font_loader = {
+                let loader = dwrite_factory.CreateInMemoryFontFileLoader()?;
+                dwrite_factory.RegisterFontFileLoader(&loader)?;
+                Arc::new(FontFileLoader {
+                    factory: dwrite_factory.clone(),
+                    loader,
+                })
+            };
+            Ok(Self {
+                d2d1_device_context,
+                dwrite_factory,
+                wic_imaging_factory,
+                font_loader,
+                multithread: d2d1_factory.cast()?,
+            })
+        }
+    }
+
+    #[inline]
+    pub(crate) fn create_factory(&self) -> Factory {
+        Factory {
+            device: Some(self.clone()),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn set_dpi(&self, dpi: f32) {
+        unsafe {
+            self.d2d1_device_context.SetDpi(dpi, dpi);
+        }
+    }
+
+    pub(crate) fn draw<R>(
+        &self,
+        bitmap: &ID2D1Bitmap1,
+        f: impl FnOnce(&dyn Renderer) -> R,
+    ) -> Result<R> {
+        let ctx = &self.d2d1_device_context;
+        unsafe {
+            ctx.SetTarget(bitmap);
+            ctx.BeginDraw();
+            let renderer = D2DRenderer::new(ctx);
+            let ret = f(&renderer);
+            let error = renderer.finish();
+            let e = ctx.EndDraw(None, None);
+            ctx.SetTarget(None);
+            if let Some(error) = error {
+                return Err(error);
+            }
+            e?;
+            Ok(ret)
+        }
+    }
+
+    pub(crate) fn create_offscreen_bitmap(
+        &self,
+        size: Size<u32>,
+        format: PixelFormat,
+        alpha_mode: AlphaMode,
+    ) -> Result<ID2D1Bitmap1> {
+        unsafe {
+            Ok(self.d2d1_device_context.CreateBitmap2(
+                D2D_SIZE_U {
+                    width: size.width,
+                    height: size.height,
+                },
+                None,
+                0,
+                &D2D1_BITMAP_PROPERTIES1 {
+                    pixelFormat: D2D1_PIXEL_FORMAT {
+                        format: format.into(),
+                        alphaMode: target_alpha_mode(format, alpha_mode),
+                    },
+                    dpiX: 96.0,
+                    dpiY: 96.0,
+                    bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET,
+                    ..Default::default()
+                },
+            )?)
+        }
+    }
+
+    pub(crate) fn read_pixels(
+        &self,
+        bitmap: &ID2D1Bitmap1,
+        alpha_mode: AlphaMode,
+    ) -> Result<ImageData> {
+        unsafe {
+            let size = bitmap.GetPixelSize();
+            let pixel_format = bitmap.GetPixelFormat();
+            let format = match pixel_format.format {
+                DXGI_FORMAT_R8G8B8A8_UNORM | DXGI_FORMAT_R8G8B8A8_UNORM_SRGB => PixelFormat::Rgba8,
+                DXGI_FORMAT_B8G8R8A8_UNORM | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB => PixelFormat::Bgra8,
+                DXGI_FORMAT_R16G16B16A16_FLOAT => PixelFormat::Rgba16F,
+                DXGI_FORMAT_A8_UNORM => PixelFormat::A8,
+                _ => return Err(Error::Unsupported),
+            };
+            let mut dpi_x = 0.0;
+            let mut dpi_y = 0.0;
+            bitmap.GetDpi(&mut dpi_x, &mut dpi_y);
+            let staging = self.d2d1_device_context.CreateBitmap2(
+                size,
+                None,
+                0,
+                &D2D1_BITMAP_PROPERTIES1 {
+                    pixelFormat: pixel_format,
+                    dpiX: dpi_x,
+                    dpiY: dpi_y,
+                    bitmapOptions: D2D1_BITMAP_OPTIONS_CPU_READ | D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
+                    ..Default::default()
+                },
+            )?;
+            staging.CopyFromBitmap(None, bitmap, None)?;
+            let mapped = staging.Map(D2D1_MAP_OPTIONS_READ)?;
+            let stride = size.width * format.bytes_per_pixel();
+            let mut bytes = Vec::with_capacity((stride * size.height) as usize);
+            for y in 0..size.height as usize {
+                let row = mapped.bits.add(y * mapped.pitch as usize);
+                bytes.extend_from_slice(std::slice::from_raw_parts(row, stride as usize));
+            }
+            staging.Unmap()?;
+            let data = ImageData {
+                width: size.width,
+                height: size.height,
+                stride,
+                format,
+                alpha_mode: if pixel_format.alphaMode == D2D1_ALPHA_MODE_STRAIGHT {
+                    AlphaMode::Straight
+                } else {
+                    AlphaMode::Premultiplied
+                },
+                bytes,
+            };
+            Ok(data.convert(format, alpha_mode))
+        }
+    }
+}
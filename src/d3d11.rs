@@ -1,15 +1,12 @@
+use crate::d2d1::Wrapper;
 use crate::*;
 use windows::core::Interface;
 use windows::Win32::Graphics::{Direct2D::Common::*, Direct2D::*, Direct3D11::*, Dxgi::*};
 
 #[derive(Clone, PartialEq, Eq)]
-pub struct RenderTarget(pub(crate) ID2D1Bitmap1);
+pub struct RenderTarget(pub(crate) ID2D1Bitmap1, AlphaMode);
 
 impl Target for RenderTarget {
-    fn bitmap(&self) -> &ID2D1Bitmap1 {
-        &self.0
-    }
-
     fn size(&self) -> Size<f32> {
         unsafe { Wrapper(self.0.GetSize()).into() }
     }
@@ -21,8 +18,7 @@ impl Target for RenderTarget {
 
 #[derive(Clone, Debug)]
 pub struct Direct3D11 {
-    d2d1_factory: ID2D1Factory6,
-    d2d1_device: ID2D1Device5,
+    device: d2d1::Device,
 }
 
 impl Direct3D11 {
@@ -35,19 +31,17 @@ impl Direct3D11 {
             let d2d1_factory =
                 D2D1CreateFactory::<ID2D1Factory6>(D2D1_FACTORY_TYPE_MULTI_THREADED, None)?;
             let dxgi_device: IDXGIDevice = d3d11_device.cast()?;
-            let d2d1_device = d2d1_factory.CreateDevice(&dxgi_device)?.cast()?;
-            Ok(Self {
-                d2d1_factory,
-                d2d1_device,
-            })
+            let d2d1_device: ID2D1Device5 = d2d1_factory.CreateDevice(&dxgi_device)?.cast()?;
+            let device = d2d1::Device::new(&d2d1_factory, &d2d1_device)?;
+            Ok(Self { device })
         }
     }
 
     pub(crate) fn create_render_target_from_swap_chain(
         &self,
-        ctx: &ID2D1DeviceContext5,
         swap_chain: &IDXGISwapChain1,
     ) -> Result<RenderTarget> {
+        let ctx = &self.device.d2d1_device_context;
         unsafe {
             let swap_chain: IDXGISwapChain1 =
                 swap_chain.cast().expect("cannot cast to IDXGISwapChain1");
@@ -58,7 +52,7 @@ impl Direct3D11 {
                 Some(&D2D1_BITMAP_PROPERTIES1 {
                     pixelFormat: D2D1_PIXEL_FORMAT {
                         format: desc.Format,
-                        alphaMode: D2D1_ALPHA_MODE_IGNORE,
+                        alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
                     },
                     bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET | D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
                     dpiX: 96.0,
@@ -66,7 +60,7 @@ impl Direct3D11 {
                     ..Default::default()
                 }),
             )?;
-            Ok(RenderTarget(bitmap))
+            Ok(RenderTarget(bitmap, AlphaMode::Premultiplied))
         }
     }
 }
@@ -79,7 +73,7 @@ impl Context<Direct3D11> {
     {
         let swap_chain = swap_chain.cast().expect("cannot cast to IDXGISwapChain1");
         self.backend
-            .create_render_target_from_swap_chain(&self.d2d1_device_context, &swap_chain)
+            .create_render_target_from_swap_chain(&swap_chain)
     }
 
     #[inline]
@@ -92,18 +86,22 @@ impl Context<Direct3D11> {
             let mut desc = D3D11_TEXTURE2D_DESC::default();
             target.GetDesc(&mut desc);
             let surface: IDXGISurface = target.cast().unwrap();
-            let bitmap = self.d2d1_device_context.CreateBitmapFromDxgiSurface(
-                &surface,
-                Some(&D2D1_BITMAP_PROPERTIES1 {
-                    pixelFormat: D2D1_PIXEL_FORMAT {
-                        format: desc.Format,
-                        alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
-                    },
-                    bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET | D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
-                    ..Default::default()
-                }),
-            )?;
-            Ok(RenderTarget(bitmap))
+            let bitmap = self
+                .backend
+                .device
+                .d2d1_device_context
+                .CreateBitmapFromDxgiSurface(
+                    &surface,
+                    Some(&D2D1_BITMAP_PROPERTIES1 {
+                        pixelFormat: D2D1_PIXEL_FORMAT {
+                            format: desc.Format,
+                            alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+                        },
+                        bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET | D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
+                        ..Default::default()
+                    }),
+                )?;
+            Ok(RenderTarget(bitmap, AlphaMode::Premultiplied))
         }
     }
 }
@@ -111,17 +109,41 @@ impl Context<Direct3D11> {
 impl Backend for Direct3D11 {
     type RenderTarget = RenderTarget;
 
-    fn d2d1_factory(&self) -> &ID2D1Factory6 {
-        &self.d2d1_factory
+    #[inline]
+    fn create_factory(&self) -> Factory {
+        self.device.create_factory()
     }
 
-    fn d2d1_device(&self) -> &ID2D1Device5 {
-        &self.d2d1_device
+    #[inline]
+    fn set_dpi(&self, dpi: f32) {
+        self.device.set_dpi(dpi);
     }
 
-    fn begin_draw(&self, _target: &Self::RenderTarget) {}
+    #[inline]
+    fn create_offscreen_target(
+        &self,
+        size: Size<u32>,
+        format: PixelFormat,
+        alpha_mode: AlphaMode,
+    ) -> Result<Self::RenderTarget> {
+        Ok(RenderTarget(
+            self.device
+                .create_offscreen_bitmap(size, format, alpha_mode)?,
+            alpha_mode,
+        ))
+    }
 
-    fn end_draw(&self, _target: &Self::RenderTarget, ret: Result<()>) -> Result<()> {
-        ret
+    #[inline]
+    fn draw<R>(
+        &self,
+        target: &Self::RenderTarget,
+        f: impl FnOnce(&dyn Renderer) -> R,
+    ) -> Result<R> {
+        self.device.draw(&target.0, f)
+    }
+
+    #[inline]
+    fn read_pixels(&self, target: &Self::RenderTarget) -> Result<ImageData> {
+        self.device.read_pixels(&target.0, target.1)
     }
 }
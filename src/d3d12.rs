@@ -1,3 +1,4 @@
+use crate::d2d1::Wrapper;
 use crate::*;
 use windows::core::{IUnknown, Interface};
 use windows::Win32::Graphics::{
@@ -6,16 +7,12 @@ use windows::Win32::Graphics::{
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct RenderTarget {
-    wrapper: ID3D11Resource,
+    wrapper: Option<ID3D11Resource>,
     bitmap: ID2D1Bitmap1,
+    alpha_mode: AlphaMode,
 }
 
 impl Target for RenderTarget {
-    #[inline]
-    fn bitmap(&self) -> &ID2D1Bitmap1 {
-        &self.bitmap
-    }
-
     #[inline]
     fn size(&self) -> Size<f32> {
         unsafe { Wrapper(self.bitmap.GetSize()).into() }
@@ -30,8 +27,7 @@ impl Target for RenderTarget {
 #[derive(Clone, Debug)]
 pub struct Direct3D12 {
     d3d11on12_device: ID3D11On12Device,
-    d2d1_factory: ID2D1Factory6,
-    d2d1_device: ID2D1Device5,
+    device: d2d1::Device,
     d3d11_device_context: ID3D11DeviceContext,
 }
 
@@ -68,10 +64,10 @@ impl Direct3D12 {
                 D2D1CreateFactory::<ID2D1Factory6>(D2D1_FACTORY_TYPE_MULTI_THREADED, None)?;
             let dxgi_device = d3d11on12_device.cast::<IDXGIDevice>()?;
             let d2d1_device = d2d1_factory.CreateDevice6(&dxgi_device)?;
+            let device = d2d1::Device::new(&d2d1_factory, &d2d1_device)?;
             Ok(Self {
                 d3d11on12_device,
-                d2d1_factory,
-                d2d1_device,
+                device,
                 d3d11_device_context,
             })
         }
@@ -119,9 +115,15 @@ impl Context<Direct3D12> {
                 };
                 let surface = wrapper.cast::<IDXGISurface>()?;
                 let bitmap = self
+                    .backend
+                    .device
                     .d2d1_device_context
                     .CreateBitmapFromDxgiSurface(&surface, Some(&bmp_props))?;
-                targets.push(RenderTarget { wrapper, bitmap });
+                targets.push(RenderTarget {
+                    wrapper: Some(wrapper),
+                    bitmap,
+                    alpha_mode: AlphaMode::Premultiplied,
+                });
             }
             Ok(targets)
         }
@@ -154,18 +156,26 @@ impl Context<Direct3D12> {
                     .map(|_| wrapper.unwrap())?
             };
             let surface = wrapper.cast::<IDXGISurface>()?;
-            let bitmap = self.d2d1_device_context.CreateBitmapFromDxgiSurface(
-                &surface,
-                Some(&D2D1_BITMAP_PROPERTIES1 {
-                    pixelFormat: D2D1_PIXEL_FORMAT {
-                        format: desc.Format,
-                        alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
-                    },
-                    bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET | D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
-                    ..Default::default()
-                }),
-            )?;
-            Ok(RenderTarget { wrapper, bitmap })
+            let bitmap = self
+                .backend
+                .device
+                .d2d1_device_context
+                .CreateBitmapFromDxgiSurface(
+                    &surface,
+                    Some(&D2D1_BITMAP_PROPERTIES1 {
+                        pixelFormat: D2D1_PIXEL_FORMAT {
+                            format: desc.Format,
+                            alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+                        },
+                        bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET | D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
+                        ..Default::default()
+                    }),
+                )?;
+            Ok(RenderTarget {
+                wrapper: Some(wrapper),
+                bitmap,
+                alpha_mode: AlphaMode::Premultiplied,
+            })
         }
     }
 
@@ -180,27 +190,65 @@ impl Context<Direct3D12> {
 impl Backend for Direct3D12 {
     type RenderTarget = RenderTarget;
 
-    fn d2d1_device(&self) -> &ID2D1Device5 {
-        &self.d2d1_device
+    #[inline]
+    fn create_factory(&self) -> Factory {
+        self.device.create_factory()
     }
 
-    fn d2d1_factory(&self) -> &ID2D1Factory6 {
-        &self.d2d1_factory
+    #[inline]
+    fn set_dpi(&self, dpi: f32) {
+        self.device.set_dpi(dpi);
     }
 
-    fn begin_draw(&self, target: &Self::RenderTarget) {
+    #[inline]
+    fn create_offscreen_target(
+        &self,
+        size: Size<u32>,
+        format: PixelFormat,
+        alpha_mode: AlphaMode,
+    ) -> Result<Self::RenderTarget> {
+        Ok(RenderTarget {
+            wrapper: None,
+            bitmap: self
+                .device
+                .create_offscreen_bitmap(size, format, alpha_mode)?,
+            alpha_mode,
+        })
+    }
+
+    fn draw<R>(
+        &self,
+        target: &Self::RenderTarget,
+        f: impl FnOnce(&dyn Renderer) -> R,
+    ) -> Result<R> {
+        let Some(wrapper) = &target.wrapper else {
+            return self.device.draw(&target.bitmap, f);
+        };
+        let wrapper = [Some(wrapper.clone())];
         unsafe {
-            self.d3d11on12_device
-                .AcquireWrappedResources(&[Some(target.wrapper.clone())]);
+            self.d3d11on12_device.AcquireWrappedResources(&wrapper);
+        }
+        let ret = self.device.draw(&target.bitmap, f);
+        unsafe {
+            self.d3d11on12_device.ReleaseWrappedResources(&wrapper);
+            self.d3d11_device_context.Flush();
+            self.d3d11_device_context.ClearState();
         }
+        ret
     }
 
-    fn end_draw(&self, target: &Self::RenderTarget, ret: Result<()>) -> Result<()> {
+    fn read_pixels(&self, target: &Self::RenderTarget) -> Result<ImageData> {
+        let Some(wrapper) = &target.wrapper else {
+            return self.device.read_pixels(&target.bitmap, target.alpha_mode);
+        };
+        let wrapper = [Some(wrapper.clone())];
         unsafe {
-            self.d3d11on12_device
-                .ReleaseWrappedResources(&[Some(target.wrapper.clone())]);
+            self.d3d11on12_device.AcquireWrappedResources(&wrapper);
+        }
+        let ret = self.device.read_pixels(&target.bitmap, target.alpha_mode);
+        unsafe {
+            self.d3d11on12_device.ReleaseWrappedResources(&wrapper);
             self.d3d11_device_context.Flush();
-            self.d3d11_device_context.ClearState();
         }
         ret
     }
@@ -0,0 +1,562 @@
+use crate::*;
+use std::cell::{Cell, RefCell};
+use std::sync::Mutex;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum DisplayCommand {
+    Clear(Rgba<f32>),
+    Fill {
+        shape: Shape,
+        brush: Brush,
+    },
+    Stroke {
+        shape: Shape,
+        brush: Brush,
+        width: f32,
+        style: Option<StrokeStyle>,
+    },
+    #[cfg(windows)]
+    DrawText {
+        layout: TextLayout,
+        origin: Point<f32>,
+        brush: Brush,
+    },
+    DrawImage {
+        image: Image,
+        dest_rect: Rect<f32>,
+        src_rect: Option<Rect<f32>>,
+        interpolation: Interpolation,
+    },
+    PushClip(Rect<f32>),
+    PopClip,
+    Translate(Point<f32>),
+    Scale(Size<f32>),
+    Concat(Transform),
+    SetTransform(Transform),
+    ResetTransform,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct DisplayList {
+    commands: Vec<DisplayCommand>,
+}
+
+impl DisplayList {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn record_balanced(f: impl FnOnce(&DrawCommand)) -> (Self, bool) {
+        let recording = Recording::default();
+        let cmd = DrawCommand::new(&recording);
+        f(&cmd);
+        let balanced = cmd.finish();
+        (recording.finish(), balanced)
+    }
+
+    // Outstanding `push_transform` calls are undone at the end of the list. `try_record` also
+    // fails with `Error::UnbalancedTransform` like `Context::draw`.
+    #[inline]
+    pub fn record(f: impl FnOnce(&DrawCommand)) -> Self {
+        Self::record_balanced(f).0
+    }
+
+    pub fn try_record(f: impl FnOnce(&DrawCommand)) -> Result<Self> {
+        match Self::record_balanced(f) {
+            (list, true) => Ok(list),
+            (_, false) => Err(Error::UnbalancedTransform),
+        }
+    }
+
+    #[inline]
+    pub fn commands(&self) -> &[DisplayCommand] {
+        &self.commands
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    #[inline]
+    pub fn push(&mut self, command: DisplayCommand) {
+        self.commands.push(command);
+    }
+
+    #[inline]
+    pub fn append(&mut self, other: &DisplayList) {
+        self.commands.extend_from_slice(&other.commands);
+    }
+
+    pub fn replay(&self, cmd: &DrawCommand) {
+        for command in &self.commands {
+            match command {
+                DisplayCommand::Clear(color) => cmd.clear(*color),
+                DisplayCommand::Fill { shape, brush } => cmd.fill(shape, brush),
+                DisplayCommand::Stroke {
+                    shape,
+                    brush,
+                    width,
+                    style,
+                } => cmd.stroke(shape, brush, *width, style.as_ref()),
+                #[cfg(windows)]
+                DisplayCommand::DrawText {
+                    layout,
+                    origin,
+                    brush,
+                } => cmd.fill(&layout.position(*origin), brush),
+                DisplayCommand::DrawImage {
+                    image,
+                    dest_rect,
+                    src_rect,
+                    interpolation,
+                } => cmd.draw_image(image, *dest_rect, *src_rect, *interpolation),
+                DisplayCommand::PushClip(rect) => cmd.push_clip(*rect),
+                DisplayCommand::PopClip => cmd.pop_clip(),
+                DisplayCommand::Translate(point) => cmd.translate(*point),
+                DisplayCommand::Scale(size) => cmd.scale(*size),
+                DisplayCommand::Concat(transform) => cmd.concat(transform),
+                DisplayCommand::SetTransform(transform) => cmd.set_transform(transform),
+                DisplayCommand::ResetTransform => cmd.reset_transform(),
+            }
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a DisplayList {
+    type Item = &'a DisplayCommand;
+    type IntoIter = std::slice::Iter<'a, DisplayCommand>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.commands.iter()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Recording {
+    commands: RefCell<Vec<DisplayCommand>>,
+    transform: Cell<Transform>,
+}
+
+impl Recording {
+    #[inline]
+    fn push(&self, command: DisplayCommand) {
+        self.commands.borrow_mut().push(command);
+    }
+
+    #[inline]
+    pub(crate) fn finish(self) -> DisplayList {
+        DisplayList {
+            commands: self.commands.into_inner(),
+        }
+    }
+}
+
+impl Renderer for Recording {
+    #[inline]
+    fn clear(&self, color: Rgba<f32>) {
+        self.push(DisplayCommand::Clear(color));
+    }
+
+    #[inline]
+    fn fill(&self, shape: &Shape, brush: &Brush) {
+        self.push(DisplayCommand::Fill {
+            shape: shape.clone(),
+            brush: brush.clone(),
+        });
+    }
+
+    #[inline]
+    fn stroke(&self, shape: &Shape, brush: &Brush, width: f32, style: Option<&StrokeStyle>) {
+        self.push(DisplayCommand::Stroke {
+            shape: shape.clone(),
+            brush: brush.clone(),
+            width,
+            style: style.cloned(),
+        });
+    }
+
+    #[cfg(windows)]
+    #[inline]
+    fn draw_text(&self, layout: &TextLayout, origin: Point<f32>, brush: &Brush) {
+        self.push(DisplayCommand::DrawText {
+            layout: layout.clone(),
+            origin,
+            brush: brush.clone(),
+        });
+    }
+
+    #[inline]
+    fn draw_image(
+        &self,
+        image: &Image,
+        dest_rect: Rect<f32>,
+        src_rect: Option<Rect<f32>>,
+        interpolation: Interpolation,
+    ) {
+        self.push(DisplayCommand::DrawImage {
+            image: image.clone(),
+            dest_rect,
+            src_rect,
+            interpolation,
+        });
+    }
+
+    #[inline]
+    fn push_clip(&self, rect: Rect<f32>) {
+        self.push(DisplayCommand::PushClip(rect));
+    }
+
+    #[inline]
+    fn pop_clip(&self) {
+        self.push(DisplayCommand::PopClip);
+    }
+
+    #[inline]
+    fn translate(&self, point: Point<f32>) {
+        self.transform
+            .set(Transform::translation(point.x, point.y) * self.transform.get());
+        self.push(DisplayCommand::Translate(point));
+    }
+
+    #[inline]
+    fn scale(&self, size: Size<f32>) {
+        self.transform
+            .set(Transform::scale(size.width, size.height) * self.transform.get());
+        self.push(DisplayCommand::Scale(size));
+    }
+
+    #[inline]
+    fn concat(&self, transform: &Transform) {
+        self.transform.set(*transform * self.transform.get());
+        self.push(DisplayCommand::Concat(*transform));
+    }
+
+    #[inline]
+    fn set_transform(&self, transform: &Transform) {
+        self.transform.set(*transform);
+        self.push(DisplayCommand::SetTransform(*transform));
+    }
+
+    #[inline]
+    fn transform(&self) -> Transform {
+        self.transform.get()
+    }
+
+    #[inline]
+    fn reset_transform(&self) {
+        self.transform.set(Transform::identity());
+        self.push(DisplayCommand::ResetTransform);
+    }
+}
+
+// Every draw is rasterized into `pixels` as it is recorded, so readback does not depend on
+// the history. Recorded frames are only kept after `set_history(true)`.
+#[derive(Debug)]
+pub struct RenderTarget {
+    size: Size<u32>,
+    pixels: software::RenderTarget,
+    display_list: Mutex<DisplayList>,
+    history: Mutex<Option<Vec<DisplayList>>>,
+}
+
+impl RenderTarget {
+    fn new(size: Size<u32>, format: PixelFormat, alpha_mode: AlphaMode) -> Self {
+        Self {
+            size,
+            pixels: software::RenderTarget::new(size, format, alpha_mode),
+            display_list: Mutex::new(DisplayList::new()),
+            history: Mutex::new(None),
+        }
+    }
+
+    // The list recorded by the most recent `Context::draw`.
+    #[inline]
+    pub fn display_list(&self) -> DisplayList {
+        self.display_list
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    #[inline]
+    pub fn set_history(&self, enabled: bool) {
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        match (enabled, history.is_some()) {
+            (true, false) => *history = Some(vec![]),
+            (false, true) => *history = None,
+            _ => {}
+        }
+    }
+
+    #[inline]
+    pub fn frames(&self) -> Vec<DisplayList> {
+        self.history
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    pub fn take_frames(&self) -> Vec<DisplayList> {
+        self.history
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+}
+
+impl Target for RenderTarget {
+    #[inline]
+    fn size(&self) -> Size<f32> {
+        Size::new(self.size.width as f32, self.size.height as f32)
+    }
+
+    #[inline]
+    fn physical_size(&self) -> Size<u32> {
+        self.size
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Recorder {
+    software: Software,
+}
+
+impl Recorder {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Context<Recorder> {
+    #[inline]
+    pub fn create_render_target(&self, size: impl Into<Size<u32>>) -> Result<RenderTarget> {
+        Ok(RenderTarget::new(
+            size.into(),
+            PixelFormat::Rgba8,
+            AlphaMode::Premultiplied,
+        ))
+    }
+}
+
+impl Backend for Recorder {
+    type RenderTarget = RenderTarget;
+
+    #[inline]
+    fn create_factory(&self) -> Factory {
+        self.software.create_factory()
+    }
+
+    #[inline]
+    fn set_dpi(&self, dpi: f32) {
+        self.software.set_dpi(dpi);
+    }
+
+    #[inline]
+    fn create_offscreen_target(
+        &self,
+        size: Size<u32>,
+        format: PixelFormat,
+        alpha_mode: AlphaMode,
+    ) -> Result<Self::RenderTarget> {
+        Ok(RenderTarget::new(size, format, alpha_mode))
+    }
+
+    fn draw<R>(
+        &self,
+        target: &Self::RenderTarget,
+        f: impl FnOnce(&dyn Renderer) -> R,
+    ) -> Result<R> {
+        let recording = Recording::default();
+        let ret = f(&recording);
+        let list = recording.finish();
+        self.software.draw(&target.pixels, |renderer| {
+            list.replay(&DrawCommand::new(renderer))
+        })?;
+        if let Some(history) = target
+            .history
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+        {
+            history.push(list.clone());
+        }
+        *target
+            .display_list
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = list;
+        Ok(ret)
+    }
+
+    #[inline]
+    fn read_pixels(&self, target: &Self::RenderTarget) -> Result<ImageData> {
+        self.software.read_pixels(&target.pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record() {
+        let factory = Context::new(Recorder::new()).unwrap().create_factory();
+        let brush = factory
+            .create_solid_color_brush((1.0, 0.0, 0.0, 1.0))
+            .unwrap();
+        let list = DisplayList::record(|cmd| {
+            cmd.clear((0.0, 0.0, 0.0, 1.0));
+            cmd.translate((1.0, 2.0));
+            cmd.clip(Rect::new((0.0, 0.0), (4.0, 4.0)), |cmd| {
+                cmd.fill(&Rect::new((1.0, 1.0), (2.0, 2.0)), &brush);
+                cmd.stroke(&line((0.0, 0.0), (4.0, 4.0)), &brush, 1.0, None);
+            });
+        });
+        assert_eq!(
+            list.commands(),
+            [
+                DisplayCommand::Clear(Rgba::new(0.0, 0.0, 0.0, 1.0)),
+                DisplayCommand::Translate(Point::new(1.0, 2.0)),
+                DisplayCommand::PushClip(Rect::new((0.0, 0.0), (4.0, 4.0))),
+                DisplayCommand::Fill {
+                    shape: Shape::Rect(Rect::new((1.0, 1.0), (2.0, 2.0))),
+                    brush: brush.clone(),
+                },
+                DisplayCommand::Stroke {
+                    shape: Shape::Line(line((0.0, 0.0), (4.0, 4.0))),
+                    brush: brush.clone(),
+                    width: 1.0,
+                    style: None,
+                },
+                DisplayCommand::PopClip,
+            ]
+        );
+        assert_eq!(list, DisplayList::record(|cmd| list.replay(cmd)));
+
+        let scale = Transform::scale(2.0, 2.0);
+        let unbalanced = |cmd: &DrawCommand| {
+            cmd.push_transform(&scale);
+            cmd.fill(&Rect::new((0.0, 0.0), (1.0, 1.0)), &brush);
+        };
+        assert_eq!(
+            DisplayList::try_record(unbalanced),
+            Err(Error::UnbalancedTransform)
+        );
+        let list = DisplayList::record(unbalanced);
+        assert_eq!(list.commands()[0], DisplayCommand::Concat(scale));
+        assert_eq!(
+            list.commands().last(),
+            Some(&DisplayCommand::SetTransform(Transform::identity()))
+        );
+        assert_eq!(
+            DisplayList::try_record(|cmd| cmd.pop_transform()),
+            Err(Error::UnbalancedTransform)
+        );
+        assert!(DisplayList::try_record(|cmd| list.replay(cmd)).is_ok());
+    }
+
+    #[test]
+    fn recorder_backend() {
+        let ctx = Context::new(Recorder::new()).unwrap();
+        let factory = ctx.create_factory();
+        let brush = factory
+            .create_solid_color_brush((0.0, 1.0, 0.0, 1.0))
+            .unwrap();
+        let target = ctx.create_render_target((4, 4)).unwrap();
+        ctx.draw(&target, |cmd| {
+            cmd.fill(&Rect::new((0.0, 0.0), (2.0, 2.0)), &brush);
+        })
+        .unwrap();
+        let expected = DisplayList::record(|cmd| {
+            cmd.fill(&Rect::new((0.0, 0.0), (2.0, 2.0)), &brush);
+        });
+        assert_eq!(target.display_list(), expected);
+        let image = ctx.read_pixels(&target).unwrap();
+        assert_eq!(image.pixel(1, 1), [0, 255, 0, 255]);
+        assert_eq!(image.pixel(2, 2), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn recorder_frames() {
+        let ctx = Context::new(Recorder::new()).unwrap();
+        let factory = ctx.create_factory();
+        let brush = factory
+            .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+            .unwrap();
+        let target = ctx.create_render_target((8, 8)).unwrap();
+        let rect = Rect::new((0.0, 0.0), (2.0, 2.0));
+        ctx.draw(&target, |cmd| {
+            cmd.translate((4.0, 0.0));
+            cmd.fill(&rect, &brush);
+        })
+        .unwrap();
+        assert!(target.frames().is_empty());
+        target.set_history(true);
+        ctx.draw(&target, |cmd| {
+            cmd.scale((2.0, 2.0));
+            cmd.fill(&rect, &brush);
+        })
+        .unwrap();
+        let expected = DisplayList::record(|cmd| {
+            cmd.scale((2.0, 2.0));
+            cmd.fill(&rect, &brush);
+        });
+        assert_eq!(target.display_list(), expected);
+        assert_eq!(target.frames(), std::slice::from_ref(&expected));
+        let image = ctx.read_pixels(&target).unwrap();
+        assert_eq!(image.pixel(5, 1), [255, 255, 255, 255]);
+        assert_eq!(image.pixel(3, 3), [255, 255, 255, 255]);
+        assert_eq!(image.pixel(5, 3), [0, 0, 0, 0]);
+        assert_eq!(image.pixel(7, 7), [0, 0, 0, 0]);
+        assert_eq!(target.take_frames().len(), 1);
+        assert!(target.frames().is_empty());
+        assert_eq!(target.display_list(), expected);
+    }
+
+    #[test]
+    fn draw_image() {
+        let image = Image::new(ImageData {
+            width: 2,
+            height: 1,
+            stride: 8,
+            format: PixelFormat::Rgba8,
+            alpha_mode: AlphaMode::Premultiplied,
+            bytes: vec![255, 0, 0, 255, 0, 255, 0, 255],
+        });
+        let ctx = Context::new(Recorder::new()).unwrap();
+        let target = ctx.create_render_target((4, 2)).unwrap();
+        ctx.draw(&target, |cmd| {
+            cmd.draw_image(
+                &image,
+                Rect::new((0.0, 0.0), (4.0, 2.0)),
+                None,
+                Interpolation::NearestNeighbor,
+            );
+        })
+        .unwrap();
+        assert_eq!(
+            target.display_list().commands(),
+            [DisplayCommand::DrawImage {
+                image: image.clone(),
+                dest_rect: Rect::new((0.0, 0.0), (4.0, 2.0)),
+                src_rect: None,
+                interpolation: Interpolation::NearestNeighbor,
+            }]
+        );
+        let pixels = ctx.read_pixels(&target).unwrap();
+        assert_eq!(pixels.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixels.pixel(3, 1), [0, 255, 0, 255]);
+    }
+}
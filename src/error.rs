@@ -1,13 +1,24 @@
+#[cfg(windows)]
 use windows::Win32::Foundation::D2DERR_RECREATE_TARGET;
 
 #[derive(Clone, PartialEq, Eq, Debug, thiserror::Error)]
 pub enum Error {
     #[error("RecreateTarget")]
     RecreateTarget,
+    #[error("Unsupported")]
+    Unsupported,
+    #[error("{0}")]
+    Codec(String),
+    #[error("unbalanced push_transform and pop_transform")]
+    UnbalancedTransform,
+    #[error("invalid path data at {position}: {message}")]
+    InvalidPathData { position: usize, message: String },
+    #[cfg(windows)]
     #[error("{0}")]
     Other(windows::core::Error),
 }
 
+#[cfg(windows)]
 impl From<windows::core::Error> for Error {
     fn from(src: windows::core::Error) -> Self {
         if src.code() == D2DERR_RECREATE_TARGET {
@@ -18,6 +29,7 @@ impl From<windows::core::Error> for Error {
     }
 }
 
+#[cfg(windows)]
 impl From<windows::core::HRESULT> for Error {
     fn from(src: windows::core::HRESULT) -> Self {
         match src {
@@ -0,0 +1,174 @@
+use crate::*;
+
+pub(crate) const TOLERANCE: f32 = 0.02;
+pub(crate) const MIN_TOLERANCE: f32 = 1e-4;
+const MAX_DEPTH: u32 = 16;
+
+// Tolerances below MIN_TOLERANCE are raised to it, and NaN or infinite ones fall back to
+// TOLERANCE, so that curves are never subdivided down to MAX_DEPTH.
+#[inline]
+pub(crate) fn clamp_tolerance(tolerance: f32) -> f32 {
+    if tolerance.is_finite() {
+        tolerance.max(MIN_TOLERANCE)
+    } else {
+        TOLERANCE
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Polyline {
+    pub points: Vec<Point<f32>>,
+    pub closed: bool,
+}
+
+#[inline]
+fn lerp(a: Point<f32>, b: Point<f32>, t: f32) -> Point<f32> {
+    Point::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t)
+}
+
+fn cubic_is_flat(p: [Point<f32>; 4], tolerance: f32) -> bool {
+    let ux = (3.0 * p[1].x - 2.0 * p[0].x - p[3].x).powi(2);
+    let uy = (3.0 * p[1].y - 2.0 * p[0].y - p[3].y).powi(2);
+    let vx = (3.0 * p[2].x - 2.0 * p[3].x - p[0].x).powi(2);
+    let vy = (3.0 * p[2].y - 2.0 * p[3].y - p[0].y).powi(2);
+    ux.max(vx) + uy.max(vy) <= 16.0 * tolerance * tolerance
+}
+
+fn flatten_cubic(p: [Point<f32>; 4], tolerance: f32, depth: u32, out: &mut Vec<Point<f32>>) {
+    if depth >= MAX_DEPTH || cubic_is_flat(p, tolerance) {
+        out.push(p[3]);
+        return;
+    }
+    let p01 = lerp(p[0], p[1], 0.5);
+    let p12 = lerp(p[1], p[2], 0.5);
+    let p23 = lerp(p[2], p[3], 0.5);
+    let p012 = lerp(p01, p12, 0.5);
+    let p123 = lerp(p12, p23, 0.5);
+    let mid = lerp(p012, p123, 0.5);
+    flatten_cubic([p[0], p01, p012, mid], tolerance, depth + 1, out);
+    flatten_cubic([mid, p123, p23, p[3]], tolerance, depth + 1, out);
+}
+
+#[inline]
+pub(crate) fn quadratic_to_cubic(
+    from: Point<f32>,
+    segment: &QuadraticBezierSegment,
+) -> CubicBezierSegment {
+    CubicBezierSegment {
+        c0: lerp(from, segment.ctrl, 2.0 / 3.0),
+        c1: lerp(segment.to, segment.ctrl, 2.0 / 3.0),
+        to: segment.to,
+    }
+}
+
+pub(crate) fn arc_to_cubics(from: Point<f32>, segment: &ArcSegment) -> Vec<CubicBezierSegment> {
+    let to = segment.to;
+    if from == to {
+        return vec![];
+    }
+    let (mut rx, mut ry) = (segment.radius.x.abs(), segment.radius.y.abs());
+    if rx == 0.0 || ry == 0.0 {
+        return vec![CubicBezierSegment {
+            c0: from,
+            c1: to,
+            to,
+        }];
+    }
+    let (sin, cos) = segment.rotation.to_radians().sin_cos();
+    let (hx, hy) = ((from.x - to.x) * 0.5, (from.y - to.y) * 0.5);
+    let x1 = cos * hx + sin * hy;
+    let y1 = -sin * hx + cos * hy;
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let clockwise = segment.sweep == SweepDirection::Clockwise;
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coef = (num / den).max(0.0).sqrt();
+    if segment.large_arc == clockwise {
+        coef = -coef;
+    }
+    let (cx1, cy1) = (coef * rx * y1 / ry, -coef * ry * x1 / rx);
+    let cx = cos * cx1 - sin * cy1 + (from.x + to.x) * 0.5;
+    let cy = sin * cx1 + cos * cy1 + (from.y + to.y) * 0.5;
+    let start = ((y1 - cy1) / ry).atan2((x1 - cx1) / rx);
+    let end = ((-y1 - cy1) / ry).atan2((-x1 - cx1) / rx);
+    let mut sweep = end - start;
+    if clockwise && sweep < 0.0 {
+        sweep += std::f32::consts::TAU;
+    } else if !clockwise && sweep > 0.0 {
+        sweep -= std::f32::consts::TAU;
+    }
+    let count = (sweep.abs() / std::f32::consts::FRAC_PI_2 - 1.0e-3)
+        .ceil()
+        .max(1.0) as usize;
+    let delta = sweep / count as f32;
+    let k = 4.0 / 3.0 * (delta / 4.0).tan();
+    let map = |x: f32, y: f32| {
+        let (x, y) = (x * rx, y * ry);
+        Point::new(cx + cos * x - sin * y, cy + sin * x + cos * y)
+    };
+    (0..count)
+        .map(|i| {
+            let (s0, c0) = (start + delta * i as f32).sin_cos();
+            let (s1, c1) = (start + delta * (i + 1) as f32).sin_cos();
+            CubicBezierSegment {
+                c0: map(c0 - k * s0, s0 + k * c0),
+                c1: map(c1 + k * s1, s1 - k * c1),
+                to: if i + 1 == count { to } else { map(c1, s1) },
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn flatten_segment(
+    from: Point<f32>,
+    segment: &PathSegment,
+    tolerance: f32,
+    points: &mut Vec<Point<f32>>,
+) {
+    match segment {
+        PathSegment::Line(point) => points.push(*point),
+        PathSegment::QuadraticBezier(segment) => {
+            let cubic = quadratic_to_cubic(from, segment);
+            flatten_cubic([from, cubic.c0, cubic.c1, cubic.to], tolerance, 0, points);
+        }
+        PathSegment::CubicBezier(segment) => {
+            flatten_cubic(
+                [from, segment.c0, segment.c1, segment.to],
+                tolerance,
+                0,
+                points,
+            );
+        }
+        PathSegment::Arc(segment) => {
+            let mut from = from;
+            for cubic in arc_to_cubics(from, segment) {
+                flatten_cubic([from, cubic.c0, cubic.c1, cubic.to], tolerance, 0, points);
+                from = cubic.to;
+            }
+        }
+    }
+}
+
+pub(crate) fn flatten_figure(figure: &PathFigure, tolerance: f32) -> Polyline {
+    let mut points = vec![figure.begin];
+    let mut current = figure.begin;
+    for segment in &figure.segments {
+        flatten_segment(current, segment, tolerance, &mut points);
+        current = segment.end_point();
+    }
+    Polyline {
+        points,
+        closed: figure.end == FigureEnd::Closed,
+    }
+}
+
+pub(crate) fn flatten(figures: &[PathFigure], tolerance: f32) -> Vec<Polyline> {
+    figures
+        .iter()
+        .map(|figure| flatten_figure(figure, tolerance))
+        .collect()
+}
@@ -0,0 +1,1031 @@
+use crate::combine::combine;
+use crate::flatten::{arc_to_cubics, clamp_tolerance, flatten, flatten_segment, TOLERANCE};
+use crate::stroker::{Polygon, Stroker};
+use crate::tessellate::tessellate;
+use crate::*;
+
+#[derive(Clone, Copy, Debug)]
+struct Bounds {
+    min: Point<f32>,
+    max: Point<f32>,
+}
+
+impl Bounds {
+    #[inline]
+    fn new() -> Self {
+        Self {
+            min: Point::new(f32::INFINITY, f32::INFINITY),
+            max: Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    #[inline]
+    fn add(&mut self, p: Point<f32>) {
+        self.min = Point::new(self.min.x.min(p.x), self.min.y.min(p.y));
+        self.max = Point::new(self.max.x.max(p.x), self.max.y.max(p.y));
+    }
+
+    #[inline]
+    fn to_rect(self) -> Rect<f32> {
+        if self.min.x > self.max.x || self.min.y > self.max.y {
+            return Rect::new((0.0, 0.0), (0.0, 0.0));
+        }
+        Rect::new(self.min, (self.max.x - self.min.x, self.max.y - self.min.y))
+    }
+}
+
+fn cubic_extrema(p: [f32; 4]) -> impl Iterator<Item = f32> {
+    let a = -p[0] + 3.0 * p[1] - 3.0 * p[2] + p[3];
+    let b = 2.0 * (p[0] - 2.0 * p[1] + p[2]);
+    let c = p[1] - p[0];
+    let roots = if a.abs() <= f32::EPSILON {
+        [(b.abs() > f32::EPSILON).then(|| -c / b), None]
+    } else {
+        let d = b * b - 4.0 * a * c;
+        if d < 0.0 {
+            [None, None]
+        } else {
+            let d = d.sqrt();
+            [Some((-b + d) / (2.0 * a)), Some((-b - d) / (2.0 * a))]
+        }
+    };
+    roots.into_iter().flatten().filter(|t| *t > 0.0 && *t < 1.0)
+}
+
+fn add_cubic(bounds: &mut Bounds, p: [Point<f32>; 4]) {
+    let at = |t: f32| {
+        let u = 1.0 - t;
+        let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+        Point::new(
+            a * p[0].x + b * p[1].x + c * p[2].x + d * p[3].x,
+            a * p[0].y + b * p[1].y + c * p[2].y + d * p[3].y,
+        )
+    };
+    for t in cubic_extrema([p[0].x, p[1].x, p[2].x, p[3].x])
+        .chain(cubic_extrema([p[0].y, p[1].y, p[2].y, p[3].y]))
+    {
+        bounds.add(at(t));
+    }
+    bounds.add(p[3]);
+}
+
+fn add_quadratic(bounds: &mut Bounds, p: [Point<f32>; 3]) {
+    let at = |t: f32| {
+        let u = 1.0 - t;
+        Point::new(
+            u * u * p[0].x + 2.0 * u * t * p[1].x + t * t * p[2].x,
+            u * u * p[0].y + 2.0 * u * t * p[1].y + t * t * p[2].y,
+        )
+    };
+    for (a, b, c) in [(p[0].x, p[1].x, p[2].x), (p[0].y, p[1].y, p[2].y)] {
+        let d = a - 2.0 * b + c;
+        if d.abs() > f32::EPSILON {
+            let t = (a - b) / d;
+            if t > 0.0 && t < 1.0 {
+                bounds.add(at(t));
+            }
+        }
+    }
+    bounds.add(p[2]);
+}
+
+pub(crate) fn figures_bounds(figures: &[PathFigure]) -> Rect<f32> {
+    let mut bounds = Bounds::new();
+    for figure in figures {
+        bounds.add(figure.begin);
+        let mut current = figure.begin;
+        for segment in &figure.segments {
+            match segment {
+                PathSegment::Line(p) => bounds.add(*p),
+                PathSegment::QuadraticBezier(s) => {
+                    add_quadratic(&mut bounds, [current, s.ctrl, s.to])
+                }
+                PathSegment::CubicBezier(s) => add_cubic(&mut bounds, [current, s.c0, s.c1, s.to]),
+                PathSegment::Arc(s) => {
+                    let mut from = current;
+                    for c in arc_to_cubics(current, s) {
+                        add_cubic(&mut bounds, [from, c.c0, c.c1, c.to]);
+                        from = c.to;
+                    }
+                }
+            }
+            current = segment.end_point();
+        }
+    }
+    bounds.to_rect()
+}
+
+fn winding(polygon: &[Point<f32>], p: Point<f32>) -> i32 {
+    let mut winding = 0;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        let side = (b.x - a.x) * (p.y - a.y) - (p.x - a.x) * (b.y - a.y);
+        if a.y <= p.y {
+            if b.y > p.y && side > 0.0 {
+                winding += 1;
+            }
+        } else if b.y <= p.y && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding
+}
+
+fn on_boundary(polygon: &[Point<f32>], p: Point<f32>, epsilon: f32) -> bool {
+    polygon.iter().enumerate().any(|(i, a)| {
+        let b = polygon[(i + 1) % polygon.len()];
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let len = dx * dx + dy * dy;
+        let t = if len > 0.0 {
+            (((p.x - a.x) * dx + (p.y - a.y) * dy) / len).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (a.x + dx * t - p.x).hypot(a.y + dy * t - p.y) <= epsilon
+    })
+}
+
+pub(crate) fn fill_contains(
+    figures: &[PathFigure],
+    fill_rule: FillRule,
+    point: Point<f32>,
+    tolerance: f32,
+) -> bool {
+    let winding = flatten(figures, tolerance)
+        .iter()
+        .map(|polyline| winding(&polyline.points, point))
+        .sum::<i32>();
+    match fill_rule {
+        FillRule::EvenOdd => winding % 2 != 0,
+        FillRule::NonZero => winding != 0,
+    }
+}
+
+pub(crate) fn stroke_polygons(
+    figures: &[PathFigure],
+    width: f32,
+    props: &StrokeStyleProperties,
+    transform: &Transform,
+    tolerance: f32,
+) -> Vec<Polygon> {
+    let scale = transform.max_scale();
+    if scale <= 0.0 || scale.is_nan() {
+        return vec![];
+    }
+    let tolerance = tolerance / scale;
+    let polylines = flatten(figures, tolerance);
+    Stroker::new(width, props.line_join, tolerance)
+        .stroke(&polylines, props)
+        .into_iter()
+        .map(|polygon| {
+            polygon
+                .into_iter()
+                .map(|point| transform.transform_point(point))
+                .collect()
+        })
+        .collect()
+}
+
+pub(crate) fn polygon_figure(points: &[Point<f32>]) -> PathFigure {
+    let mut figure = PathFigure::new(points[0]);
+    figure.segments = points[1..].iter().map(|&p| PathSegment::Line(p)).collect();
+    figure.end = FigureEnd::Closed;
+    figure
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PointAtLength {
+    pub point: Point<f32>,
+    pub tangent: Vector<f32>,
+}
+
+// `closing` is set for the implicit line closing a figure. It is not stored in the figure, so its
+// `index` is `segments.len()`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SegmentAtLength {
+    pub figure: usize,
+    pub index: usize,
+    pub from: Point<f32>,
+    pub segment: PathSegment,
+    pub start: f32,
+    pub closing: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Edge {
+    segment: usize,
+    a: Point<f32>,
+    b: Point<f32>,
+    start: f32,
+    length: f32,
+}
+
+fn measure(figures: &[PathFigure], tolerance: f32) -> (Vec<SegmentAtLength>, Vec<Edge>) {
+    let mut segments = vec![];
+    let mut edges = vec![];
+    let mut total = 0.0;
+    for (i, figure) in figures.iter().enumerate() {
+        let mut from = figure.begin;
+        let closing = (figure.end == FigureEnd::Closed)
+            .then_some(PathSegment::Line(figure.begin))
+            .filter(|_| figure.segments.last().map(|s| s.end_point()) != Some(figure.begin));
+        for (index, segment) in figure.segments.iter().copied().chain(closing).enumerate() {
+            let mut points = vec![from];
+            flatten_segment(from, &segment, tolerance, &mut points);
+            segments.push(SegmentAtLength {
+                figure: i,
+                index,
+                from,
+                segment,
+                start: total,
+                closing: index == figure.segments.len(),
+            });
+            for pair in points.windows(2) {
+                let length = (pair[1].x - pair[0].x).hypot(pair[1].y - pair[0].y);
+                if length > 0.0 {
+                    edges.push(Edge {
+                        segment: segments.len() - 1,
+                        a: pair[0],
+                        b: pair[1],
+                        start: total,
+                        length,
+                    });
+                    total += length;
+                }
+            }
+            from = segment.end_point();
+        }
+    }
+    (segments, edges)
+}
+
+fn edge_at_length(edges: &[Edge], length: f32) -> Option<&Edge> {
+    let i = edges.partition_point(|edge| edge.start + edge.length <= length);
+    edges.get(i).or(edges.last())
+}
+
+pub(crate) fn transformed_data(geometry: &impl Geometry, transform: &Transform) -> PathData {
+    let figures = geometry
+        .figures()
+        .iter()
+        .map(|figure| figure.transform(transform))
+        .collect::<Vec<_>>();
+    let mut data = PathData::from(figures);
+    data.set_fill_rule(geometry.fill_rule());
+    data
+}
+
+// Methods taking a tolerance raise it to at least 1e-4, and treat NaN or infinity as the default
+// tolerance of 0.02.
+pub trait Geometry {
+    type Transformed: Geometry;
+
+    fn figures(&self) -> Vec<PathFigure>;
+
+    fn transformed(&self, transform: &Transform) -> Self::Transformed;
+
+    #[inline]
+    fn fill_rule(&self) -> FillRule {
+        FillRule::NonZero
+    }
+
+    #[inline]
+    fn is_fillable(&self) -> bool {
+        true
+    }
+
+    // The figures that are filled, and those that are only ever stroked.
+    #[inline]
+    fn filled_figures(&self) -> Vec<PathFigure> {
+        if self.is_fillable() {
+            self.figures()
+        } else {
+            Vec::new()
+        }
+    }
+
+    #[inline]
+    fn hollow_figures(&self) -> Vec<PathFigure> {
+        if self.is_fillable() {
+            Vec::new()
+        } else {
+            self.figures()
+        }
+    }
+
+    #[inline]
+    fn flatten(&self, tolerance: f32) -> Vec<Polyline> {
+        let tolerance = clamp_tolerance(tolerance);
+        flatten(&self.figures(), tolerance)
+    }
+
+    fn length(&self, tolerance: f32) -> f32 {
+        let tolerance = clamp_tolerance(tolerance);
+        measure(&self.figures(), tolerance)
+            .1
+            .last()
+            .map_or(0.0, |edge| edge.start + edge.length)
+    }
+
+    fn point_at_length(&self, length: f32, tolerance: f32) -> Option<PointAtLength> {
+        let tolerance = clamp_tolerance(tolerance);
+        let (_, edges) = measure(&self.figures(), tolerance);
+        let edge = edge_at_length(&edges, length)?;
+        let t = ((length - edge.start) / edge.length).clamp(0.0, 1.0);
+        let d = Vector::new(edge.b.x - edge.a.x, edge.b.y - edge.a.y);
+        Some(PointAtLength {
+            point: Point::new(edge.a.x + d.x * t, edge.a.y + d.y * t),
+            tangent: Vector::new(d.x / edge.length, d.y / edge.length),
+        })
+    }
+
+    fn segment_at_length(&self, length: f32, tolerance: f32) -> Option<SegmentAtLength> {
+        let tolerance = clamp_tolerance(tolerance);
+        let (segments, edges) = measure(&self.figures(), tolerance);
+        let edge = edge_at_length(&edges, length)?;
+        Some(segments[edge.segment])
+    }
+
+    #[inline]
+    fn bounds(&self) -> Rect<f32> {
+        figures_bounds(&self.figures())
+    }
+
+    fn stroke_bounds(
+        &self,
+        width: f32,
+        style: Option<&StrokeStyle>,
+        transform: Option<&Transform>,
+    ) -> Rect<f32> {
+        let props = style.map(|style| style.properties()).unwrap_or_default();
+        let transform = transform.copied().unwrap_or_default();
+        let mut bounds = Bounds::new();
+        for polygon in stroke_polygons(&self.figures(), width, &props, &transform, TOLERANCE) {
+            polygon.into_iter().for_each(|p| bounds.add(p));
+        }
+        bounds.to_rect()
+    }
+
+    #[inline]
+    fn tessellate(&self, tolerance: f32) -> Mesh {
+        let tolerance = clamp_tolerance(tolerance);
+        tessellate(&self.figures(), self.fill_rule(), tolerance)
+    }
+
+    fn widen(&self, width: f32, props: &StrokeStyleProperties, tolerance: f32) -> FilledPath {
+        let tolerance = clamp_tolerance(tolerance);
+        let figures = stroke_polygons(
+            &self.figures(),
+            width,
+            props,
+            &Transform::identity(),
+            tolerance,
+        )
+        .iter()
+        .filter(|polygon| polygon.len() > 2)
+        .map(|polygon| polygon_figure(polygon))
+        .collect::<Vec<_>>();
+        let mut data = PathData::from(figures);
+        data.set_fill_rule(FillRule::NonZero);
+        FilledPath::from(data)
+    }
+
+    #[inline]
+    fn fill_contains(&self, point: impl Into<Point<f32>>, tolerance: f32) -> bool
+    where
+        Self: Sized,
+    {
+        let tolerance = clamp_tolerance(tolerance);
+        self.is_fillable()
+            && fill_contains(&self.figures(), self.fill_rule(), point.into(), tolerance)
+    }
+
+    fn stroke_contains(
+        &self,
+        point: impl Into<Point<f32>>,
+        width: f32,
+        style: Option<&StrokeStyle>,
+        tolerance: f32,
+    ) -> bool
+    where
+        Self: Sized,
+    {
+        let tolerance = clamp_tolerance(tolerance);
+        let point = point.into();
+        let props = style.map(|style| style.properties()).unwrap_or_default();
+        let (winding, edge) = stroke_polygons(
+            &self.figures(),
+            width,
+            &props,
+            &Transform::identity(),
+            tolerance,
+        )
+        .iter()
+        .fold((0, false), |(sum, edge), polygon| {
+            (
+                sum + winding(polygon, point),
+                edge || on_boundary(polygon, point, tolerance),
+            )
+        });
+        winding != 0 || edge
+    }
+
+    #[inline]
+    fn combine(&self, other: &impl Geometry, mode: CombineMode, tolerance: f32) -> FilledPath
+    where
+        Self: Sized,
+    {
+        let tolerance = clamp_tolerance(tolerance);
+        FilledPath::from(combine(
+            (&self.figures(), self.fill_rule()),
+            (&other.figures(), other.fill_rule()),
+            mode,
+            tolerance,
+        ))
+    }
+}
+
+impl Geometry for Rect<f32> {
+    type Transformed = FilledPath;
+
+    #[inline]
+    fn figures(&self) -> Vec<PathFigure> {
+        Shape::Rect(*self).figures()
+    }
+
+    #[inline]
+    fn transformed(&self, transform: &Transform) -> FilledPath {
+        FilledPath::from(transformed_data(self, transform))
+    }
+}
+
+impl Geometry for Circle {
+    type Transformed = FilledPath;
+
+    #[inline]
+    fn figures(&self) -> Vec<PathFigure> {
+        Shape::Circle(*self).figures()
+    }
+
+    #[inline]
+    fn transformed(&self, transform: &Transform) -> FilledPath {
+        FilledPath::from(transformed_data(self, transform))
+    }
+}
+
+impl Geometry for Ellipse {
+    type Transformed = FilledPath;
+
+    #[inline]
+    fn figures(&self) -> Vec<PathFigure> {
+        Shape::Ellipse(*self).figures()
+    }
+
+    #[inline]
+    fn transformed(&self, transform: &Transform) -> FilledPath {
+        FilledPath::from(transformed_data(self, transform))
+    }
+}
+
+impl Geometry for RoundedRect {
+    type Transformed = FilledPath;
+
+    #[inline]
+    fn figures(&self) -> Vec<PathFigure> {
+        Shape::RoundedRect(*self).figures()
+    }
+
+    #[inline]
+    fn transformed(&self, transform: &Transform) -> FilledPath {
+        FilledPath::from(transformed_data(self, transform))
+    }
+}
+
+impl Geometry for Line {
+    type Transformed = Line;
+
+    #[inline]
+    fn figures(&self) -> Vec<PathFigure> {
+        Shape::Line(*self).figures()
+    }
+
+    #[inline]
+    fn transformed(&self, transform: &Transform) -> Line {
+        Line(
+            transform.transform_point(self.0),
+            transform.transform_point(self.1),
+        )
+    }
+
+    #[inline]
+    fn is_fillable(&self) -> bool {
+        false
+    }
+}
+
+impl Geometry for FilledPath {
+    type Transformed = FilledPath;
+
+    #[inline]
+    fn figures(&self) -> Vec<PathFigure> {
+        self.path_data().figures().to_vec()
+    }
+
+    #[inline]
+    fn transformed(&self, transform: &Transform) -> FilledPath {
+        FilledPath::from(transformed_data(self, transform))
+    }
+
+    #[inline]
+    fn fill_rule(&self) -> FillRule {
+        self.path_data().fill_rule()
+    }
+}
+
+impl Geometry for HollowPath {
+    type Transformed = HollowPath;
+
+    #[inline]
+    fn figures(&self) -> Vec<PathFigure> {
+        self.path_data().figures().to_vec()
+    }
+
+    #[inline]
+    fn transformed(&self, transform: &Transform) -> HollowPath {
+        HollowPath::from(transformed_data(self, transform))
+    }
+
+    #[inline]
+    fn is_fillable(&self) -> bool {
+        false
+    }
+}
+
+impl Geometry for GeometryGroup {
+    type Transformed = GeometryGroup;
+
+    #[inline]
+    fn figures(&self) -> Vec<PathFigure> {
+        let mut figures = self.path().figures();
+        figures.extend(self.hollow_path().figures());
+        figures
+    }
+
+    #[inline]
+    fn fill_rule(&self) -> FillRule {
+        GeometryGroup::fill_rule(self)
+    }
+
+    #[inline]
+    fn filled_figures(&self) -> Vec<PathFigure> {
+        self.path().figures()
+    }
+
+    #[inline]
+    fn hollow_figures(&self) -> Vec<PathFigure> {
+        self.hollow_path().figures()
+    }
+
+    #[inline]
+    fn transformed(&self, transform: &Transform) -> GeometryGroup {
+        GeometryGroup {
+            filled: self.path().transformed(transform),
+            hollow: self.hollow_path().transformed(transform),
+        }
+    }
+
+    #[inline]
+    fn tessellate(&self, tolerance: f32) -> Mesh {
+        self.path().tessellate(tolerance)
+    }
+
+    #[inline]
+    fn fill_contains(&self, point: impl Into<Point<f32>>, tolerance: f32) -> bool {
+        self.path().fill_contains(point, tolerance)
+    }
+
+    #[inline]
+    fn combine(&self, other: &impl Geometry, mode: CombineMode, tolerance: f32) -> FilledPath {
+        self.path().combine(other, mode, tolerance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_rect(actual: Rect<f32>, expected: Rect<f32>) {
+        let ep = (actual.endpoint(), expected.endpoint());
+        assert!(
+            [
+                actual.origin.x - expected.origin.x,
+                actual.origin.y - expected.origin.y,
+                ep.0.x - ep.1.x,
+                ep.0.y - ep.1.y,
+            ]
+            .iter()
+            .all(|d| d.abs() < 0.03),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn bounds() {
+        assert_rect(
+            Circle::new((10.0, 20.0), 5.0).bounds(),
+            Rect::new((5.0, 15.0), (10.0, 10.0)),
+        );
+        assert_rect(
+            Ellipse::new((0.0, 0.0), (4.0, 2.0)).bounds(),
+            Rect::new((-4.0, -2.0), (8.0, 4.0)),
+        );
+        assert_rect(
+            line((3.0, 1.0), (-1.0, 2.0)).bounds(),
+            Rect::new((-1.0, 1.0), (4.0, 1.0)),
+        );
+        let path = PathData::builder()
+            .begin((0.0, 0.0))
+            .cubic_bezier_to((0.0, 10.0), (10.0, 10.0), (10.0, 0.0))
+            .quadratic_bezier_to((15.0, -10.0), (20.0, 0.0))
+            .end(FigureEnd::Open)
+            .unwrap()
+            .close()
+            .unwrap();
+        assert_rect(
+            HollowPath::from(path).bounds(),
+            Rect::new((0.0, -5.0), (20.0, 12.5)),
+        );
+        assert_eq!(
+            HollowPath::from(PathData::new()).bounds(),
+            Rect::new((0.0, 0.0), (0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn stroke_bounds() {
+        let rect = Rect::new((0.0, 0.0), (10.0, 10.0));
+        assert_rect(
+            rect.stroke_bounds(2.0, None, None),
+            Rect::new((-1.0, -1.0), (12.0, 12.0)),
+        );
+        assert_rect(
+            rect.stroke_bounds(2.0, None, Some(&Transform::scale(2.0, 1.0))),
+            Rect::new((-2.0, -1.0), (24.0, 12.0)),
+        );
+        let style = StrokeStyle::new(&StrokeStyleProperties {
+            start_cap: CapStyle::Square,
+            end_cap: CapStyle::Round,
+            ..Default::default()
+        });
+        assert_rect(
+            line((0.0, 0.0), (10.0, 0.0)).stroke_bounds(4.0, Some(&style), None),
+            Rect::new((-2.0, -2.0), (14.0, 4.0)),
+        );
+        let wedge = PathData::builder()
+            .begin((0.0, 0.0))
+            .line_to((10.0, 1.0))
+            .line_to((0.0, 2.0))
+            .end(FigureEnd::Open)
+            .unwrap()
+            .close()
+            .unwrap();
+        let wedge = HollowPath::from(wedge);
+        let miter = wedge.stroke_bounds(1.0, None, None);
+        assert!(miter.endpoint().x > 14.0, "{:?}", miter);
+        let bevel = StrokeStyle::new(&StrokeStyleProperties {
+            line_join: LineJoin::MiterOrBevel(2.0),
+            ..Default::default()
+        });
+        let bevel = wedge.stroke_bounds(1.0, Some(&bevel), None);
+        assert!(bevel.endpoint().x < 10.6, "{:?}", bevel);
+    }
+    #[test]
+    fn flatten() {
+        let rect = Rect::new((0.0, 0.0), (10.0, 5.0)).flatten(TOLERANCE);
+        assert_eq!(rect.len(), 1);
+        assert!(rect[0].closed);
+        assert_eq!(
+            rect[0].points[..4],
+            [
+                Point::new(0.0, 0.0),
+                Point::new(10.0, 0.0),
+                Point::new(10.0, 5.0),
+                Point::new(0.0, 5.0),
+            ]
+        );
+        let circle = Circle::new((0.0, 0.0), 10.0);
+        let coarse = circle.flatten(1.0);
+        let fine = circle.flatten(0.01);
+        assert!(coarse[0].points.len() < fine[0].points.len());
+        for p in &fine[0].points {
+            assert!((p.x.hypot(p.y) - 10.0).abs() <= 0.01, "{:?}", p);
+        }
+        for tolerance in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            let points = circle.flatten(tolerance)[0].points.len();
+            assert!(points <= 2048, "{} {}", tolerance, points);
+            assert!((circle.length(tolerance) - 20.0 * std::f32::consts::PI).abs() < 0.1);
+        }
+        let path = PathData::builder()
+            .begin((0.0, 0.0))
+            .arc_to(
+                (20.0, 0.0),
+                (10.0, 10.0),
+                0.0,
+                SweepDirection::Clockwise,
+                false,
+            )
+            .end(FigureEnd::Open)
+            .unwrap()
+            .close()
+            .unwrap();
+        let arc = HollowPath::from(path).flatten(0.01);
+        assert!(!arc[0].closed);
+        assert_eq!(arc[0].points.first(), Some(&Point::new(0.0, 0.0)));
+        assert_eq!(arc[0].points.last(), Some(&Point::new(20.0, 0.0)));
+        for p in &arc[0].points {
+            assert!(((p.x - 10.0).hypot(p.y) - 10.0).abs() <= 0.01, "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn widen() {
+        let line = line((0.0, 0.0), (10.0, 0.0));
+        let props = StrokeStyleProperties {
+            start_cap: CapStyle::Square,
+            end_cap: CapStyle::Round,
+            ..Default::default()
+        };
+        let outline = line.widen(4.0, &props, TOLERANCE);
+        assert_eq!(outline.fill_rule(), FillRule::NonZero);
+        assert_rect(outline.bounds(), Rect::new((-2.0, -2.0), (14.0, 4.0)));
+        let style = StrokeStyle::new(&props);
+        for p in [
+            (-1.5, 1.5),
+            (11.0, 0.5),
+            (11.5, 1.5),
+            (5.0, 2.5),
+            (5.0, 0.0),
+        ] {
+            assert_eq!(
+                outline.fill_contains(p, TOLERANCE),
+                line.stroke_contains(p, 4.0, Some(&style), TOLERANCE),
+                "{:?}",
+                p
+            );
+        }
+        let dashed = line.widen(
+            1.0,
+            &StrokeStyleProperties {
+                dash: Some(Dash {
+                    style: DashStyle::Custom(&[2.0, 2.0]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            TOLERANCE,
+        );
+        assert!(dashed.path_data().figures().len() > 1);
+        assert!(dashed.fill_contains((1.0, 0.0), TOLERANCE));
+        assert!(!dashed.fill_contains((3.0, 0.0), TOLERANCE));
+        let wedge = PathData::builder()
+            .begin((0.0, 0.0))
+            .line_to((10.0, 1.0))
+            .line_to((0.0, 2.0))
+            .end(FigureEnd::Open)
+            .unwrap()
+            .close()
+            .unwrap();
+        let wedge = HollowPath::from(wedge);
+        let bevel = StrokeStyleProperties {
+            line_join: LineJoin::MiterOrBevel(2.0),
+            ..Default::default()
+        };
+        assert!(wedge
+            .widen(1.0, &Default::default(), TOLERANCE)
+            .fill_contains((12.0, 1.0), TOLERANCE));
+        assert!(!wedge
+            .widen(1.0, &bevel, TOLERANCE)
+            .fill_contains((12.0, 1.0), TOLERANCE));
+    }
+
+    #[test]
+    fn length() {
+        let rect = Rect::new((0.0, 0.0), (10.0, 5.0));
+        assert_eq!(rect.length(TOLERANCE), 30.0);
+        let circle = Circle::new((0.0, 0.0), 10.0);
+        assert!((circle.length(0.001) - std::f32::consts::TAU * 10.0).abs() < 0.01);
+        assert_eq!(HollowPath::from(PathData::new()).length(TOLERANCE), 0.0);
+        assert_eq!(
+            HollowPath::from(PathData::new()).point_at_length(1.0, TOLERANCE),
+            None
+        );
+
+        let p = rect.point_at_length(12.5, TOLERANCE).unwrap();
+        assert_eq!(p.point, Point::new(10.0, 2.5));
+        assert_eq!(p.tangent, Vector::new(0.0, 1.0));
+        let p = rect.point_at_length(27.0, TOLERANCE).unwrap();
+        assert_eq!(p.point, Point::new(0.0, 3.0));
+        assert_eq!(p.tangent, Vector::new(0.0, -1.0));
+        let p = rect.point_at_length(-1.0, TOLERANCE).unwrap();
+        assert_eq!(p.point, Point::new(0.0, 0.0));
+        let p = rect.point_at_length(100.0, TOLERANCE).unwrap();
+        assert_eq!(p.point, Point::new(0.0, 0.0));
+        assert_eq!(p.tangent, Vector::new(0.0, -1.0));
+
+        let p = circle
+            .point_at_length(circle.length(0.001) / 4.0, 0.001)
+            .unwrap();
+        assert!((p.point.x.hypot(p.point.y) - 10.0).abs() < 0.01);
+        assert!((p.tangent.x.hypot(p.tangent.y) - 1.0).abs() < 1e-5);
+        assert!((p.point.x * p.tangent.x + p.point.y * p.tangent.y).abs() / 10.0 < 0.02);
+
+        let path = PathData::builder()
+            .begin((0.0, 0.0))
+            .line_to((10.0, 0.0))
+            .quadratic_bezier_to((15.0, 0.0), (20.0, 0.0))
+            .end(FigureEnd::Open)
+            .unwrap()
+            .begin((0.0, 10.0))
+            .line_to((0.0, 20.0))
+            .end(FigureEnd::Open)
+            .unwrap()
+            .close()
+            .unwrap();
+        let path = HollowPath::from(path);
+        assert_eq!(path.length(TOLERANCE), 30.0);
+        let segment = path.segment_at_length(5.0, TOLERANCE).unwrap();
+        assert_eq!((segment.figure, segment.index), (0, 0));
+        let segment = path.segment_at_length(12.0, TOLERANCE).unwrap();
+        assert_eq!((segment.figure, segment.index), (0, 1));
+        assert_eq!(segment.from, Point::new(10.0, 0.0));
+        assert_eq!(segment.start, 10.0);
+        assert!(matches!(segment.segment, PathSegment::QuadraticBezier(_)));
+        let segment = path.segment_at_length(25.0, TOLERANCE).unwrap();
+        assert_eq!((segment.figure, segment.index, segment.start), (1, 0, 20.0));
+        let p = path.point_at_length(25.0, TOLERANCE).unwrap();
+        assert_eq!(p.point, Point::new(0.0, 15.0));
+        assert!(!segment.closing);
+        let segment = rect.segment_at_length(27.0, TOLERANCE).unwrap();
+        assert_eq!(segment.segment.end_point(), Point::new(0.0, 0.0));
+        assert!(segment.closing);
+        assert_eq!(segment.index, rect.figures()[0].segments.len());
+        let segment = rect.segment_at_length(21.0, TOLERANCE).unwrap();
+        assert!(!segment.closing);
+        assert_eq!(segment.index, 2);
+    }
+
+    #[test]
+    fn transformed() {
+        let rect = Rect::new((0.0, 0.0), (10.0, 4.0));
+        let rotated = rect.transformed(&Transform::rotation(90.0, (0.0, 0.0)));
+        assert_eq!(rotated.fill_rule(), FillRule::NonZero);
+        assert_rect(rotated.bounds(), Rect::new((-4.0, 0.0), (4.0, 10.0)));
+        assert!(rotated.fill_contains((-2.0, 8.0), TOLERANCE));
+        assert!(!rotated.fill_contains((2.0, 8.0), TOLERANCE));
+        let scaled = rect.transformed(&Transform::scale(3.0, 3.0));
+        assert_rect(
+            scaled.stroke_bounds(2.0, None, None),
+            Rect::new((-1.0, -1.0), (32.0, 14.0)),
+        );
+
+        let circle = Circle::new((0.0, 0.0), 5.0)
+            .transformed(&Transform::scale(2.0, 1.0).then(&Transform::translation(10.0, 0.0)));
+        assert_rect(circle.bounds(), Rect::new((0.0, -5.0), (20.0, 10.0)));
+
+        let path = PathData::builder()
+            .begin((0.0, 0.0))
+            .line_to((4.0, 0.0))
+            .line_to((4.0, 4.0))
+            .end(FigureEnd::Closed)
+            .unwrap()
+            .fill_rule(FillRule::EvenOdd)
+            .close()
+            .unwrap();
+        let filled = FilledPath::from(path.clone()).transformed(&Transform::translation(1.0, 1.0));
+        assert_eq!(filled.fill_rule(), FillRule::EvenOdd);
+        assert_eq!(filled.path_data().figures()[0].begin, Point::new(1.0, 1.0));
+        let hollow: HollowPath = HollowPath::from(path).transformed(&Transform::scale(2.0, 2.0));
+        assert_eq!(hollow.bounds(), Rect::new((0.0, 0.0), (8.0, 8.0)));
+        assert_eq!(
+            line((1.0, 0.0), (2.0, 0.0)).transformed(&Transform::translation(0.0, 1.0)),
+            line((1.0, 1.0), (2.0, 1.0))
+        );
+        let shape = Shape::Rect(rect).transformed(&Transform::identity());
+        assert!(matches!(shape, Shape::FilledPath(_)));
+        assert_eq!(shape.bounds(), rect);
+    }
+
+    #[test]
+    fn geometry_group() {
+        let mut frame = GeometryGroup::new(FillRule::EvenOdd);
+        frame.push(&Rect::new((0.0, 0.0), (10.0, 10.0)));
+        frame.push(&Circle::new((5.0, 5.0), 3.0));
+        assert_eq!(frame.figures().len(), 2);
+        assert_rect(frame.bounds(), Rect::new((0.0, 0.0), (10.0, 10.0)));
+        assert!(frame.fill_contains((1.0, 1.0), TOLERANCE));
+        assert!(!frame.fill_contains((5.0, 5.0), TOLERANCE));
+        assert!(frame.stroke_contains((5.0, 2.0), 1.0, None, TOLERANCE));
+        let moved = frame.transformed(&Transform::translation(10.0, 0.0));
+        assert_eq!(moved.fill_rule(), FillRule::EvenOdd);
+        assert!(!moved.fill_contains((15.0, 5.0), TOLERANCE));
+        assert!(moved.fill_contains((11.0, 1.0), TOLERANCE));
+        let path = FilledPath::from(moved);
+        assert_eq!(path.path_data().figures().len(), 2);
+        frame.push(&line((20.0, 0.0), (20.0, 10.0)));
+        assert_eq!(frame.figures().len(), 3);
+        assert_eq!(frame.path().path_data().figures().len(), 2);
+        assert_eq!(
+            frame.hollow_path().path_data().figures()[0].end,
+            FigureEnd::Open
+        );
+        assert_rect(frame.bounds(), Rect::new((0.0, 0.0), (20.0, 10.0)));
+        assert!(!frame.fill_contains((15.0, 5.0), TOLERANCE));
+        assert!(frame.stroke_contains((20.0, 5.0), 1.0, None, TOLERANCE));
+        assert!(!frame.stroke_contains((15.0, 10.0), 1.0, None, TOLERANCE));
+
+        let nested = GeometryGroup::new(FillRule::NonZero)
+            .with(&frame)
+            .with(&line((0.0, 20.0), (10.0, 20.0)));
+        assert_eq!(nested.path().path_data().figures().len(), 2);
+        assert_eq!(nested.hollow_path().path_data().figures().len(), 2);
+        assert!(nested
+            .hollow_path()
+            .path_data()
+            .figures()
+            .iter()
+            .all(|figure| figure.end == FigureEnd::Open));
+        assert!(!nested.fill_contains((15.0, 5.0), TOLERANCE));
+        assert!(nested.stroke_contains((20.0, 5.0), 1.0, None, TOLERANCE));
+    }
+
+    #[test]
+    fn fill_contains() {
+        let circle = Circle::new((10.0, 10.0), 5.0);
+        assert!(circle.fill_contains((10.0, 10.0), TOLERANCE));
+        assert!(circle.fill_contains((14.5, 10.0), TOLERANCE));
+        assert!(!circle.fill_contains((14.0, 14.0), TOLERANCE));
+        assert!(!line((0.0, 0.0), (10.0, 0.0)).fill_contains((5.0, 0.0), TOLERANCE));
+        let star = |rule| {
+            PathData::builder()
+                .begin((0.0, 0.0))
+                .line_to((30.0, 0.0))
+                .line_to((30.0, 30.0))
+                .line_to((10.0, 30.0))
+                .line_to((10.0, 10.0))
+                .line_to((20.0, 10.0))
+                .line_to((20.0, 20.0))
+                .line_to((0.0, 20.0))
+                .end(FigureEnd::Closed)
+                .unwrap()
+                .fill_rule(rule)
+                .close()
+                .unwrap()
+        };
+        let even_odd = FilledPath::from(star(FillRule::EvenOdd));
+        let non_zero = FilledPath::from(star(FillRule::NonZero));
+        assert!(!even_odd.fill_contains((15.0, 15.0), TOLERANCE));
+        assert!(non_zero.fill_contains((15.0, 15.0), TOLERANCE));
+        assert!(even_odd.fill_contains((5.0, 5.0), TOLERANCE));
+        assert!(!non_zero.fill_contains((35.0, 5.0), TOLERANCE));
+    }
+
+    #[test]
+    fn stroke_contains() {
+        let rect = Rect::new((0.0, 0.0), (10.0, 10.0));
+        assert!(rect.stroke_contains((0.5, 5.0), 2.0, None, TOLERANCE));
+        assert!(rect.stroke_contains((-0.5, 5.0), 2.0, None, TOLERANCE));
+        assert!(!rect.stroke_contains((5.0, 5.0), 2.0, None, TOLERANCE));
+        assert!(!rect.stroke_contains((-1.5, 5.0), 2.0, None, TOLERANCE));
+        assert!(rect.stroke_contains((-1.0 - TOLERANCE * 0.5, 5.0), 2.0, None, TOLERANCE));
+        assert!(!rect.stroke_contains((-1.0 - TOLERANCE * 2.0, 5.0), 2.0, None, TOLERANCE));
+        let circle = Circle::new((5.0, 5.0), 3.0);
+        assert!(circle.stroke_contains((5.0, 2.0), 1.0, None, TOLERANCE));
+        let line = line((0.0, 0.0), (10.0, 0.0));
+        assert!(!line.stroke_contains((11.0, 0.5), 4.0, None, TOLERANCE));
+        let style = StrokeStyle::new(&StrokeStyleProperties {
+            start_cap: CapStyle::Square,
+            end_cap: CapStyle::Round,
+            ..Default::default()
+        });
+        assert!(line.stroke_contains((-1.5, 1.5), 4.0, Some(&style), TOLERANCE));
+        assert!(line.stroke_contains((11.0, 0.5), 4.0, Some(&style), TOLERANCE));
+        assert!(!line.stroke_contains((11.5, 1.5), 4.0, Some(&style), TOLERANCE));
+        let wedge = PathData::builder()
+            .begin((0.0, 0.0))
+            .line_to((10.0, 1.0))
+            .line_to((0.0, 2.0))
+            .end(FigureEnd::Open)
+            .unwrap()
+            .close()
+            .unwrap();
+        let wedge = HollowPath::from(wedge);
+        assert!(wedge.stroke_contains((12.0, 1.0), 1.0, None, TOLERANCE));
+        let bevel = StrokeStyle::new(&StrokeStyleProperties {
+            line_join: LineJoin::MiterOrBevel(2.0),
+            ..Default::default()
+        });
+        assert!(!wedge.stroke_contains((12.0, 1.0), 1.0, Some(&bevel), TOLERANCE));
+    }
+}
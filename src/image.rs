@@ -1,6 +1,10 @@
 use crate::*;
+#[cfg(windows)]
 use std::path::Path;
-use windows::core::{Interface, GUID, HSTRING};
+use std::sync::Arc;
+#[cfg(windows)]
+use windows::core::{GUID, HSTRING};
+#[cfg(windows)]
 use windows::Win32::{
     Graphics::Direct2D::*, Graphics::Imaging::D2D::*, Graphics::Imaging::*,
     System::SystemServices::*,
@@ -9,20 +13,196 @@ use windows::Win32::{
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(u32)]
 pub enum Interpolation {
-    NearestNeighbor = D2D1_INTERPOLATION_MODE_NEAREST_NEIGHBOR.0,
-    Linear = D2D1_INTERPOLATION_MODE_LINEAR.0,
-    Cubic = D2D1_INTERPOLATION_MODE_CUBIC.0,
-    MultiSampleLinear = D2D1_INTERPOLATION_MODE_MULTI_SAMPLE_LINEAR.0,
-    Anisotropic = D2D1_INTERPOLATION_MODE_ANISOTROPIC.0,
-    HighQualityCubic = D2D1_INTERPOLATION_MODE_HIGH_QUALITY_CUBIC.0,
+    NearestNeighbor = 0,
+    Linear = 1,
+    Cubic = 2,
+    MultiSampleLinear = 3,
+    Anisotropic = 4,
+    HighQualityCubic = 5,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PixelFormat {
+    Rgba8,
+    Bgra8,
+    Rgba16F,
+    A8,
+}
+
+impl PixelFormat {
+    #[inline]
+    pub fn bytes_per_pixel(&self) -> u32 {
+        match self {
+            Self::Rgba8 | Self::Bgra8 => 4,
+            Self::Rgba16F => 8,
+            Self::A8 => 1,
+        }
+    }
+
+    pub(crate) fn load(&self, p: &[u8]) -> [f32; 4] {
+        let unorm = |c: u8| c as f32 / 255.0;
+        match self {
+            Self::Rgba8 => [unorm(p[0]), unorm(p[1]), unorm(p[2]), unorm(p[3])],
+            Self::Bgra8 => [unorm(p[2]), unorm(p[1]), unorm(p[0]), unorm(p[3])],
+            Self::Rgba16F => [0, 2, 4, 6].map(|i| f16_to_f32(u16::from_le_bytes([p[i], p[i + 1]]))),
+            Self::A8 => [0.0, 0.0, 0.0, unorm(p[0])],
+        }
+    }
+
+    fn store(&self, c: [f32; 4], bytes: &mut Vec<u8>) {
+        let unorm = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        match self {
+            Self::Rgba8 => bytes.extend_from_slice(&c.map(unorm)),
+            Self::Bgra8 => bytes.extend_from_slice(&[c[2], c[1], c[0], c[3]].map(unorm)),
+            Self::Rgba16F => c
+                .iter()
+                .for_each(|&c| bytes.extend_from_slice(&f32_to_f16(c).to_le_bytes())),
+            Self::A8 => bytes.push(unorm(c[3])),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum AlphaMode {
+    #[default]
+    Premultiplied,
+    Straight,
+}
+
+fn f16_to_f32(h: u16) -> f32 {
+    let exponent = (h >> 10) & 0x1f;
+    let mantissa = (h & 0x3ff) as u32;
+    let v = match exponent {
+        0 => mantissa as f32 / 16_777_216.0,
+        0x1f => f32::from_bits(0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(((exponent as u32 + 112) << 23) | (mantissa << 13)),
+    };
+    if h & 0x8000 != 0 {
+        -v
+    } else {
+        v
+    }
+}
+
+fn f32_to_f16(v: f32) -> u16 {
+    let sign = ((v.to_bits() >> 16) & 0x8000) as u16;
+    let v = v.abs();
+    let bits = if v.is_nan() {
+        0x7e00
+    } else if v >= 65520.0 {
+        0x7c00
+    } else if v < 6.103_515_6e-5 {
+        (v * 16_777_216.0).round() as u16
+    } else {
+        let bits = v.to_bits();
+        let exponent = (bits >> 23) - 112;
+        let mantissa = bits & 0x7f_ffff;
+        (((exponent << 10) | (mantissa >> 13)) + ((mantissa >> 12) & 1)) as u16
+    };
+    sign | bits
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
-pub struct Image(ID2D1Bitmap1);
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+    pub format: PixelFormat,
+    pub alpha_mode: AlphaMode,
+    pub bytes: Vec<u8>,
+}
+
+impl ImageData {
+    #[inline]
+    pub fn row(&self, y: u32) -> &[u8] {
+        let start = (y * self.stride) as usize;
+        &self.bytes[start..start + (self.width * self.format.bytes_per_pixel()) as usize]
+    }
+
+    #[inline]
+    pub fn pixel(&self, x: u32, y: u32) -> &[u8] {
+        let bpp = self.format.bytes_per_pixel() as usize;
+        let start = x as usize * bpp;
+        &self.row(y)[start..start + bpp]
+    }
+
+    pub fn convert(&self, format: PixelFormat, alpha_mode: AlphaMode) -> ImageData {
+        let stride = self.width * format.bytes_per_pixel();
+        if format == self.format && alpha_mode == self.alpha_mode && stride == self.stride {
+            return self.clone();
+        }
+        let mut bytes = Vec::with_capacity((stride * self.height) as usize);
+        for y in 0..self.height {
+            for p in self
+                .row(y)
+                .chunks_exact(self.format.bytes_per_pixel() as usize)
+            {
+                let mut c = self.format.load(p);
+                let a = c[3];
+                if self.alpha_mode == AlphaMode::Straight {
+                    c[..3].iter_mut().for_each(|c| *c *= a);
+                }
+                if alpha_mode == AlphaMode::Straight {
+                    c[..3].iter_mut().for_each(|c| {
+                        *c = if a > 0.0 { *c / a } else { 0.0 };
+                    });
+                }
+                format.store(c, &mut bytes);
+            }
+        }
+        ImageData {
+            width: self.width,
+            height: self.height,
+            stride,
+            format,
+            alpha_mode,
+            bytes,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn to_straight_rgba(&self) -> Vec<u8> {
+        self.convert(PixelFormat::Rgba8, AlphaMode::Straight).bytes
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let rgba = self.to_straight_rgba();
+        let mut buffer = vec![];
+        let mut encoder = png::Encoder::new(&mut buffer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&rgba))
+            .map_err(|e| Error::Codec(e.to_string()))?;
+        Ok(buffer)
+    }
+}
+
+// The pixels are shared between clones, which compare equal only to each other. On Windows the
+// D2D bitmap is created from them on first use and cached per device context.
+#[derive(Clone, Debug)]
+pub struct Image(pub(crate) Arc<ImageInner>);
+
+#[derive(Debug)]
+pub(crate) struct ImageInner {
+    pub(crate) data: ImageData,
+    #[cfg(windows)]
+    pub(crate) bitmap: crate::d2d1::DeviceCache<ID2D1Bitmap1>,
+}
 
 impl Image {
+    #[inline]
+    pub fn new(data: ImageData) -> Self {
+        Self(Arc::new(ImageInner {
+            data,
+            #[cfg(windows)]
+            bitmap: Default::default(),
+        }))
+    }
+
+    #[cfg(windows)]
     pub(crate) fn from_file(
-        dc: &ID2D1DeviceContext5,
         factory: &IWICImagingFactory2,
         path: impl AsRef<Path>,
     ) -> Result<Image> {
@@ -34,29 +214,101 @@ impl Image {
                 WICDecodeMetadataCacheOnDemand,
             )?;
             let frame = decoder.GetFrame(0)?;
-            let converter = {
-                let converter = factory.CreateFormatConverter()?;
-                let guid = GUID_WICPixelFormat32bppPBGRA;
-                converter.Initialize(
-                    &frame,
-                    &guid,
-                    WICBitmapDitherTypeNone,
-                    None,
-                    1.0,
-                    WICBitmapPaletteTypeMedianCut,
-                )?;
-                converter
-            };
-            let bitmap = dc.CreateBitmapFromWicBitmap(&converter, None)?.cast()?;
-            Ok(Image(bitmap))
+            let converter = factory.CreateFormatConverter()?;
+            let guid = GUID_WICPixelFormat32bppPBGRA;
+            converter.Initialize(
+                &frame,
+                &guid,
+                WICBitmapDitherTypeNone,
+                None,
+                1.0,
+                WICBitmapPaletteTypeMedianCut,
+            )?;
+            let (mut width, mut height) = (0, 0);
+            converter.GetSize(&mut width, &mut height)?;
+            let stride = width * 4;
+            let mut bytes = vec![0; (stride * height) as usize];
+            converter.CopyPixels(std::ptr::null(), stride, &mut bytes)?;
+            Ok(Image::new(ImageData {
+                width,
+                height,
+                stride,
+                format: PixelFormat::Bgra8,
+                alpha_mode: AlphaMode::Premultiplied,
+                bytes,
+            }))
         }
     }
 
+    #[inline]
     pub fn size(&self) -> Size<u32> {
-        unsafe { Wrapper(self.0.GetPixelSize()).into() }
+        Size::new(self.0.data.width, self.0.data.height)
     }
 
-    pub(crate) fn handle(&self) -> &ID2D1Bitmap1 {
-        &self.0
+    #[inline]
+    pub fn data(&self) -> &ImageData {
+        &self.0.data
+    }
+
+    #[inline]
+    pub fn read_pixels(&self) -> Result<ImageData> {
+        Ok(self.0.data.clone())
+    }
+}
+
+impl PartialEq for Image {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Image {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert() {
+        let image = ImageData {
+            width: 2,
+            height: 1,
+            stride: 8,
+            format: PixelFormat::Rgba8,
+            alpha_mode: AlphaMode::Premultiplied,
+            bytes: vec![255, 0, 0, 255, 0, 64, 0, 128],
+        };
+        let straight = image.convert(PixelFormat::Bgra8, AlphaMode::Straight);
+        assert_eq!(straight.stride, 8);
+        assert_eq!(straight.bytes, [0, 0, 255, 255, 0, 128, 0, 128]);
+        assert_eq!(
+            straight.convert(PixelFormat::Rgba8, AlphaMode::Premultiplied),
+            image
+        );
+        let mask = image.convert(PixelFormat::A8, AlphaMode::Premultiplied);
+        assert_eq!(mask.stride, 2);
+        assert_eq!(mask.bytes, [255, 128]);
+        let half = image.convert(PixelFormat::Rgba16F, AlphaMode::Premultiplied);
+        assert_eq!(half.stride, 16);
+        assert_eq!(&half.bytes[..8], &[0x00, 0x3c, 0, 0, 0, 0, 0x00, 0x3c]);
+        assert_eq!(
+            half.convert(PixelFormat::Rgba8, AlphaMode::Premultiplied),
+            image
+        );
+    }
+
+    #[test]
+    fn half_float() {
+        for v in [0.0, 1.0, -2.5, 0.5, 65504.0, 1.0e-5, 1.0 / 3.0] {
+            let h = f32_to_f16(v);
+            assert!(
+                (f16_to_f32(h) - v).abs() <= v.abs() / 1024.0 + 3.0e-8,
+                "{}",
+                v
+            );
+        }
+        assert_eq!(f32_to_f16(1.0e6), 0x7c00);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
     }
 }
@@ -1,27 +1,58 @@
 mod brush;
+mod combine;
 mod context;
+#[cfg(windows)]
 pub mod d2d;
+#[cfg(windows)]
+mod d2d1;
+#[cfg(windows)]
 pub mod d3d11;
+#[cfg(windows)]
 pub mod d3d12;
+pub mod display_list;
 mod error;
+mod flatten;
+mod geometry;
 mod image;
 mod path;
+pub mod pdf;
 mod shape;
+pub mod software;
 mod stroke_style;
+mod stroker;
+pub mod svg;
+mod tessellate;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 mod text;
+pub mod trace;
+mod transform;
+mod truetype;
 mod utility;
 
 pub use brush::*;
+pub use combine::CombineMode;
 pub use context::*;
+#[cfg(windows)]
 pub use d2d::Direct2D;
+#[cfg(windows)]
 pub use d3d11::Direct3D11;
+#[cfg(windows)]
 pub use d3d12::Direct3D12;
+pub use display_list::{DisplayCommand, DisplayList, Recorder};
 pub use error::*;
+pub use flatten::Polyline;
+pub use geometry::*;
 pub use image::*;
 pub use path::*;
+pub use pdf::Pdf;
 pub use shape::*;
+pub use software::Software;
 pub use stroke_style::*;
+pub use tessellate::Mesh;
 pub use text::*;
+pub use trace::{Trace, TraceLog};
+pub use transform::Transform;
 pub use utility::*;
 
 pub type RenderTarget<T> = <T as Backend>::RenderTarget;
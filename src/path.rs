@@ -1,15 +1,302 @@
+use crate::flatten::arc_to_cubics;
 use crate::*;
-use windows::Win32::Graphics::{Direct2D::Common::*, Direct2D::*};
+use std::sync::Arc;
 
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct FilledPath(ID2D1PathGeometry);
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum SweepDirection {
+    CounterClockwise = 0,
+    Clockwise = 1,
+}
 
-impl Fill for FilledPath {
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ArcSegment {
+    pub to: Point<f32>,
+    pub radius: Vector<f32>,
+    pub rotation: f32,
+    pub sweep: SweepDirection,
+    pub large_arc: bool,
+}
+
+impl ArcSegment {
     #[inline]
-    fn fill(&self, dc: &ID2D1DeviceContext5, brush: &ID2D1Brush) {
-        unsafe {
-            dc.FillGeometry(&self.0, brush, None);
+    pub fn to_cubic_beziers(&self, from: impl Into<Point<f32>>) -> Vec<CubicBezierSegment> {
+        arc_to_cubics(from.into(), self)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PathSegment {
+    Line(Point<f32>),
+    QuadraticBezier(QuadraticBezierSegment),
+    CubicBezier(CubicBezierSegment),
+    Arc(ArcSegment),
+}
+
+impl PathSegment {
+    #[inline]
+    pub fn end_point(&self) -> Point<f32> {
+        match self {
+            Self::Line(point) => *point,
+            Self::QuadraticBezier(segment) => segment.to,
+            Self::CubicBezier(segment) => segment.to,
+            Self::Arc(segment) => segment.to,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PathFigure {
+    pub begin: Point<f32>,
+    pub segments: Vec<PathSegment>,
+    pub end: FigureEnd,
+}
+
+impl PathFigure {
+    #[inline]
+    pub fn new(begin: impl Into<Point<f32>>) -> Self {
+        Self {
+            begin: begin.into(),
+            segments: vec![],
+            end: FigureEnd::Open,
+        }
+    }
+
+    pub(crate) fn transform(&self, transform: &Transform) -> Self {
+        let f = |point: Point<f32>| transform.transform_point(point);
+        let cubic = |segment: &CubicBezierSegment| {
+            PathSegment::CubicBezier(CubicBezierSegment {
+                c0: f(segment.c0),
+                c1: f(segment.c1),
+                to: f(segment.to),
+            })
+        };
+        let mut segments = Vec::with_capacity(self.segments.len());
+        let mut current = self.begin;
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Line(point) => segments.push(PathSegment::Line(f(*point))),
+                PathSegment::QuadraticBezier(segment) => {
+                    segments.push(PathSegment::QuadraticBezier(QuadraticBezierSegment {
+                        ctrl: f(segment.ctrl),
+                        to: f(segment.to),
+                    }))
+                }
+                PathSegment::CubicBezier(segment) => segments.push(cubic(segment)),
+                PathSegment::Arc(segment) => {
+                    segments.extend(arc_to_cubics(current, segment).iter().map(cubic))
+                }
+            }
+            current = segment.end_point();
+        }
+        Self {
+            begin: f(self.begin),
+            segments,
+            end: self.end,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PathEvent {
+    Begin(Point<f32>),
+    Segment {
+        from: Point<f32>,
+        segment: PathSegment,
+    },
+    End(FigureEnd),
+}
+
+#[derive(Clone, Debug)]
+pub struct Segments<'a> {
+    figures: &'a [PathFigure],
+    figure: usize,
+    index: usize,
+    current: Point<f32>,
+}
+
+impl Iterator for Segments<'_> {
+    type Item = PathEvent;
+
+    fn next(&mut self) -> Option<PathEvent> {
+        let figure = self.figures.get(self.figure)?;
+        let index = self.index;
+        self.index += 1;
+        if index == 0 {
+            self.current = figure.begin;
+            return Some(PathEvent::Begin(figure.begin));
         }
+        match figure.segments.get(index - 1) {
+            Some(segment) => {
+                let from = self.current;
+                self.current = segment.end_point();
+                Some(PathEvent::Segment {
+                    from,
+                    segment: *segment,
+                })
+            }
+            None => {
+                self.figure += 1;
+                self.index = 0;
+                Some(PathEvent::End(figure.end))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[repr(u32)]
+pub enum FillRule {
+    #[default]
+    EvenOdd = 0,
+    NonZero = 1,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct PathData {
+    figures: Vec<PathFigure>,
+    fill_rule: FillRule,
+}
+
+impl PathData {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn builder() -> PathBuilder<PathData> {
+        PathBuilder::new()
+    }
+
+    #[inline]
+    pub fn figures(&self) -> &[PathFigure] {
+        &self.figures
+    }
+
+    #[inline]
+    pub fn fill_rule(&self) -> FillRule {
+        self.fill_rule
+    }
+
+    #[inline]
+    pub fn set_fill_rule(&mut self, fill_rule: FillRule) {
+        self.fill_rule = fill_rule;
+    }
+
+    #[inline]
+    pub fn push(&mut self, figure: PathFigure) {
+        self.figures.push(figure);
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.figures.is_empty()
+    }
+
+    #[inline]
+    pub fn segments(&self) -> Segments<'_> {
+        Segments {
+            figures: &self.figures,
+            figure: 0,
+            index: 0,
+            current: Point::new(0.0, 0.0),
+        }
+    }
+}
+
+impl std::str::FromStr for PathData {
+    type Err = Error;
+
+    #[inline]
+    fn from_str(d: &str) -> Result<Self> {
+        svg::parse_path_data(d).map(Self::from)
+    }
+}
+
+impl std::fmt::Display for PathData {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&svg::path_data(&self.figures))
+    }
+}
+
+impl From<Vec<PathFigure>> for PathData {
+    #[inline]
+    fn from(figures: Vec<PathFigure>) -> Self {
+        Self {
+            figures,
+            fill_rule: FillRule::default(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a PathData {
+    type Item = &'a PathFigure;
+    type IntoIter = std::slice::Iter<'a, PathFigure>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.figures.iter()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct PathGeometry {
+    pub(crate) data: PathData,
+    #[cfg(windows)]
+    pub(crate) geometry:
+        crate::d2d1::DeviceCache<windows::Win32::Graphics::Direct2D::ID2D1PathGeometry>,
+}
+
+impl PathGeometry {
+    #[inline]
+    fn new(data: PathData) -> Self {
+        Self {
+            data,
+            #[cfg(windows)]
+            geometry: Default::default(),
+        }
+    }
+
+    fn data_mut(this: &mut Arc<Self>) -> &mut PathData {
+        let geometry = Arc::make_mut(this);
+        #[cfg(windows)]
+        {
+            geometry.geometry = Default::default();
+        }
+        &mut geometry.data
+    }
+}
+
+impl PartialEq for PathGeometry {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct FilledPath(pub(crate) Arc<PathGeometry>);
+
+impl FilledPath {
+    #[inline]
+    pub fn path_data(&self) -> &PathData {
+        &self.0.data
+    }
+}
+
+impl From<PathData> for FilledPath {
+    #[inline]
+    fn from(data: PathData) -> Self {
+        Self(Arc::new(PathGeometry::new(data)))
+    }
+}
+
+impl Fill for FilledPath {
+    #[inline]
+    fn fill(&self, renderer: &dyn Renderer, brush: &Brush) {
+        renderer.fill(&Shape::FilledPath(self.clone()), brush);
     }
 }
 
@@ -17,31 +304,128 @@ impl Stroke for FilledPath {
     #[inline]
     fn stroke(
         &self,
-        dc: &ID2D1DeviceContext5,
-        brush: &ID2D1Brush,
+        renderer: &dyn Renderer,
+        brush: &Brush,
         width: f32,
-        style: Option<&ID2D1StrokeStyle>,
+        style: Option<&StrokeStyle>,
     ) {
-        unsafe {
-            dc.DrawGeometry(&self.0, brush, width, style);
-        }
+        renderer.stroke(&Shape::FilledPath(self.clone()), brush, width, style);
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct HollowPath(ID2D1PathGeometry);
+#[derive(Clone, PartialEq, Debug)]
+pub struct HollowPath(pub(crate) Arc<PathGeometry>);
+
+impl HollowPath {
+    #[inline]
+    pub fn path_data(&self) -> &PathData {
+        &self.0.data
+    }
+}
+
+impl From<PathData> for HollowPath {
+    #[inline]
+    fn from(data: PathData) -> Self {
+        Self(Arc::new(PathGeometry::new(data)))
+    }
+}
 
 impl Stroke for HollowPath {
     #[inline]
     fn stroke(
         &self,
-        dc: &ID2D1DeviceContext5,
-        brush: &ID2D1Brush,
+        renderer: &dyn Renderer,
+        brush: &Brush,
         width: f32,
-        style: Option<&ID2D1StrokeStyle>,
+        style: Option<&StrokeStyle>,
     ) {
-        unsafe {
-            dc.DrawGeometry(&self.0, brush, width, style);
+        renderer.stroke(&Shape::HollowPath(self.clone()), brush, width, style);
+    }
+}
+
+// Hollow members such as lines and hollow paths are kept apart so that they stay open: they are
+// stroked with the group but never filled.
+#[derive(Clone, PartialEq, Debug)]
+pub struct GeometryGroup {
+    pub(crate) filled: FilledPath,
+    pub(crate) hollow: HollowPath,
+}
+
+impl GeometryGroup {
+    #[inline]
+    pub fn new(fill_rule: FillRule) -> Self {
+        let mut data = PathData::new();
+        data.set_fill_rule(fill_rule);
+        Self {
+            filled: FilledPath::from(data),
+            hollow: HollowPath::from(PathData::new()),
+        }
+    }
+
+    #[inline]
+    pub fn with(mut self, geometry: &impl Geometry) -> Self {
+        self.push(geometry);
+        self
+    }
+
+    pub fn push(&mut self, geometry: &impl Geometry) {
+        for (path, figures) in [
+            (&mut self.filled.0, geometry.filled_figures()),
+            (&mut self.hollow.0, geometry.hollow_figures()),
+        ] {
+            if figures.is_empty() {
+                continue;
+            }
+            let data = PathGeometry::data_mut(path);
+            for figure in figures {
+                data.push(figure);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn fill_rule(&self) -> FillRule {
+        self.filled.path_data().fill_rule()
+    }
+
+    #[inline]
+    pub fn path(&self) -> &FilledPath {
+        &self.filled
+    }
+
+    #[inline]
+    pub fn hollow_path(&self) -> &HollowPath {
+        &self.hollow
+    }
+}
+
+impl From<GeometryGroup> for FilledPath {
+    #[inline]
+    fn from(group: GeometryGroup) -> Self {
+        group.filled
+    }
+}
+
+impl Fill for GeometryGroup {
+    #[inline]
+    fn fill(&self, renderer: &dyn Renderer, brush: &Brush) {
+        self.filled.fill(renderer, brush);
+    }
+}
+
+impl Stroke for GeometryGroup {
+    fn stroke(
+        &self,
+        renderer: &dyn Renderer,
+        brush: &Brush,
+        width: f32,
+        style: Option<&StrokeStyle>,
+    ) {
+        if !self.filled.path_data().is_empty() {
+            self.filled.stroke(renderer, brush, width, style);
+        }
+        if !self.hollow.path_data().is_empty() {
+            self.hollow.stroke(renderer, brush, width, style);
         }
     }
 }
@@ -49,18 +433,18 @@ impl Stroke for HollowPath {
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(u32)]
 pub enum FigureEnd {
-    Open = D2D1_FIGURE_END_OPEN.0,
-    Closed = D2D1_FIGURE_END_CLOSED.0,
+    Open = 0,
+    Closed = 1,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 #[repr(C)]
 pub struct QuadraticBezierSegment {
     pub ctrl: Point<f32>,
     pub to: Point<f32>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 #[repr(C)]
 pub struct CubicBezierSegment {
     pub c0: Point<f32>,
@@ -69,159 +453,317 @@ pub struct CubicBezierSegment {
 }
 
 pub struct Figure<T> {
-    geometry: ID2D1PathGeometry,
-    sink: ID2D1GeometrySink,
-    _t: std::marker::PhantomData<T>,
+    builder: PathBuilder<T>,
+    current: PathFigure,
 }
 
 impl<T> Figure<T> {
     #[inline]
-    pub fn line_to(self, point: impl Into<Point<f32>>) -> Self {
-        unsafe {
-            self.sink.AddLine(Wrapper(point.into()).into());
-        }
+    pub fn line_to(mut self, point: impl Into<Point<f32>>) -> Self {
+        self.current.segments.push(PathSegment::Line(point.into()));
         self
     }
 
     #[inline]
-    pub fn lines(self, points: &[Point<f32>]) -> Self {
-        unsafe {
-            let lines =
-                std::slice::from_raw_parts(points.as_ptr() as *const D2D_POINT_2F, points.len());
-            self.sink.AddLines(lines);
-        }
+    pub fn lines(mut self, points: &[Point<f32>]) -> Self {
+        self.current
+            .segments
+            .extend(points.iter().map(|&point| PathSegment::Line(point)));
         self
     }
 
     #[inline]
     pub fn quadratic_bezier_to(
-        self,
+        mut self,
         ctrl: impl Into<Point<f32>>,
         to: impl Into<Point<f32>>,
     ) -> Self {
-        unsafe {
-            self.sink
-                .AddQuadraticBezier(&D2D1_QUADRATIC_BEZIER_SEGMENT {
-                    point1: Wrapper(ctrl.into()).into(),
-                    point2: Wrapper(to.into()).into(),
-                });
-        }
+        self.current
+            .segments
+            .push(PathSegment::QuadraticBezier(QuadraticBezierSegment {
+                ctrl: ctrl.into(),
+                to: to.into(),
+            }));
         self
     }
 
     #[inline]
-    pub fn quadratic_beziers(self, segments: &[QuadraticBezierSegment]) -> Self {
-        unsafe {
-            let segments = std::slice::from_raw_parts(
-                segments.as_ptr() as *const D2D1_QUADRATIC_BEZIER_SEGMENT,
-                segments.len(),
-            );
-            self.sink.AddQuadraticBeziers(segments);
-        }
+    pub fn quadratic_beziers(mut self, segments: &[QuadraticBezierSegment]) -> Self {
+        self.current.segments.extend(
+            segments
+                .iter()
+                .map(|&segment| PathSegment::QuadraticBezier(segment)),
+        );
         self
     }
 
     #[inline]
     pub fn cubic_bezier_to(
-        self,
+        mut self,
         c0: impl Into<Point<f32>>,
         c1: impl Into<Point<f32>>,
         to: impl Into<Point<f32>>,
     ) -> Self {
-        unsafe {
-            self.sink.AddBezier(&D2D1_BEZIER_SEGMENT {
-                point1: Wrapper(c0.into()).into(),
-                point2: Wrapper(c1.into()).into(),
-                point3: Wrapper(to.into()).into(),
-            });
-        }
+        self.current
+            .segments
+            .push(PathSegment::CubicBezier(CubicBezierSegment {
+                c0: c0.into(),
+                c1: c1.into(),
+                to: to.into(),
+            }));
         self
     }
 
     #[inline]
-    pub fn cubic_beziers(self, segments: &[CubicBezierSegment]) -> Self {
-        unsafe {
-            let segments = std::slice::from_raw_parts(
-                segments.as_ptr() as *const D2D1_BEZIER_SEGMENT,
-                segments.len(),
-            );
-            self.sink.AddBeziers(segments);
-        }
+    pub fn cubic_beziers(mut self, segments: &[CubicBezierSegment]) -> Self {
+        self.current.segments.extend(
+            segments
+                .iter()
+                .map(|&segment| PathSegment::CubicBezier(segment)),
+        );
         self
     }
 
     #[inline]
-    pub fn end(self, end: FigureEnd) -> Result<PathBuilder<T>> {
-        unsafe {
-            self.sink.EndFigure(D2D1_FIGURE_END(end as _));
-        }
-        Ok(PathBuilder {
-            geometry: self.geometry,
-            sink: self.sink,
-            _t: std::marker::PhantomData,
-        })
+    pub fn arc_to(
+        mut self,
+        to: impl Into<Point<f32>>,
+        radius: impl Into<Vector<f32>>,
+        rotation: f32,
+        sweep: SweepDirection,
+        large_arc: bool,
+    ) -> Self {
+        self.current.segments.push(PathSegment::Arc(ArcSegment {
+            to: to.into(),
+            radius: radius.into(),
+            rotation,
+            sweep,
+            large_arc,
+        }));
+        self
+    }
+
+    #[inline]
+    pub fn end(mut self, end: FigureEnd) -> Result<PathBuilder<T>> {
+        self.current.end = end;
+        self.builder.data.push(self.current);
+        Ok(self.builder)
     }
 }
 
 pub struct PathBuilder<T> {
-    geometry: ID2D1PathGeometry,
-    sink: ID2D1GeometrySink,
+    data: PathData,
     _t: std::marker::PhantomData<T>,
 }
 
 impl<T> PathBuilder<T> {
-    pub(crate) fn new(geometry: ID2D1PathGeometry) -> Result<Self> {
-        let sink = unsafe { geometry.Open()? };
-        Ok(Self {
-            geometry,
-            sink,
+    pub(crate) fn new() -> Self {
+        Self {
+            data: PathData::new(),
             _t: std::marker::PhantomData,
-        })
+        }
     }
-}
 
-impl PathBuilder<FilledPath> {
     #[inline]
-    pub fn begin(self, start: impl Into<Point<f32>>) -> Figure<FilledPath> {
-        unsafe {
-            self.sink
-                .BeginFigure(Wrapper(start.into()).into(), D2D1_FIGURE_BEGIN_FILLED);
-        }
+    pub fn svg(mut self, d: &str) -> Result<Self> {
+        self.data.figures.extend(svg::parse_path_data(d)?);
+        Ok(self)
+    }
+
+    #[inline]
+    pub fn begin(self, start: impl Into<Point<f32>>) -> Figure<T> {
         Figure {
-            geometry: self.geometry,
-            sink: self.sink,
-            _t: std::marker::PhantomData,
+            builder: self,
+            current: PathFigure::new(start),
         }
     }
+}
+
+impl PathBuilder<FilledPath> {
+    #[inline]
+    pub fn fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.data.fill_rule = fill_rule;
+        self
+    }
 
     #[inline]
     pub fn close(self) -> Result<FilledPath> {
-        unsafe {
-            self.sink.Close()?;
-        }
-        Ok(FilledPath(self.geometry))
+        Ok(self.data.into())
     }
 }
 
 impl PathBuilder<HollowPath> {
     #[inline]
-    pub fn begin(self, start: impl Into<Point<f32>>) -> Figure<HollowPath> {
-        unsafe {
-            self.sink
-                .BeginFigure(Wrapper(start.into()).into(), D2D1_FIGURE_BEGIN_HOLLOW);
-        }
-        Figure {
-            geometry: self.geometry,
-            sink: self.sink,
-            _t: std::marker::PhantomData,
-        }
+    pub fn close(self) -> Result<HollowPath> {
+        Ok(self.data.into())
     }
+}
 
+impl PathBuilder<PathData> {
     #[inline]
-    pub fn close(self) -> Result<HollowPath> {
-        unsafe {
-            self.sink.Close()?;
+    pub fn fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.data.fill_rule = fill_rule;
+        self
+    }
+
+    #[inline]
+    pub fn close(self) -> Result<PathData> {
+        Ok(self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_data() {
+        let data = PathData::builder()
+            .begin((0.0, 0.0))
+            .line_to((10.0, 0.0))
+            .quadratic_bezier_to((10.0, 10.0), (0.0, 10.0))
+            .end(FigureEnd::Closed)
+            .unwrap()
+            .begin((20.0, 0.0))
+            .cubic_bezier_to((25.0, 5.0), (30.0, 5.0), (35.0, 0.0))
+            .end(FigureEnd::Open)
+            .unwrap()
+            .close()
+            .unwrap();
+        assert_eq!(data.figures().len(), 2);
+        let events = data.segments().collect::<Vec<_>>();
+        assert_eq!(events.len(), 7);
+        assert_eq!(events[0], PathEvent::Begin(Point::new(0.0, 0.0)));
+        assert_eq!(
+            events[2],
+            PathEvent::Segment {
+                from: Point::new(10.0, 0.0),
+                segment: PathSegment::QuadraticBezier(QuadraticBezierSegment {
+                    ctrl: Point::new(10.0, 10.0),
+                    to: Point::new(0.0, 10.0),
+                }),
+            }
+        );
+        assert_eq!(events[3], PathEvent::End(FigureEnd::Closed));
+        assert_eq!(events[4], PathEvent::Begin(Point::new(20.0, 0.0)));
+        assert_eq!(events[6], PathEvent::End(FigureEnd::Open));
+        let path = FilledPath::from(data.clone());
+        assert_eq!(path.path_data(), &data);
+        let factory = Context::new(Software::new()).unwrap().create_factory();
+        let built = factory
+            .create_hollow_path()
+            .unwrap()
+            .begin((0.0, 0.0))
+            .line_to((10.0, 0.0))
+            .end(FigureEnd::Open)
+            .unwrap()
+            .close()
+            .unwrap();
+        let mut figure = PathFigure::new((0.0, 0.0));
+        figure
+            .segments
+            .push(PathSegment::Line(Point::new(10.0, 0.0)));
+        let mut expected = PathData::new();
+        expected.push(figure);
+        assert_eq!(built.path_data(), &expected);
+        assert_eq!(HollowPath::from(expected), built);
+    }
+
+    #[test]
+    fn arc_segment() {
+        let mut figure = PathFigure::new((0.0, 0.0));
+        figure.segments.push(PathSegment::Arc(ArcSegment {
+            to: Point::new(20.0, 0.0),
+            radius: Vector::new(10.0, 10.0),
+            rotation: 0.0,
+            sweep: SweepDirection::Clockwise,
+            large_arc: false,
+        }));
+        let transformed = figure.transform(&Transform::identity());
+        assert_eq!(transformed.segments.len(), 2);
+        let PathSegment::CubicBezier(first) = transformed.segments[0] else {
+            panic!("{:?}", transformed.segments[0]);
+        };
+        assert!((first.to.x - 10.0).abs() < 1.0e-4);
+        assert!((first.to.y + 10.0).abs() < 1.0e-4);
+        assert_eq!(transformed.segments[1].end_point(), Point::new(20.0, 0.0));
+    }
+
+    #[test]
+    fn arc_to() {
+        let on_circle =
+            |from: Point<f32>, cubics: &[CubicBezierSegment], center: (f32, f32), r: f32| {
+                let mut p0 = from;
+                cubics.iter().all(|c| {
+                    let ok = (0..=8).all(|i| {
+                        let t = i as f32 / 8.0;
+                        let u = 1.0 - t;
+                        let x = u * u * u * p0.x
+                            + 3.0 * u * u * t * c.c0.x
+                            + 3.0 * u * t * t * c.c1.x
+                            + t * t * t * c.to.x;
+                        let y = u * u * u * p0.y
+                            + 3.0 * u * u * t * c.c0.y
+                            + 3.0 * u * t * t * c.c1.y
+                            + t * t * t * c.to.y;
+                        ((x - center.0).hypot(y - center.1) - r).abs() < 0.01
+                    });
+                    p0 = c.to;
+                    ok
+                })
+            };
+        let cases = [
+            (SweepDirection::Clockwise, false, (0.0, 10.0), 1),
+            (SweepDirection::CounterClockwise, false, (10.0, 0.0), 1),
+            (SweepDirection::Clockwise, true, (10.0, 0.0), 3),
+            (SweepDirection::CounterClockwise, true, (0.0, 10.0), 3),
+        ];
+        for (sweep, large_arc, center, count) in cases {
+            let path = PathData::builder()
+                .begin((0.0, 0.0))
+                .arc_to((10.0, 10.0), (10.0, 10.0), 0.0, sweep, large_arc)
+                .end(FigureEnd::Open)
+                .unwrap()
+                .close()
+                .unwrap();
+            let PathSegment::Arc(arc) = path.figures()[0].segments[0] else {
+                panic!();
+            };
+            let cubics = arc.to_cubic_beziers((0.0, 0.0));
+            assert_eq!(cubics.len(), count, "{:?} {}", sweep, large_arc);
+            assert!(on_circle(Point::new(0.0, 0.0), &cubics, center, 10.0));
+            assert_eq!(cubics[count - 1].to, Point::new(10.0, 10.0));
+        }
+        let small = ArcSegment {
+            to: Point::new(10.0, 10.0),
+            radius: Vector::new(1.0, 1.0),
+            rotation: 0.0,
+            sweep: SweepDirection::Clockwise,
+            large_arc: false,
+        };
+        let cubics = small.to_cubic_beziers((0.0, 0.0));
+        assert_eq!(cubics.len(), 2);
+        assert!(on_circle(
+            Point::new(0.0, 0.0),
+            &cubics,
+            (5.0, 5.0),
+            50.0f32.sqrt()
+        ));
+        let rotated = ArcSegment {
+            to: Point::new(0.0, 20.0),
+            radius: Vector::new(10.0, 5.0),
+            rotation: 90.0,
+            ..small
+        };
+        let cubics = rotated.to_cubic_beziers((0.0, 0.0));
+        assert!(cubics
+            .iter()
+            .all(|c| (c.to.x.abs() - 5.0).abs() < 1.0e-3 || c.to.x.abs() < 1.0e-3));
+        assert!(ArcSegment {
+            to: Point::new(0.0, 0.0),
+            ..small
         }
-        Ok(HollowPath(self.geometry))
+        .to_cubic_beziers((0.0, 0.0))
+        .is_empty());
     }
 }
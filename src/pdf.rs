@@ -0,0 +1,1348 @@
+use crate::flatten::TOLERANCE;
+use crate::flatten::{arc_to_cubics, quadratic_to_cubic};
+use crate::stroker::{dash_pattern, native_cap, LineCap};
+#[cfg(windows)]
+use crate::text::{font_face_name, font_file_data};
+#[cfg(windows)]
+use crate::truetype::FontFile;
+use crate::*;
+use std::collections::BTreeMap;
+#[cfg(windows)]
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::sync::Mutex;
+#[cfg(windows)]
+use windows::Win32::Graphics::DirectWrite::IDWriteFontFace;
+
+const POINTS_PER_DIP: f32 = 0.75;
+const PAGES_ID: usize = 1;
+const CATALOG_ID: usize = 2;
+const MAX_REPEAT: f32 = 256.0;
+
+pub fn document(size: impl Into<Size<f32>>, f: impl FnOnce(&DrawCommand)) -> Vec<u8> {
+    DisplayList::record(f).to_pdf(size)
+}
+
+impl DisplayList {
+    pub fn to_pdf(&self, size: impl Into<Size<f32>>) -> Vec<u8> {
+        let mut document = Document::new();
+        document.page(size.into(), self);
+        document.to_bytes()
+    }
+}
+
+fn num(v: f32) -> String {
+    if !v.is_finite() {
+        return "0".to_string();
+    }
+    let s = format!("{:.4}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+fn matrix(t: &Transform) -> String {
+    [t.m11, t.m12, t.m21, t.m22, t.m31, t.m32]
+        .map(num)
+        .join(" ")
+}
+
+fn rgb(color: Rgba<f32>) -> String {
+    [color.r, color.g, color.b]
+        .map(|c| num(c.clamp(0.0, 1.0)))
+        .join(" ")
+}
+
+fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+    let data = miniz_oxide::deflate::compress_to_vec_zlib(data, 6);
+    let mut object = format!(
+        "<< {} /Filter /FlateDecode /Length {} >>\nstream\n",
+        dict,
+        data.len()
+    )
+    .into_bytes();
+    object.extend_from_slice(&data);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+#[derive(Clone, Debug, Default)]
+struct Objects(Vec<Vec<u8>>);
+
+impl Objects {
+    #[inline]
+    fn reserve(&mut self) -> usize {
+        self.0.push(vec![]);
+        self.0.len()
+    }
+
+    #[inline]
+    fn set(&mut self, id: usize, object: impl Into<Vec<u8>>) {
+        self.0[id - 1] = object.into();
+    }
+
+    #[inline]
+    fn add(&mut self, object: impl Into<Vec<u8>>) -> usize {
+        self.0.push(object.into());
+        self.0.len()
+    }
+}
+
+#[cfg(windows)]
+#[derive(Debug)]
+struct EmbeddedFont {
+    id: usize,
+    name: String,
+    data: Vec<u8>,
+    index: u32,
+    glyphs: BTreeMap<u16, String>,
+}
+
+// Static TrueType fonts are subset to the glyphs used. CFF fonts are embedded whole as
+// OpenType, and variable fonts are embedded whole too, so viewers draw their default instance.
+#[cfg(windows)]
+impl EmbeddedFont {
+    fn write(&self, objects: &mut Objects) {
+        let Some(font) = FontFile::parse(&self.data, self.index) else {
+            return;
+        };
+        let scale = 1000.0 / font.units_per_em() as f32;
+        let units = |v: i16| num(v as f32 * scale);
+        let glyphs = self.glyphs.keys().copied().collect::<BTreeSet<_>>();
+        let subset = font.subset(&glyphs);
+        let base_font = match &subset {
+            Some(_) => {
+                let tag = (0..6)
+                    .map(|i| (b'A' + ((self.id * 7 + i * 3) % 26) as u8) as char)
+                    .collect::<String>();
+                format!("{}+{}", tag, self.name)
+            }
+            None => self.name.clone(),
+        };
+        let Some(data) = subset.or_else(|| font.face()) else {
+            return;
+        };
+        let (file_key, file, subtype) = if font.is_cff() {
+            (
+                "FontFile3",
+                stream("/Subtype /OpenType", &data),
+                "CIDFontType0",
+            )
+        } else {
+            (
+                "FontFile2",
+                stream(&format!("/Length1 {}", data.len()), &data),
+                "CIDFontType2",
+            )
+        };
+        let file = objects.add(file);
+        let bbox = font.bbox().map(units).join(" ");
+        let descriptor = objects.add(format!(
+            "<< /Type /FontDescriptor /FontName /{} /Flags 4 /FontBBox [{}] /ItalicAngle 0 /Ascent {} /Descent {} /CapHeight {} /StemV 80 /{} {} 0 R >>",
+            base_font,
+            bbox,
+            units(font.ascender()),
+            units(font.descender()),
+            units(font.ascender()),
+            file_key,
+            file
+        ));
+        let widths = glyphs
+            .iter()
+            .map(|&glyph| format!("{} [{}]", glyph, num(font.advance(glyph) as f32 * scale)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let cid_to_gid = if subtype == "CIDFontType2" {
+            " /CIDToGIDMap /Identity"
+        } else {
+            ""
+        };
+        let descendant = objects.add(format!(
+            "<< /Type /Font /Subtype /{} /BaseFont /{} /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> /FontDescriptor {} 0 R /W [{}]{} >>",
+            subtype, base_font, descriptor, widths, cid_to_gid
+        ));
+        let to_unicode = objects.add(stream("", self.to_unicode().as_bytes()));
+        objects.set(
+            self.id,
+            format!(
+                "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+                base_font, descendant, to_unicode
+            ),
+        );
+    }
+
+    fn to_unicode(&self) -> String {
+        let mut cmap = String::new();
+        cmap.push_str("/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n");
+        cmap.push_str("/CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n");
+        cmap.push_str("/CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n");
+        cmap.push_str("1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n");
+        let glyphs = self
+            .glyphs
+            .iter()
+            .filter(|(_, text)| !text.is_empty())
+            .collect::<Vec<_>>();
+        for chunk in glyphs.chunks(100) {
+            writeln!(cmap, "{} beginbfchar", chunk.len()).unwrap();
+            for (glyph, text) in chunk {
+                let utf16 = text
+                    .encode_utf16()
+                    .map(|u| format!("{:04X}", u))
+                    .collect::<String>();
+                writeln!(cmap, "<{:04X}> <{}>", glyph, utf16).unwrap();
+            }
+            cmap.push_str("endbfchar\n");
+        }
+        cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+        cmap
+    }
+}
+
+#[derive(Debug)]
+struct Document {
+    objects: Objects,
+    pages: Vec<usize>,
+    images: Vec<(Image, bool, usize)>,
+    #[cfg(windows)]
+    fonts: Vec<EmbeddedFont>,
+}
+
+impl Document {
+    fn new() -> Self {
+        let mut objects = Objects::default();
+        objects.reserve();
+        objects.reserve();
+        Self {
+            objects,
+            pages: vec![],
+            images: vec![],
+            #[cfg(windows)]
+            fonts: vec![],
+        }
+    }
+
+    fn page(&mut self, size: Size<f32>, display_list: &DisplayList) {
+        let mut writer = Writer::new(self, size);
+        for command in display_list {
+            writer.command(command);
+        }
+        writer.finish();
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut objects = self.objects.clone();
+        #[cfg(windows)]
+        for font in &self.fonts {
+            font.write(&mut objects);
+        }
+        let kids = self
+            .pages
+            .iter()
+            .map(|id| format!("{} 0 R", id))
+            .collect::<Vec<_>>()
+            .join(" ");
+        objects.set(
+            PAGES_ID,
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids,
+                self.pages.len()
+            ),
+        );
+        objects.set(
+            CATALOG_ID,
+            format!("<< /Type /Catalog /Pages {} 0 R >>", PAGES_ID),
+        );
+        let mut pdf = b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.0.len());
+        for (i, object) in objects.0.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = pdf.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.0.len() + 1);
+        for offset in offsets {
+            writeln!(trailer, "{:010} 00000 n ", offset).unwrap();
+        }
+        write!(
+            trailer,
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.0.len() + 1,
+            CATALOG_ID,
+            xref
+        )
+        .unwrap();
+        pdf.extend_from_slice(trailer.as_bytes());
+        pdf
+    }
+}
+
+#[derive(Default)]
+struct Resources {
+    ext_g_states: BTreeMap<String, String>,
+    patterns: BTreeMap<String, usize>,
+    x_objects: BTreeMap<String, usize>,
+    #[cfg(windows)]
+    fonts: BTreeMap<String, usize>,
+}
+
+impl Resources {
+    fn ext_g_state(&mut self, dict: String) -> String {
+        if let Some((name, _)) = self.ext_g_states.iter().find(|(_, d)| **d == dict) {
+            return name.clone();
+        }
+        let name = format!("GS{}", self.ext_g_states.len());
+        self.ext_g_states.insert(name.clone(), dict);
+        name
+    }
+
+    fn to_dict(&self) -> String {
+        let refs = |map: &BTreeMap<String, usize>| {
+            map.iter()
+                .map(|(name, id)| format!("/{} {} 0 R", name, id))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let mut dict = String::from("<<");
+        if !self.ext_g_states.is_empty() {
+            let states = self
+                .ext_g_states
+                .iter()
+                .map(|(name, d)| format!("/{} {}", name, d))
+                .collect::<Vec<_>>()
+                .join(" ");
+            write!(dict, " /ExtGState << {} >>", states).unwrap();
+        }
+        if !self.patterns.is_empty() {
+            write!(dict, " /Pattern << {} >>", refs(&self.patterns)).unwrap();
+        }
+        if !self.x_objects.is_empty() {
+            write!(dict, " /XObject << {} >>", refs(&self.x_objects)).unwrap();
+        }
+        #[cfg(windows)]
+        if !self.fonts.is_empty() {
+            write!(dict, " /Font << {} >>", refs(&self.fonts)).unwrap();
+        }
+        dict.push_str(" >>");
+        dict
+    }
+}
+
+struct Shading<'a> {
+    shading_type: u32,
+    coords: String,
+    stops: &'a [GradientStop],
+    first: i32,
+    count: usize,
+    mirror: bool,
+    transform: Transform,
+}
+
+struct Writer<'a> {
+    document: &'a mut Document,
+    size: Size<f32>,
+    base: Transform,
+    content: String,
+    resources: Resources,
+    transform: Transform,
+    clip_depth: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn new(document: &'a mut Document, size: Size<f32>) -> Self {
+        Self {
+            document,
+            size,
+            base: Transform::scale(POINTS_PER_DIP, -POINTS_PER_DIP)
+                * Transform::translation(0.0, size.height * POINTS_PER_DIP),
+            content: String::new(),
+            resources: Resources::default(),
+            transform: Transform::identity(),
+            clip_depth: 0,
+        }
+    }
+
+    fn path(&mut self, figures: &[PathFigure]) {
+        let c = &mut self.content;
+        for figure in figures {
+            writeln!(c, "{} {} m", num(figure.begin.x), num(figure.begin.y)).unwrap();
+            let mut current = figure.begin;
+            for segment in &figure.segments {
+                let cubics = match segment {
+                    PathSegment::Line(p) => {
+                        writeln!(c, "{} {} l", num(p.x), num(p.y)).unwrap();
+                        vec![]
+                    }
+                    PathSegment::QuadraticBezier(s) => vec![quadratic_to_cubic(current, s)],
+                    PathSegment::CubicBezier(s) => vec![*s],
+                    PathSegment::Arc(s) => arc_to_cubics(current, s),
+                };
+                for s in cubics {
+                    writeln!(
+                        c,
+                        "{} {} {} {} {} {} c",
+                        num(s.c0.x),
+                        num(s.c0.y),
+                        num(s.c1.x),
+                        num(s.c1.y),
+                        num(s.to.x),
+                        num(s.to.y)
+                    )
+                    .unwrap();
+                }
+                current = segment.end_point();
+            }
+            if figure.end == FigureEnd::Closed {
+                c.push_str("h\n");
+            }
+        }
+    }
+
+    fn gradient_function(&mut self, stops: &[GradientStop], alpha: bool) -> usize {
+        let mut stops = stops
+            .iter()
+            .map(|stop| GradientStop::new(stop.position.clamp(0.0, 1.0), stop.color))
+            .collect::<Vec<_>>();
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        let (Some(first), Some(last)) = (stops.first().copied(), stops.last().copied()) else {
+            let c = if alpha { "0" } else { "0 0 0" };
+            return self.document.objects.add(format!(
+                "<< /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1 >>",
+                c, c
+            ));
+        };
+        if first.position > 0.0 {
+            stops.insert(0, GradientStop::new(0.0, first.color));
+        }
+        if last.position < 1.0 || stops.len() == 1 {
+            stops.push(GradientStop::new(1.0, last.color));
+        }
+        let component = |color: Rgba<f32>| {
+            if alpha {
+                num(color.a.clamp(0.0, 1.0))
+            } else {
+                rgb(color)
+            }
+        };
+        let functions = stops
+            .windows(2)
+            .map(|w| {
+                format!(
+                    "<< /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1 >>",
+                    component(w[0].color),
+                    component(w[1].color)
+                )
+            })
+            .collect::<Vec<_>>();
+        if functions.len() == 1 {
+            return self.document.objects.add(functions[0].clone());
+        }
+        let bounds = stops[1..stops.len() - 1]
+            .iter()
+            .map(|stop| num(stop.position))
+            .collect::<Vec<_>>();
+        self.document.objects.add(format!(
+            "<< /FunctionType 3 /Domain [0 1] /Functions [{}] /Bounds [{}] /Encode [{}] >>",
+            functions.join(" "),
+            bounds.join(" "),
+            vec!["0 1"; functions.len()].join(" ")
+        ))
+    }
+
+    fn repeat_function(
+        &mut self,
+        function: usize,
+        first: i32,
+        count: usize,
+        mirror: bool,
+    ) -> usize {
+        if count <= 1 && (!mirror || first.rem_euclid(2) == 0) {
+            return function;
+        }
+        let functions = vec![format!("{} 0 R", function); count];
+        let bounds = (1..count)
+            .map(|i| num(i as f32 / count as f32))
+            .collect::<Vec<_>>();
+        let encode = (0..count)
+            .map(|i| {
+                if mirror && (first + i as i32).rem_euclid(2) == 1 {
+                    "1 0"
+                } else {
+                    "0 1"
+                }
+            })
+            .collect::<Vec<_>>();
+        self.document.objects.add(format!(
+            "<< /FunctionType 3 /Domain [0 1] /Functions [{}] /Bounds [{}] /Encode [{}] >>",
+            functions.join(" "),
+            bounds.join(" "),
+            encode.join(" ")
+        ))
+    }
+
+    fn page_corners(&self, transform: &Transform) -> Vec<Point<f32>> {
+        let Some(inverse) = transform.invert() else {
+            return vec![];
+        };
+        [
+            Point::new(0.0, 0.0),
+            Point::new(self.size.width, 0.0),
+            Point::new(self.size.width, self.size.height),
+            Point::new(0.0, self.size.height),
+        ]
+        .into_iter()
+        .map(|p| inverse.transform_point(p))
+        .collect()
+    }
+
+    fn shading<'b>(&self, brush: &'b Brush) -> Option<Shading<'b>> {
+        match brush {
+            Brush::SolidColor(_) => None,
+            Brush::LinearGradient(brush) => {
+                let stops = brush.stops();
+                let (start, end) = (brush.start(), brush.end());
+                let d = Point::new(end.x - start.x, end.y - start.y);
+                let len2 = d.x * d.x + d.y * d.y;
+                let (t0, t1) = if stops.mode() == GradientMode::Clamp || len2 <= 0.0 {
+                    (0.0, 1.0)
+                } else {
+                    let ts = self
+                        .page_corners(&self.transform)
+                        .into_iter()
+                        .map(|p| ((p.x - start.x) * d.x + (p.y - start.y) * d.y) / len2)
+                        .collect::<Vec<_>>();
+                    let min = ts.iter().copied().fold(0.0f32, f32::min).floor();
+                    let max = ts.iter().copied().fold(1.0f32, f32::max).ceil();
+                    (min.max(-MAX_REPEAT), max.min(MAX_REPEAT))
+                };
+                Some(Shading {
+                    shading_type: 2,
+                    coords: [
+                        start.x + d.x * t0,
+                        start.y + d.y * t0,
+                        start.x + d.x * t1,
+                        start.y + d.y * t1,
+                    ]
+                    .map(num)
+                    .join(" "),
+                    stops: stops.stops(),
+                    first: t0 as i32,
+                    count: (t1 - t0) as usize,
+                    mirror: stops.mode() == GradientMode::Mirror,
+                    transform: self.transform,
+                })
+            }
+            Brush::RadialGradient(brush) => {
+                let stops = brush.stops();
+                let ellipse = brush.ellipse();
+                let offset = brush.offset();
+                let unit = Transform::scale(ellipse.radius.x, ellipse.radius.y)
+                    * Transform::translation(ellipse.center.x, ellipse.center.y);
+                let focal = Point::new(offset.x / ellipse.radius.x, offset.y / ellipse.radius.y);
+                let focal = if focal.x.is_finite() && focal.y.is_finite() {
+                    focal
+                } else {
+                    Point::new(0.0, 0.0)
+                };
+                let k = if stops.mode() == GradientMode::Clamp {
+                    1.0
+                } else {
+                    let f = focal.x.hypot(focal.y);
+                    let max = self
+                        .page_corners(&(unit * self.transform))
+                        .into_iter()
+                        .map(|p| (p.x - focal.x).hypot(p.y - focal.y))
+                        .fold(1.0f32, f32::max);
+                    (max / (1.0 - f).max(1e-3)).ceil().clamp(1.0, MAX_REPEAT)
+                };
+                Some(Shading {
+                    shading_type: 3,
+                    coords: [
+                        focal.x,
+                        focal.y,
+                        0.0,
+                        focal.x * (1.0 - k),
+                        focal.y * (1.0 - k),
+                        k,
+                    ]
+                    .map(num)
+                    .join(" "),
+                    stops: stops.stops(),
+                    first: 0,
+                    count: k as usize,
+                    mirror: stops.mode() == GradientMode::Mirror,
+                    transform: unit * self.transform,
+                })
+            }
+        }
+    }
+
+    fn shading_dict(&mut self, shading: &Shading, alpha: bool) -> String {
+        let base = self.gradient_function(shading.stops, alpha);
+        let function = self.repeat_function(base, shading.first, shading.count, shading.mirror);
+        format!(
+            "<< /ShadingType {} /ColorSpace /{} /Coords [{}] /Function {} 0 R /Extend [true true] >>",
+            shading.shading_type,
+            if alpha { "DeviceGray" } else { "DeviceRGB" },
+            shading.coords,
+            function
+        )
+    }
+
+    fn pattern(&mut self, shading: &Shading) -> usize {
+        let dict = self.shading_dict(shading, false);
+        self.document.objects.add(format!(
+            "<< /Type /Pattern /PatternType 2 /Shading {} /Matrix [{}] >>",
+            dict,
+            matrix(&(shading.transform * self.base))
+        ))
+    }
+
+    // Shadings have no alpha, so translucent stops are painted through a luminosity soft mask
+    // built from a gray shading of the stop alphas, as images are with their alpha channel.
+    fn soft_mask(&mut self, shading: &Shading) -> Option<String> {
+        if shading.stops.iter().all(|stop| stop.color.a >= 1.0) {
+            return None;
+        }
+        let bbox = shading
+            .transform
+            .invert()?
+            .transform_rect(&Rect::new((0.0, 0.0), self.size));
+        let ep = bbox.endpoint();
+        let dict = self.shading_dict(shading, true);
+        let form = self.document.objects.add(stream(
+            &format!(
+                "/Type /XObject /Subtype /Form /BBox [{} {} {} {}] /Matrix [{}] /Group << /S /Transparency /CS /DeviceGray >> /Resources << /Shading << /Sh0 {} >> >>",
+                num(bbox.origin.x),
+                num(bbox.origin.y),
+                num(ep.x),
+                num(ep.y),
+                matrix(&shading.transform),
+                dict
+            ),
+            b"/Sh0 sh",
+        ));
+        Some(self.resources.ext_g_state(format!(
+            "<< /SMask << /Type /Mask /S /Luminosity /G {} 0 R >> >>",
+            form
+        )))
+    }
+
+    fn paint(&mut self, brush: &Brush, stroke: bool) {
+        match brush {
+            Brush::SolidColor(brush) => {
+                let color = brush.color();
+                let a = color.a.clamp(0.0, 1.0);
+                if a < 1.0 {
+                    let key = if stroke { "CA" } else { "ca" };
+                    let name = self
+                        .resources
+                        .ext_g_state(format!("<< /{} {} >>", key, num(a)));
+                    writeln!(self.content, "/{} gs", name).unwrap();
+                }
+                let op = if stroke { "RG" } else { "rg" };
+                writeln!(self.content, "{} {}", rgb(color), op).unwrap();
+            }
+            _ => {
+                let Some(shading) = self.shading(brush) else {
+                    return;
+                };
+                if let Some(mask) = self.soft_mask(&shading) {
+                    writeln!(self.content, "/{} gs", mask).unwrap();
+                }
+                let id = self.pattern(&shading);
+                let name = format!("P{}", self.resources.patterns.len());
+                self.resources.patterns.insert(name.clone(), id);
+                if stroke {
+                    writeln!(self.content, "/Pattern CS /{} SCN", name).unwrap();
+                } else {
+                    writeln!(self.content, "/Pattern cs /{} scn", name).unwrap();
+                }
+            }
+        }
+    }
+
+    fn stroke_style(&mut self, width: f32, props: &StrokeStyleProperties, cap: LineCap) {
+        let cap = match cap {
+            LineCap::Butt => 0,
+            LineCap::Round => 1,
+            LineCap::Square => 2,
+        };
+        let join = match props.line_join {
+            LineJoin::Miter | LineJoin::MiterOrBevel(_) => 0,
+            LineJoin::Round => 1,
+            LineJoin::Bevel => 2,
+        };
+        writeln!(
+            self.content,
+            "{} w {} J {} j {} M",
+            num(width),
+            cap,
+            join,
+            num(props.line_join.miter_limit())
+        )
+        .unwrap();
+        if let Some((pattern, offset)) = dash_pattern(props) {
+            let dashes = pattern.iter().map(|d| num(d * width)).collect::<Vec<_>>();
+            writeln!(
+                self.content,
+                "[{}] {} d",
+                dashes.join(" "),
+                num(offset * width)
+            )
+            .unwrap();
+        }
+    }
+
+    fn command(&mut self, command: &DisplayCommand) {
+        match command {
+            DisplayCommand::Clear(c) => {
+                if self.clip_depth == 0 {
+                    self.content.clear();
+                }
+                let a = c.a.clamp(0.0, 1.0);
+                if a > 0.0 {
+                    self.content.push_str("q\n");
+                    if a < 1.0 {
+                        let name = self.resources.ext_g_state(format!("<< /ca {} >>", num(a)));
+                        writeln!(self.content, "/{} gs", name).unwrap();
+                    }
+                    writeln!(
+                        self.content,
+                        "{} rg\n0 0 {} {} re\nf\nQ",
+                        rgb(*c),
+                        num(self.size.width),
+                        num(self.size.height)
+                    )
+                    .unwrap();
+                }
+            }
+            DisplayCommand::Fill { shape, brush } => {
+                if !shape.is_fillable() {
+                    return;
+                }
+                self.content.push_str("q\n");
+                self.paint(brush, false);
+                writeln!(self.content, "{} cm", matrix(&self.transform)).unwrap();
+                self.path(&shape.figures());
+                let op = match shape.fill_rule() {
+                    FillRule::EvenOdd => "f*",
+                    FillRule::NonZero => "f",
+                };
+                writeln!(self.content, "{}\nQ", op).unwrap();
+            }
+            DisplayCommand::Stroke {
+                shape,
+                brush,
+                width,
+                style,
+            } => {
+                let props = style
+                    .as_ref()
+                    .map(|style| style.properties())
+                    .unwrap_or_default();
+                let Some(cap) = native_cap(&shape.figures(), &props) else {
+                    let scale = self.transform.max_scale();
+                    if scale > 0.0 {
+                        let outline = shape.widen(*width, &props, TOLERANCE / scale);
+                        self.command(&DisplayCommand::Fill {
+                            shape: Shape::FilledPath(outline),
+                            brush: brush.clone(),
+                        });
+                    }
+                    return;
+                };
+                self.content.push_str("q\n");
+                self.paint(brush, true);
+                writeln!(self.content, "{} cm", matrix(&self.transform)).unwrap();
+                self.stroke_style(*width, &props, cap);
+                self.path(&shape.figures());
+                self.content.push_str("S\nQ\n");
+            }
+            #[cfg(windows)]
+            DisplayCommand::DrawText {
+                layout,
+                origin,
+                brush,
+            } => self.text(layout, *origin, brush),
+            DisplayCommand::DrawImage {
+                image,
+                dest_rect,
+                src_rect,
+                interpolation,
+            } => self.image(image, *dest_rect, *src_rect, *interpolation),
+            DisplayCommand::PushClip(rect) => {
+                let rect = self.transform.transform_rect(rect);
+                writeln!(
+                    self.content,
+                    "q\n{} {} {} {} re\nW n",
+                    num(rect.origin.x),
+                    num(rect.origin.y),
+                    num(rect.size.width),
+                    num(rect.size.height)
+                )
+                .unwrap();
+                self.clip_depth += 1;
+            }
+            DisplayCommand::PopClip => {
+                if self.clip_depth > 0 {
+                    self.content.push_str("Q\n");
+                    self.clip_depth -= 1;
+                }
+            }
+            DisplayCommand::Translate(point) => {
+                self.transform = Transform::translation(point.x, point.y) * self.transform;
+            }
+            DisplayCommand::Scale(size) => {
+                self.transform = Transform::scale(size.width, size.height) * self.transform;
+            }
+            DisplayCommand::Concat(transform) => self.transform = *transform * self.transform,
+            DisplayCommand::SetTransform(transform) => self.transform = *transform,
+            DisplayCommand::ResetTransform => self.transform = Transform::identity(),
+        }
+    }
+
+    #[cfg(windows)]
+    fn font(&mut self, face: &IDWriteFontFace) -> Option<(String, usize)> {
+        let data = font_file_data(face).ok()?;
+        let index = unsafe { face.GetIndex() };
+        FontFile::parse(&data, index)?;
+        let fonts = &mut self.document.fonts;
+        let i = match fonts
+            .iter()
+            .position(|font| font.index == index && font.data == data)
+        {
+            Some(i) => i,
+            None => {
+                let name = font_face_name(face)
+                    .unwrap_or_default()
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric())
+                    .collect::<String>();
+                fonts.push(EmbeddedFont {
+                    id: self.document.objects.reserve(),
+                    name: if name.is_empty() { "Font".into() } else { name },
+                    data,
+                    index,
+                    glyphs: BTreeMap::new(),
+                });
+                fonts.len() - 1
+            }
+        };
+        let id = fonts[i].id;
+        let name = match self.resources.fonts.iter().find(|(_, v)| **v == id) {
+            Some((name, _)) => name.clone(),
+            None => {
+                let name = format!("F{}", self.resources.fonts.len());
+                self.resources.fonts.insert(name.clone(), id);
+                name
+            }
+        };
+        Some((name, i))
+    }
+
+    // Glyphs are placed one by one where DirectWrite put them, each run with its own font, so
+    // fallback fonts and shaping survive. `.notdef` is never mapped back to text.
+    #[cfg(windows)]
+    fn text(&mut self, layout: &TextLayout, origin: Point<f32>, brush: &Brush) {
+        let Ok(runs) = layout.glyph_runs(origin) else {
+            return;
+        };
+        self.content.push_str("q\n");
+        self.paint(brush, false);
+        writeln!(self.content, "{} cm\nBT", matrix(&self.transform)).unwrap();
+        for run in runs {
+            let Some((name, font)) = self.font(&run.face) else {
+                continue;
+            };
+            writeln!(self.content, "/{} {} Tf", name, num(run.size)).unwrap();
+            for glyph in run.glyphs {
+                let text = self.document.fonts[font]
+                    .glyphs
+                    .entry(glyph.index)
+                    .or_default();
+                if text.is_empty() && glyph.index != 0 {
+                    *text = glyph.text;
+                }
+                writeln!(
+                    self.content,
+                    "1 0 0 -1 {} {} Tm <{:04X}> Tj",
+                    num(glyph.position.x),
+                    num(glyph.position.y),
+                    glyph.index
+                )
+                .unwrap();
+            }
+        }
+        self.content.push_str("ET\nQ\n");
+    }
+
+    fn image(
+        &mut self,
+        image: &Image,
+        dest_rect: Rect<f32>,
+        src_rect: Option<Rect<f32>>,
+        interpolation: Interpolation,
+    ) {
+        let interpolate = interpolation != Interpolation::NearestNeighbor;
+        let images = &mut self.document.images;
+        let id = match images
+            .iter()
+            .find(|(i, interp, _)| i == image && *interp == interpolate)
+        {
+            Some(&(_, _, id)) => id,
+            None => {
+                let data = image.data();
+                let rgba = data.to_straight_rgba();
+                let rgb = rgba
+                    .chunks_exact(4)
+                    .flat_map(|p| [p[0], p[1], p[2]])
+                    .collect::<Vec<_>>();
+                let alpha = rgba.chunks_exact(4).map(|p| p[3]).collect::<Vec<_>>();
+                let dict = format!(
+                    "/Type /XObject /Subtype /Image /Width {} /Height {} /BitsPerComponent 8",
+                    data.width, data.height
+                );
+                let objects = &mut self.document.objects;
+                let smask =
+                    objects.add(stream(&format!("{} /ColorSpace /DeviceGray", dict), &alpha));
+                let id = objects.add(stream(
+                    &format!(
+                        "{} /ColorSpace /DeviceRGB /SMask {} 0 R /Interpolate {}",
+                        dict, smask, interpolate
+                    ),
+                    &rgb,
+                ));
+                images.push((image.clone(), interpolate, id));
+                id
+            }
+        };
+        let name = match self.resources.x_objects.iter().find(|(_, v)| **v == id) {
+            Some((name, _)) => name.clone(),
+            None => {
+                let name = format!("Im{}", self.resources.x_objects.len());
+                self.resources.x_objects.insert(name.clone(), id);
+                name
+            }
+        };
+        let size = image.size();
+        let d = dest_rect;
+        writeln!(self.content, "q\n{} cm", matrix(&self.transform)).unwrap();
+        let placement = match src_rect {
+            None => Rect::new(d.origin, d.size),
+            Some(s) => {
+                writeln!(
+                    self.content,
+                    "{} {} {} {} re\nW n",
+                    num(d.origin.x),
+                    num(d.origin.y),
+                    num(d.size.width),
+                    num(d.size.height)
+                )
+                .unwrap();
+                let sx = d.size.width / s.size.width;
+                let sy = d.size.height / s.size.height;
+                Rect::new(
+                    (d.origin.x - s.origin.x * sx, d.origin.y - s.origin.y * sy),
+                    (size.width as f32 * sx, size.height as f32 * sy),
+                )
+            }
+        };
+        writeln!(
+            self.content,
+            "{} 0 0 {} {} {} cm\n/{} Do\nQ",
+            num(placement.size.width),
+            num(-placement.size.height),
+            num(placement.origin.x),
+            num(placement.origin.y + placement.size.height),
+            name
+        )
+        .unwrap();
+    }
+
+    fn finish(self) {
+        let mut content = format!("{} cm\n", matrix(&self.base));
+        content.push_str(&self.content);
+        for _ in 0..self.clip_depth {
+            content.push_str("Q\n");
+        }
+        let contents = self.document.objects.add(stream("", content.as_bytes()));
+        let page = self.document.objects.add(format!(
+            "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources {} /Contents {} 0 R >>",
+            PAGES_ID,
+            num(self.size.width * POINTS_PER_DIP),
+            num(self.size.height * POINTS_PER_DIP),
+            self.resources.to_dict(),
+            contents
+        ));
+        self.document.pages.push(page);
+    }
+}
+
+#[derive(Debug)]
+pub struct RenderTarget {
+    size: Size<u32>,
+    document: Mutex<Document>,
+}
+
+impl RenderTarget {
+    #[inline]
+    pub fn page_count(&self) -> usize {
+        self.document
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pages
+            .len()
+    }
+
+    #[inline]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.document
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .to_bytes()
+    }
+}
+
+impl Target for RenderTarget {
+    #[inline]
+    fn size(&self) -> Size<f32> {
+        Size::new(self.size.width as f32, self.size.height as f32)
+    }
+
+    #[inline]
+    fn physical_size(&self) -> Size<u32> {
+        self.size
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Pdf;
+
+impl Pdf {
+    #[inline]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Context<Pdf> {
+    #[inline]
+    pub fn create_render_target(&self, size: impl Into<Size<u32>>) -> Result<RenderTarget> {
+        Ok(RenderTarget {
+            size: size.into(),
+            document: Mutex::new(Document::new()),
+        })
+    }
+}
+
+impl Backend for Pdf {
+    type RenderTarget = RenderTarget;
+
+    #[inline]
+    fn create_factory(&self) -> Factory {
+        Factory {
+            #[cfg(windows)]
+            device: None,
+        }
+    }
+
+    #[inline]
+    fn set_dpi(&self, _dpi: f32) {}
+
+    #[inline]
+    fn create_offscreen_target(
+        &self,
+        size: Size<u32>,
+        _format: PixelFormat,
+        _alpha_mode: AlphaMode,
+    ) -> Result<Self::RenderTarget> {
+        Ok(RenderTarget {
+            size,
+            document: Mutex::new(Document::new()),
+        })
+    }
+
+    fn draw<R>(
+        &self,
+        target: &Self::RenderTarget,
+        f: impl FnOnce(&dyn Renderer) -> R,
+    ) -> Result<R> {
+        let mut ret = None;
+        let display_list = DisplayList::record(|cmd| ret = Some(f(cmd.renderer)));
+        target
+            .document
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .page(target.size(), &display_list);
+        ret.ok_or(Error::Unsupported)
+    }
+
+    #[inline]
+    fn read_pixels(&self, _target: &Self::RenderTarget) -> Result<ImageData> {
+        Err(Error::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn streams(pdf: &[u8]) -> Vec<String> {
+        let mut streams = vec![];
+        let mut rest = pdf;
+        while let Some(start) = rest.windows(10).position(|w| w == b">>\nstream\n") {
+            rest = &rest[start + 10..];
+            let end = rest.windows(10).position(|w| w == b"\nendstream").unwrap();
+            if let Ok(data) = miniz_oxide::inflate::decompress_to_vec_zlib(&rest[..end]) {
+                streams.push(String::from_utf8_lossy(&data).into_owned());
+            }
+            rest = &rest[end..];
+        }
+        streams
+    }
+
+    fn check_xref(pdf: &[u8]) {
+        let startxref = pdf.windows(10).rposition(|w| w == b"startxref\n").unwrap();
+        let tail = String::from_utf8_lossy(&pdf[startxref + 10..]);
+        let xref = tail.lines().next().unwrap().parse::<usize>().unwrap();
+        let table = String::from_utf8_lossy(&pdf[xref..]);
+        assert!(table.starts_with("xref\n"));
+        for (i, line) in table.lines().skip(3).enumerate() {
+            if line.starts_with("trailer") {
+                break;
+            }
+            let offset = line[..10].parse::<usize>().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", i + 1).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn document() {
+        let factory = Context::new(Pdf::new()).unwrap().create_factory();
+        let brush = factory
+            .create_solid_color_brush((1.0, 0.0, 0.0, 0.5))
+            .unwrap();
+        let stops = factory
+            .create_gradient_stop_collection(
+                GradientMode::Mirror,
+                &[(0.0, (0.0, 0.0, 0.0, 1.0)), (1.0, (1.0, 1.0, 1.0, 1.0))],
+            )
+            .unwrap();
+        let gradient = factory
+            .create_linear_gradient_brush((0.0, 0.0), (10.0, 0.0), &stops)
+            .unwrap();
+        let style = factory
+            .create_stroke_style(&StrokeStyleProperties {
+                dash: Some(Dash {
+                    style: DashStyle::Dash,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .unwrap();
+        let pdf = super::document((100.0, 50.0), |cmd| {
+            cmd.clear((1.0, 1.0, 1.0, 1.0));
+            cmd.translate((1.0, 2.0));
+            cmd.fill(&Rect::new((0.0, 0.0), (4.0, 3.0)), &brush);
+            cmd.clip(Rect::new((1.0, 1.0), (5.0, 5.0)), |cmd| {
+                cmd.stroke(
+                    &line((0.0, 0.0), (10.0, 10.0)),
+                    &gradient,
+                    1.5,
+                    Some(&style),
+                );
+            });
+        });
+        assert!(pdf.starts_with(b"%PDF-1.7\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        check_xref(&pdf);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Type /Pages /Kids [") && text.contains("/Count 1"));
+        assert!(text.contains("/MediaBox [0 0 75 37.5]"));
+        assert!(text.contains("/ExtGState << /GS0 << /ca 0.5 >> >>"));
+        assert!(text.contains("/ShadingType 2"));
+        let content = streams(&pdf)
+            .into_iter()
+            .find(|s| s.starts_with("0.75 0 0 -0.75 0 37.5 cm\n"))
+            .unwrap();
+        assert!(content.contains("1 1 1 rg\n0 0 100 50 re\nf\nQ"));
+        assert!(content
+            .contains("q\n/GS0 gs\n1 0 0 rg\n1 0 0 1 1 2 cm\n0 0 m\n4 0 l\n4 3 l\n0 3 l\nh\nf\nQ"));
+        assert!(content.contains("q\n2 3 5 5 re\nW n\n"));
+        assert!(content.contains("/Pattern CS /P0 SCN"));
+        assert!(content.contains("1.5 w 0 J 0 j 10 M\n[3 3] 0 d\n0 0 m\n10 10 l\nS\nQ\nQ\n"));
+    }
+
+    #[test]
+    fn pages() {
+        let ctx = Context::new(Pdf::new()).unwrap();
+        let factory = ctx.create_factory();
+        let brush = factory
+            .create_solid_color_brush((0.0, 1.0, 0.0, 1.0))
+            .unwrap();
+        let target = ctx.create_render_target((20, 10)).unwrap();
+        for _ in 0..2 {
+            ctx.draw(&target, |cmd| {
+                cmd.fill(&Circle::new((5.0, 5.0), 2.0), &brush);
+            })
+            .unwrap();
+        }
+        assert_eq!(target.page_count(), 2);
+        assert_eq!(ctx.read_pixels(&target), Err(Error::Unsupported));
+        let pdf = target.to_bytes();
+        check_xref(&pdf);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("/MediaBox [0 0 15 7.5]"));
+        let contents = streams(&pdf);
+        assert_eq!(contents.len(), 2);
+        assert!(contents
+            .iter()
+            .all(|s| s.contains(" c\n") && s.contains("0 1 0 rg")));
+    }
+
+    #[test]
+    fn translucent_gradient() {
+        let factory = Context::new(Pdf::new()).unwrap().create_factory();
+        let stops = factory
+            .create_gradient_stop_collection(
+                GradientMode::Clamp,
+                &[(0.0, (1.0, 0.0, 0.0, 1.0)), (1.0, (0.0, 0.0, 1.0, 0.25))],
+            )
+            .unwrap();
+        let gradient = factory
+            .create_linear_gradient_brush((0.0, 0.0), (10.0, 0.0), &stops)
+            .unwrap();
+        let pdf = super::document((20.0, 10.0), |cmd| {
+            cmd.fill(&Rect::new((0.0, 0.0), (10.0, 10.0)), &gradient);
+        });
+        check_xref(&pdf);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/C0 [1 0 0] /C1 [0 0 1]"));
+        assert!(text.contains("/C0 [1] /C1 [0.25]"));
+        assert!(text.contains("/ColorSpace /DeviceGray /Coords [0 0 10 0]"));
+        assert!(text.contains("/Group << /S /Transparency /CS /DeviceGray >>"));
+        assert!(text.contains("/ExtGState << /GS0 << /SMask << /Type /Mask /S /Luminosity /G "));
+        let content = streams(&pdf)
+            .into_iter()
+            .find(|s| s.starts_with("0.75 0 0 -0.75 0 7.5 cm\n"))
+            .unwrap();
+        assert!(content.contains("q\n/GS0 gs\n/Pattern cs /P0 scn\n"));
+        assert!(streams(&pdf).iter().any(|s| s == "/Sh0 sh"));
+    }
+
+    #[test]
+    fn caps() {
+        let factory = Context::new(Pdf::new()).unwrap().create_factory();
+        let brush = factory
+            .create_solid_color_brush((0.0, 0.0, 1.0, 1.0))
+            .unwrap();
+        let stroke = |start_cap, end_cap| {
+            let style = factory
+                .create_stroke_style(&StrokeStyleProperties {
+                    start_cap,
+                    end_cap,
+                    ..Default::default()
+                })
+                .unwrap();
+            let pdf = super::document((10.0, 10.0), |cmd| {
+                cmd.stroke(&line((1.0, 5.0), (9.0, 5.0)), &brush, 2.0, Some(&style));
+            });
+            streams(&pdf).remove(0)
+        };
+        assert!(stroke(CapStyle::Round, CapStyle::Round).contains("2 w 1 J 0 j 10 M\n"));
+        for (start_cap, end_cap) in [
+            (CapStyle::Square, CapStyle::Flat),
+            (CapStyle::Triangle, CapStyle::Triangle),
+        ] {
+            let content = stroke(start_cap, end_cap);
+            assert!(!content.contains(" J "));
+            assert!(content.contains("0 0 1 rg\n"));
+            assert!(content.ends_with("h\nf\nQ\n"));
+        }
+
+        let spike = HollowPath::from(
+            PathData::builder()
+                .begin((1.0, 1.0))
+                .line_to((9.0, 5.0))
+                .line_to((1.0, 2.0))
+                .end(FigureEnd::Open)
+                .unwrap()
+                .close()
+                .unwrap(),
+        );
+        let stroke = |line_join| {
+            let style = factory
+                .create_stroke_style(&StrokeStyleProperties {
+                    line_join,
+                    ..Default::default()
+                })
+                .unwrap();
+            let pdf = super::document((10.0, 10.0), |cmd| {
+                cmd.stroke(&spike, &brush, 1.0, Some(&style));
+            });
+            streams(&pdf).remove(0)
+        };
+        assert!(stroke(LineJoin::Miter).ends_with("h\nf\nQ\n"));
+        assert!(stroke(LineJoin::MiterOrBevel(10.0)).contains("1 w 0 J 0 j 10 M\n"));
+    }
+
+    #[test]
+    fn image() {
+        let image = Image::new(ImageData {
+            width: 2,
+            height: 1,
+            stride: 8,
+            format: PixelFormat::Rgba8,
+            alpha_mode: AlphaMode::Premultiplied,
+            bytes: vec![255, 0, 0, 255, 0, 255, 0, 255],
+        });
+        let pdf = super::document((10.0, 10.0), |cmd| {
+            cmd.draw_image(
+                &image,
+                Rect::new((1.0, 2.0), (4.0, 2.0)),
+                None,
+                Interpolation::Linear,
+            );
+        });
+        check_xref(&pdf);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/XObject << /Im0 "));
+        assert!(text.contains(
+            "/Subtype /Image /Width 2 /Height 1 /BitsPerComponent 8 /ColorSpace /DeviceRGB /SMask "
+        ));
+        let content = streams(&pdf)
+            .into_iter()
+            .find(|s| s.contains(" cm\n/Im0 Do\nQ"))
+            .unwrap();
+        assert!(content.contains("4 0 0 -2 1 4 cm\n/Im0 Do\nQ"));
+
+        let ctx = Context::new(Pdf::new()).unwrap();
+        let target = ctx.create_render_target((10, 10)).unwrap();
+        for _ in 0..2 {
+            ctx.draw(&target, |cmd| {
+                for x in [0.0, 4.0] {
+                    cmd.draw_image(
+                        &image,
+                        Rect::new((x, 0.0), (2.0, 1.0)),
+                        None,
+                        Interpolation::Linear,
+                    );
+                }
+            })
+            .unwrap();
+        }
+        let pdf = target.to_bytes();
+        check_xref(&pdf);
+        let text = String::from_utf8_lossy(&pdf);
+        assert_eq!(text.matches("/ColorSpace /DeviceRGB").count(), 1);
+        assert_eq!(text.matches("/XObject << /Im0 ").count(), 2);
+        assert!(!text.contains("/Im1"));
+    }
+}
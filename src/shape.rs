@@ -1,5 +1,4 @@
 use crate::*;
-use windows::Win32::Graphics::{Direct2D::Common::*, Direct2D::*};
 
 pub type Point<T> = gecl::Point<T>;
 pub type Size<T> = gecl::Size<T>;
@@ -8,117 +7,165 @@ pub type Vector<T> = gecl::Vector<T>;
 pub type Circle = gecl::Circle<f32>;
 pub type Ellipse = gecl::Ellipse<f32>;
 
-impl From<Wrapper<Point<f32>>> for D2D_POINT_2F {
-    #[inline]
-    fn from(src: Wrapper<Point<f32>>) -> Self {
-        Self {
-            x: src.0.x,
-            y: src.0.y,
-        }
-    }
+#[derive(Clone, PartialEq, Debug)]
+pub enum Shape {
+    Rect(Rect<f32>),
+    Circle(Circle),
+    Ellipse(Ellipse),
+    RoundedRect(RoundedRect),
+    Line(Line),
+    FilledPath(FilledPath),
+    HollowPath(HollowPath),
 }
 
-impl From<Wrapper<D2D_POINT_2F>> for Point<f32> {
-    #[inline]
-    fn from(src: Wrapper<D2D_POINT_2F>) -> Self {
-        Self {
-            x: src.0.x,
-            y: src.0.y,
-        }
+// The distance of cubic Bézier control points approximating a quarter of a unit circle.
+const KAPPA: f32 = 0.552_284_8;
+
+fn ellipse_figure(center: Point<f32>, rx: f32, ry: f32) -> PathFigure {
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    let (cx, cy) = (center.x, center.y);
+    let arc = |c0: (f32, f32), c1: (f32, f32), to: (f32, f32)| {
+        PathSegment::CubicBezier(CubicBezierSegment {
+            c0: c0.into(),
+            c1: c1.into(),
+            to: to.into(),
+        })
+    };
+    PathFigure {
+        begin: Point::new(cx + rx, cy),
+        segments: vec![
+            arc((cx + rx, cy + ky), (cx + kx, cy + ry), (cx, cy + ry)),
+            arc((cx - kx, cy + ry), (cx - rx, cy + ky), (cx - rx, cy)),
+            arc((cx - rx, cy - ky), (cx - kx, cy - ry), (cx, cy - ry)),
+            arc((cx + kx, cy - ry), (cx + rx, cy - ky), (cx + rx, cy)),
+        ],
+        end: FigureEnd::Closed,
     }
 }
 
-impl From<Wrapper<Size<f32>>> for D2D_SIZE_F {
-    #[inline]
-    fn from(src: Wrapper<Size<f32>>) -> Self {
-        Self {
-            width: src.0.width,
-            height: src.0.height,
-        }
+fn rect_figure(rect: &Rect<f32>) -> PathFigure {
+    let ep = rect.endpoint();
+    PathFigure {
+        begin: rect.origin,
+        segments: vec![
+            PathSegment::Line(Point::new(ep.x, rect.origin.y)),
+            PathSegment::Line(ep),
+            PathSegment::Line(Point::new(rect.origin.x, ep.y)),
+        ],
+        end: FigureEnd::Closed,
     }
 }
 
-impl From<Wrapper<D2D_SIZE_F>> for Size<f32> {
-    #[inline]
-    fn from(src: Wrapper<D2D_SIZE_F>) -> Self {
-        Self {
-            width: src.0.width,
-            height: src.0.height,
-        }
+fn rounded_rect_figure(rrect: &RoundedRect) -> PathFigure {
+    let rect = &rrect.rect;
+    let rx = rrect.radius.x.abs().min(rect.size.width.abs() * 0.5);
+    let ry = rrect.radius.y.abs().min(rect.size.height.abs() * 0.5);
+    if rx <= 0.0 || ry <= 0.0 {
+        return rect_figure(rect);
+    }
+    let (l, t) = (rect.origin.x, rect.origin.y);
+    let ep = rect.endpoint();
+    let (r, b) = (ep.x, ep.y);
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    let line = |x: f32, y: f32| PathSegment::Line(Point::new(x, y));
+    let arc = |c0: (f32, f32), c1: (f32, f32), to: (f32, f32)| {
+        PathSegment::CubicBezier(CubicBezierSegment {
+            c0: c0.into(),
+            c1: c1.into(),
+            to: to.into(),
+        })
+    };
+    PathFigure {
+        begin: Point::new(l + rx, t),
+        segments: vec![
+            line(r - rx, t),
+            arc((r - rx + kx, t), (r, t + ry - ky), (r, t + ry)),
+            line(r, b - ry),
+            arc((r, b - ry + ky), (r - rx + kx, b), (r - rx, b)),
+            line(l + rx, b),
+            arc((l + rx - kx, b), (l, b - ry + ky), (l, b - ry)),
+            line(l, t + ry),
+            arc((l, t + ry - ky), (l + rx - kx, t), (l + rx, t)),
+        ],
+        end: FigureEnd::Closed,
     }
 }
 
-impl From<Wrapper<Size<u32>>> for D2D_SIZE_U {
-    #[inline]
-    fn from(src: Wrapper<Size<u32>>) -> Self {
-        Self {
-            width: src.0.width,
-            height: src.0.height,
+impl Geometry for Shape {
+    type Transformed = Shape;
+
+    fn figures(&self) -> Vec<PathFigure> {
+        match self {
+            Self::Rect(rect) => vec![rect_figure(rect)],
+            Self::Circle(circle) => {
+                vec![ellipse_figure(circle.center, circle.radius, circle.radius)]
+            }
+            Self::Ellipse(ellipse) => vec![ellipse_figure(
+                ellipse.center,
+                ellipse.radius.x,
+                ellipse.radius.y,
+            )],
+            Self::RoundedRect(rrect) => vec![rounded_rect_figure(rrect)],
+            Self::Line(line) => vec![PathFigure {
+                begin: line.0,
+                segments: vec![PathSegment::Line(line.1)],
+                end: FigureEnd::Open,
+            }],
+            Self::FilledPath(path) => path.path_data().figures().to_vec(),
+            Self::HollowPath(path) => path.path_data().figures().to_vec(),
         }
     }
-}
 
-impl From<Wrapper<D2D_SIZE_U>> for Size<u32> {
     #[inline]
-    fn from(src: Wrapper<D2D_SIZE_U>) -> Self {
-        Self {
-            width: src.0.width,
-            height: src.0.height,
+    fn fill_rule(&self) -> FillRule {
+        match self {
+            Self::FilledPath(path) => path.path_data().fill_rule(),
+            _ => FillRule::NonZero,
         }
     }
-}
 
-impl From<Wrapper<Vector<f32>>> for D2D_POINT_2F {
     #[inline]
-    fn from(src: Wrapper<Vector<f32>>) -> Self {
-        Self {
-            x: src.0.x,
-            y: src.0.y,
-        }
+    fn is_fillable(&self) -> bool {
+        !matches!(self, Self::Line(_) | Self::HollowPath(_))
     }
-}
 
-impl From<Wrapper<Rect<f32>>> for D2D_RECT_F {
-    #[inline]
-    fn from(src: Wrapper<Rect<f32>>) -> Self {
-        let ep = src.0.endpoint();
-        Self {
-            left: src.0.origin.x,
-            top: src.0.origin.y,
-            right: ep.x,
-            bottom: ep.y,
+    fn transformed(&self, transform: &Transform) -> Shape {
+        match self {
+            Self::Rect(rect) => Self::FilledPath(rect.transformed(transform)),
+            Self::Circle(circle) => Self::FilledPath(circle.transformed(transform)),
+            Self::Ellipse(ellipse) => Self::FilledPath(ellipse.transformed(transform)),
+            Self::RoundedRect(rrect) => Self::FilledPath(rrect.transformed(transform)),
+            Self::Line(line) => Self::Line(line.transformed(transform)),
+            Self::FilledPath(path) => Self::FilledPath(path.transformed(transform)),
+            Self::HollowPath(path) => Self::HollowPath(path.transformed(transform)),
         }
     }
 }
 
-impl From<Wrapper<Circle>> for D2D1_ELLIPSE {
+impl Fill for Shape {
     #[inline]
-    fn from(src: Wrapper<Circle>) -> Self {
-        Self {
-            point: Wrapper(src.0.center).into(),
-            radiusX: src.0.radius,
-            radiusY: src.0.radius,
-        }
+    fn fill(&self, renderer: &dyn Renderer, brush: &Brush) {
+        renderer.fill(self, brush);
     }
 }
 
-impl From<Wrapper<Ellipse>> for D2D1_ELLIPSE {
+impl Stroke for Shape {
     #[inline]
-    fn from(src: Wrapper<Ellipse>) -> Self {
-        Self {
-            point: Wrapper(src.0.center).into(),
-            radiusX: src.0.radius.x,
-            radiusY: src.0.radius.y,
-        }
+    fn stroke(
+        &self,
+        renderer: &dyn Renderer,
+        brush: &Brush,
+        width: f32,
+        style: Option<&StrokeStyle>,
+    ) {
+        renderer.stroke(self, brush, width, style);
     }
 }
 
 impl Fill for Rect<f32> {
     #[inline]
-    fn fill(&self, dc: &ID2D1DeviceContext5, brush: &ID2D1Brush) {
-        unsafe {
-            dc.FillRectangle(&Wrapper(*self).into(), brush);
-        }
+    fn fill(&self, renderer: &dyn Renderer, brush: &Brush) {
+        renderer.fill(&Shape::Rect(*self), brush);
     }
 }
 
@@ -126,23 +173,19 @@ impl Stroke for Rect<f32> {
     #[inline]
     fn stroke(
         &self,
-        dc: &ID2D1DeviceContext5,
-        brush: &ID2D1Brush,
+        renderer: &dyn Renderer,
+        brush: &Brush,
         width: f32,
-        style: Option<&ID2D1StrokeStyle>,
+        style: Option<&StrokeStyle>,
     ) {
-        unsafe {
-            dc.DrawRectangle(&Wrapper(*self).into(), brush, width, style);
-        }
+        renderer.stroke(&Shape::Rect(*self), brush, width, style);
     }
 }
 
 impl Fill for Circle {
     #[inline]
-    fn fill(&self, dc: &ID2D1DeviceContext5, brush: &ID2D1Brush) {
-        unsafe {
-            dc.FillEllipse(&Wrapper(Ellipse::from(*self)).into(), brush);
-        }
+    fn fill(&self, renderer: &dyn Renderer, brush: &Brush) {
+        renderer.fill(&Shape::Circle(*self), brush);
     }
 }
 
@@ -150,23 +193,19 @@ impl Stroke for Circle {
     #[inline]
     fn stroke(
         &self,
-        dc: &ID2D1DeviceContext5,
-        brush: &ID2D1Brush,
+        renderer: &dyn Renderer,
+        brush: &Brush,
         width: f32,
-        style: Option<&ID2D1StrokeStyle>,
+        style: Option<&StrokeStyle>,
     ) {
-        unsafe {
-            dc.DrawEllipse(&Wrapper(Ellipse::from(*self)).into(), brush, width, style);
-        }
+        renderer.stroke(&Shape::Circle(*self), brush, width, style);
     }
 }
 
 impl Fill for Ellipse {
     #[inline]
-    fn fill(&self, dc: &ID2D1DeviceContext5, brush: &ID2D1Brush) {
-        unsafe {
-            dc.FillEllipse(&Wrapper(*self).into(), brush);
-        }
+    fn fill(&self, renderer: &dyn Renderer, brush: &Brush) {
+        renderer.fill(&Shape::Ellipse(*self), brush);
     }
 }
 
@@ -174,18 +213,16 @@ impl Stroke for Ellipse {
     #[inline]
     fn stroke(
         &self,
-        dc: &ID2D1DeviceContext5,
-        brush: &ID2D1Brush,
+        renderer: &dyn Renderer,
+        brush: &Brush,
         width: f32,
-        style: Option<&ID2D1StrokeStyle>,
+        style: Option<&StrokeStyle>,
     ) {
-        unsafe {
-            dc.DrawEllipse(&Wrapper(*self).into(), brush, width, style);
-        }
+        renderer.stroke(&Shape::Ellipse(*self), brush, width, style);
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Line(pub Point<f32>, pub Point<f32>);
 
 impl Line {
@@ -204,24 +241,16 @@ impl Stroke for Line {
     #[inline]
     fn stroke(
         &self,
-        dc: &ID2D1DeviceContext5,
-        brush: &ID2D1Brush,
+        renderer: &dyn Renderer,
+        brush: &Brush,
         width: f32,
-        style: Option<&ID2D1StrokeStyle>,
+        style: Option<&StrokeStyle>,
     ) {
-        unsafe {
-            dc.DrawLine(
-                Wrapper(self.0).into(),
-                Wrapper(self.1).into(),
-                brush,
-                width,
-                style,
-            );
-        }
+        renderer.stroke(&Shape::Line(*self), brush, width, style);
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct RoundedRect {
     pub rect: Rect<f32>,
     pub radius: Vector<f32>,
@@ -237,17 +266,6 @@ impl RoundedRect {
     }
 }
 
-impl From<RoundedRect> for D2D1_ROUNDED_RECT {
-    #[inline]
-    fn from(src: RoundedRect) -> Self {
-        Self {
-            rect: Wrapper(src.rect).into(),
-            radiusX: src.radius.x,
-            radiusY: src.radius.y,
-        }
-    }
-}
-
 #[inline]
 pub fn rounded_rect(rect: impl Into<Rect<f32>>, radius: impl Into<Vector<f32>>) -> RoundedRect {
     RoundedRect::new(rect, radius)
@@ -255,10 +273,8 @@ pub fn rounded_rect(rect: impl Into<Rect<f32>>, radius: impl Into<Vector<f32>>)
 
 impl Fill for RoundedRect {
     #[inline]
-    fn fill(&self, dc: &ID2D1DeviceContext5, brush: &ID2D1Brush) {
-        unsafe {
-            dc.FillRoundedRectangle(&(*self).into(), brush);
-        }
+    fn fill(&self, renderer: &dyn Renderer, brush: &Brush) {
+        renderer.fill(&Shape::RoundedRect(*self), brush);
     }
 }
 
@@ -266,13 +282,11 @@ impl Stroke for RoundedRect {
     #[inline]
     fn stroke(
         &self,
-        dc: &ID2D1DeviceContext5,
-        brush: &ID2D1Brush,
+        renderer: &dyn Renderer,
+        brush: &Brush,
         width: f32,
-        style: Option<&ID2D1StrokeStyle>,
+        style: Option<&StrokeStyle>,
     ) {
-        unsafe {
-            dc.DrawRoundedRectangle(&(*self).into(), brush, width, style);
-        }
+        renderer.stroke(&Shape::RoundedRect(*self), brush, width, style);
     }
 }
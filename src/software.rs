@@ -0,0 +1,1197 @@
+use crate::flatten::{flatten, TOLERANCE};
+use crate::geometry::stroke_polygons;
+use crate::stroker::Polygon;
+use crate::transform::Transform;
+use crate::*;
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+#[derive(Debug)]
+pub struct RenderTarget {
+    size: Size<u32>,
+    format: PixelFormat,
+    alpha_mode: AlphaMode,
+    pixels: Mutex<Vec<u8>>,
+}
+
+impl RenderTarget {
+    // Pixels are always stored as 8-bit premultiplied RGBA and converted to `format` and
+    // `alpha_mode` in `read_pixels`, so Rgba16F targets are quantised to 8 bits per channel.
+    pub(crate) fn new(size: Size<u32>, format: PixelFormat, alpha_mode: AlphaMode) -> Self {
+        Self {
+            size,
+            format,
+            alpha_mode,
+            pixels: Mutex::new(vec![0; size.width as usize * size.height as usize * 4]),
+        }
+    }
+}
+
+impl Target for RenderTarget {
+    #[inline]
+    fn size(&self) -> Size<f32> {
+        Size::new(self.size.width as f32, self.size.height as f32)
+    }
+
+    #[inline]
+    fn physical_size(&self) -> Size<u32> {
+        self.size
+    }
+}
+
+#[derive(Debug)]
+pub struct Software {
+    dpi: AtomicU32,
+}
+
+impl Software {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            dpi: AtomicU32::new(96.0f32.to_bits()),
+        }
+    }
+}
+
+impl Default for Software {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Context<Software> {
+    #[inline]
+    pub fn create_render_target(&self, size: impl Into<Size<u32>>) -> Result<RenderTarget> {
+        Ok(RenderTarget::new(
+            size.into(),
+            PixelFormat::Rgba8,
+            AlphaMode::Premultiplied,
+        ))
+    }
+}
+
+impl Backend for Software {
+    type RenderTarget = RenderTarget;
+
+    #[inline]
+    fn create_factory(&self) -> Factory {
+        Factory {
+            #[cfg(windows)]
+            device: None,
+        }
+    }
+
+    #[inline]
+    fn set_dpi(&self, dpi: f32) {
+        self.dpi.store(dpi.to_bits(), Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn create_offscreen_target(
+        &self,
+        size: Size<u32>,
+        format: PixelFormat,
+        alpha_mode: AlphaMode,
+    ) -> Result<Self::RenderTarget> {
+        Ok(RenderTarget::new(size, format, alpha_mode))
+    }
+
+    fn draw<R>(
+        &self,
+        target: &Self::RenderTarget,
+        f: impl FnOnce(&dyn Renderer) -> R,
+    ) -> Result<R> {
+        let mut pixels = target.pixels.lock().unwrap_or_else(|e| e.into_inner());
+        let scale = f32::from_bits(self.dpi.load(Ordering::Relaxed)) / 96.0;
+        let canvas = Canvas {
+            width: target.size.width as usize,
+            height: target.size.height as usize,
+            pixels: RefCell::new(&mut pixels),
+            base: Transform::scale(scale, scale),
+            transform: Cell::new(Transform::identity()),
+            clips: RefCell::new(vec![]),
+        };
+        Ok(f(&canvas))
+    }
+
+    fn read_pixels(&self, target: &Self::RenderTarget) -> Result<ImageData> {
+        let pixels = target.pixels.lock().unwrap_or_else(|e| e.into_inner());
+        let data = ImageData {
+            width: target.size.width,
+            height: target.size.height,
+            stride: target.size.width * 4,
+            format: PixelFormat::Rgba8,
+            alpha_mode: AlphaMode::Premultiplied,
+            bytes: pixels.clone(),
+        };
+        Ok(data.convert(target.format, target.alpha_mode))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Color {
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+}
+
+impl Color {
+    #[inline]
+    fn premultiplied(color: Rgba<f32>) -> Self {
+        let a = color.a.clamp(0.0, 1.0);
+        Self {
+            r: color.r.clamp(0.0, 1.0) * a,
+            g: color.g.clamp(0.0, 1.0) * a,
+            b: color.b.clamp(0.0, 1.0) * a,
+            a,
+        }
+    }
+
+    #[inline]
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Self {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+}
+
+struct Gradient {
+    mode: GradientMode,
+    stops: Vec<(f32, Color)>,
+}
+
+impl Gradient {
+    fn new(collection: &GradientStopCollection) -> Self {
+        let mut stops = collection
+            .stops()
+            .iter()
+            .map(|stop| (stop.position, Color::premultiplied(stop.color)))
+            .collect::<Vec<_>>();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            mode: collection.mode(),
+            stops,
+        }
+    }
+
+    fn color(&self, t: f32) -> Color {
+        let t = match self.mode {
+            GradientMode::Clamp => t.clamp(0.0, 1.0),
+            GradientMode::Wrap => t - t.floor(),
+            GradientMode::Mirror => {
+                let t = t.rem_euclid(2.0);
+                if t > 1.0 {
+                    2.0 - t
+                } else {
+                    t
+                }
+            }
+        };
+        let Some(first) = self.stops.first() else {
+            return Color::premultiplied(Rgba::new(0.0, 0.0, 0.0, 0.0));
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        for pair in self.stops.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if t <= b.0 {
+                let d = b.0 - a.0;
+                return if d <= 0.0 {
+                    b.1
+                } else {
+                    a.1.lerp(b.1, (t - a.0) / d)
+                };
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
+}
+
+enum Paint {
+    Solid(Color),
+    Linear {
+        start: Point<f32>,
+        dir: Point<f32>,
+        gradient: Gradient,
+    },
+    Radial {
+        center: Point<f32>,
+        radius: Point<f32>,
+        focus: Point<f32>,
+        gradient: Gradient,
+    },
+    Image {
+        data: ImageData,
+        dest: Rect<f32>,
+        src: Rect<f32>,
+        nearest: bool,
+    },
+}
+
+impl Paint {
+    fn new(brush: &Brush) -> Self {
+        match brush {
+            Brush::SolidColor(brush) => Self::Solid(Color::premultiplied(brush.color())),
+            Brush::LinearGradient(brush) => {
+                let (start, end) = (brush.start(), brush.end());
+                let (dx, dy) = (end.x - start.x, end.y - start.y);
+                let len2 = dx * dx + dy * dy;
+                let dir = if len2 > 0.0 {
+                    Point::new(dx / len2, dy / len2)
+                } else {
+                    Point::new(0.0, 0.0)
+                };
+                Self::Linear {
+                    start,
+                    dir,
+                    gradient: Gradient::new(brush.stops()),
+                }
+            }
+            Brush::RadialGradient(brush) => {
+                let ellipse = brush.ellipse();
+                let radius = Point::new(ellipse.radius.x, ellipse.radius.y);
+                let offset = brush.offset();
+                Self::Radial {
+                    center: ellipse.center,
+                    radius,
+                    focus: Point::new(offset.x / radius.x, offset.y / radius.y),
+                    gradient: Gradient::new(brush.stops()),
+                }
+            }
+        }
+    }
+
+    fn color(&self, p: Point<f32>) -> Color {
+        match self {
+            Self::Solid(color) => *color,
+            Self::Linear {
+                start,
+                dir,
+                gradient,
+            } => gradient.color((p.x - start.x) * dir.x + (p.y - start.y) * dir.y),
+            Self::Radial {
+                center,
+                radius,
+                focus,
+                gradient,
+            } => {
+                let px = (p.x - center.x) / radius.x;
+                let py = (p.y - center.y) / radius.y;
+                let (dx, dy) = (px - focus.x, py - focus.y);
+                let dd = dx * dx + dy * dy;
+                if dd <= 0.0 || dd.is_nan() {
+                    return gradient.color(0.0);
+                }
+                let fd = focus.x * dx + focus.y * dy;
+                let ff = focus.x * focus.x + focus.y * focus.y;
+                let disc = (fd * fd - dd * (ff - 1.0)).max(0.0);
+                let s = (-fd + disc.sqrt()) / dd;
+                gradient.color(if s > 0.0 { s.recip() } else { 0.0 })
+            }
+            Self::Image {
+                data,
+                dest,
+                src,
+                nearest,
+            } => {
+                let u = src.origin.x + (p.x - dest.origin.x) * src.size.width / dest.size.width;
+                let v = src.origin.y + (p.y - dest.origin.y) * src.size.height / dest.size.height;
+                let texel = |x: f32, y: f32| {
+                    let x = (x.max(0.0) as u32).min(data.width - 1);
+                    let y = (y.max(0.0) as u32).min(data.height - 1);
+                    let c = data.format.load(data.pixel(x, y));
+                    Color {
+                        r: c[0],
+                        g: c[1],
+                        b: c[2],
+                        a: c[3],
+                    }
+                };
+                if *nearest {
+                    return texel(u.floor(), v.floor());
+                }
+                let (u, v) = (u - 0.5, v - 0.5);
+                let (x, y) = (u.floor(), v.floor());
+                let (fx, fy) = (u - x, v - y);
+                let top = texel(x, y).lerp(texel(x + 1.0, y), fx);
+                let bottom = texel(x, y + 1.0).lerp(texel(x + 1.0, y + 1.0), fx);
+                top.lerp(bottom, fy)
+            }
+        }
+    }
+
+    #[inline]
+    fn is_solid(&self) -> bool {
+        matches!(self, Self::Solid(_))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ClipRect {
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+}
+
+impl ClipRect {
+    #[inline]
+    fn coverage(&self, x: usize, y: usize) -> f32 {
+        let (x, y) = (x as f32, y as f32);
+        let cx = (self.right.min(x + 1.0) - self.left.max(x)).clamp(0.0, 1.0);
+        let cy = (self.bottom.min(y + 1.0) - self.top.max(y)).clamp(0.0, 1.0);
+        cx * cy
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Winding {
+    NonZero,
+    EvenOdd,
+}
+
+struct Rasterizer {
+    top: usize,
+    stride: usize,
+    width: f32,
+    rows: usize,
+    acc: Vec<f32>,
+}
+
+impl Rasterizer {
+    fn new(width: usize, top: usize, bottom: usize) -> Self {
+        let stride = width + 2;
+        let rows = bottom - top;
+        Self {
+            top,
+            stride,
+            width: width as f32,
+            rows,
+            acc: vec![0.0; stride * rows],
+        }
+    }
+
+    fn line(&mut self, p0: Point<f32>, p1: Point<f32>) {
+        let (x0, x1) = (p0.x.min(p1.x), p0.x.max(p1.x));
+        let mut cuts = [0.0f32, self.width]
+            .into_iter()
+            .filter(|&x| x0 < x && x < x1)
+            .map(|x| (x - p0.x) / (p1.x - p0.x))
+            .collect::<Vec<_>>();
+        cuts.sort_by(f32::total_cmp);
+        let mut start = p0;
+        for t in cuts.into_iter().chain(std::iter::once(1.0)) {
+            let end = if t >= 1.0 {
+                p1
+            } else {
+                Point::new(p0.x + (p1.x - p0.x) * t, p0.y + (p1.y - p0.y) * t)
+            };
+            let clamp =
+                |p: Point<f32>| Point::new(p.x.clamp(0.0, self.width), p.y - self.top as f32);
+            self.accumulate(clamp(start), clamp(end));
+            start = end;
+        }
+    }
+
+    fn accumulate(&mut self, p0: Point<f32>, p1: Point<f32>) {
+        if p0.y == p1.y {
+            return;
+        }
+        let (dir, p0, p1) = if p0.y < p1.y {
+            (1.0, p0, p1)
+        } else {
+            (-1.0, p1, p0)
+        };
+        let dxdy = (p1.x - p0.x) / (p1.y - p0.y);
+        let mut x = p0.x;
+        let y0 = p0.y.max(0.0);
+        if p0.y < 0.0 {
+            x -= p0.y * dxdy;
+        }
+        let y_end = (p1.y.ceil().max(0.0) as usize).min(self.rows);
+        for y in (y0 as usize)..y_end {
+            let row = y * self.stride;
+            let dy = ((y + 1) as f32).min(p1.y) - (y as f32).max(p0.y);
+            let xnext = x + dxdy * dy;
+            let d = dy * dir;
+            let (x0, x1) = if x < xnext { (x, xnext) } else { (xnext, x) };
+            let x0floor = x0.floor();
+            let x0i = x0floor as usize;
+            let x1ceil = x1.ceil();
+            let x1i = x1ceil as usize;
+            if x1i <= x0i + 1 {
+                let xmf = 0.5 * (x + xnext) - x0floor;
+                self.acc[row + x0i] += d - d * xmf;
+                self.acc[row + x0i + 1] += d * xmf;
+            } else {
+                let s = (x1 - x0).recip();
+                let x0f = x0 - x0floor;
+                let a0 = 0.5 * s * (1.0 - x0f) * (1.0 - x0f);
+                let x1f = x1 - x1ceil + 1.0;
+                let am = 0.5 * s * x1f * x1f;
+                self.acc[row + x0i] += d * a0;
+                if x1i == x0i + 2 {
+                    self.acc[row + x0i + 1] += d * (1.0 - a0 - am);
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    self.acc[row + x0i + 1] += d * (a1 - a0);
+                    for xi in x0i + 2..x1i - 1 {
+                        self.acc[row + xi] += d * s;
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                    self.acc[row + x1i - 1] += d * (1.0 - a2 - am);
+                }
+                self.acc[row + x1i] += d * am;
+            }
+            x = xnext;
+        }
+    }
+}
+
+struct Canvas<'a> {
+    width: usize,
+    height: usize,
+    pixels: RefCell<&'a mut Vec<u8>>,
+    base: Transform,
+    transform: Cell<Transform>,
+    clips: RefCell<Vec<ClipRect>>,
+}
+
+impl<'a> Canvas<'a> {
+    #[inline]
+    fn device_transform(&self) -> Transform {
+        self.transform.get() * self.base
+    }
+
+    fn clip(&self) -> ClipRect {
+        let mut clip = ClipRect {
+            left: 0.0,
+            top: 0.0,
+            right: self.width as f32,
+            bottom: self.height as f32,
+        };
+        for c in self.clips.borrow().iter() {
+            clip.left = clip.left.max(c.left);
+            clip.top = clip.top.max(c.top);
+            clip.right = clip.right.min(c.right);
+            clip.bottom = clip.bottom.min(c.bottom);
+        }
+        clip
+    }
+
+    fn fill_polygons(&self, polygons: &[Polygon], winding: Winding, paint: &Paint) {
+        let clip = self.clip();
+        let mut bounds = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for point in polygons.iter().flatten() {
+            if !point.x.is_finite() || !point.y.is_finite() {
+                return;
+            }
+            bounds.0 = bounds.0.min(point.x);
+            bounds.1 = bounds.1.min(point.y);
+            bounds.2 = bounds.2.max(point.x);
+            bounds.3 = bounds.3.max(point.y);
+        }
+        let left = bounds.0.max(clip.left).max(0.0).floor() as usize;
+        let top = bounds.1.max(clip.top).max(0.0).floor() as usize;
+        let right = (bounds.2.min(clip.right).ceil().max(0.0) as usize).min(self.width);
+        let bottom = (bounds.3.min(clip.bottom).ceil().max(0.0) as usize).min(self.height);
+        if left >= right || top >= bottom {
+            return;
+        }
+        let mut raster = Rasterizer::new(self.width, top, bottom);
+        for polygon in polygons {
+            for (i, p0) in polygon.iter().enumerate() {
+                raster.line(*p0, polygon[(i + 1) % polygon.len()]);
+            }
+        }
+        let inverse = if paint.is_solid() {
+            Transform::identity()
+        } else {
+            match self.device_transform().invert() {
+                Some(inverse) => inverse,
+                None => return,
+            }
+        };
+        let mut pixels = self.pixels.borrow_mut();
+        for y in top..bottom {
+            let row = (y - top) * raster.stride;
+            let mut acc = 0.0;
+            for x in 0..right {
+                acc += raster.acc[row + x];
+                if x < left {
+                    continue;
+                }
+                let coverage = match winding {
+                    Winding::NonZero => acc.abs().min(1.0),
+                    Winding::EvenOdd => {
+                        let c = acc.abs() % 2.0;
+                        if c > 1.0 {
+                            2.0 - c
+                        } else {
+                            c
+                        }
+                    }
+                } * clip.coverage(x, y);
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let color = paint
+                    .color(inverse.transform_point(Point::new(x as f32 + 0.5, y as f32 + 0.5)));
+                let i = (y * self.width + x) * 4;
+                blend(&mut pixels[i..i + 4], color, coverage);
+            }
+        }
+    }
+}
+
+#[inline]
+fn blend(dst: &mut [u8], src: Color, coverage: f32) {
+    let inv = 1.0 - src.a * coverage;
+    let f = |d: u8, s: f32| {
+        ((d as f32 / 255.0 * inv + s * coverage) * 255.0)
+            .round()
+            .clamp(0.0, 255.0) as u8
+    };
+    dst[0] = f(dst[0], src.r);
+    dst[1] = f(dst[1], src.g);
+    dst[2] = f(dst[2], src.b);
+    dst[3] = f(dst[3], src.a);
+}
+
+impl<'a> Renderer for Canvas<'a> {
+    fn clear(&self, color: Rgba<f32>) {
+        let color = Color::premultiplied(color);
+        let clip = self.clip();
+        let mut pixels = self.pixels.borrow_mut();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let coverage = clip.coverage(x, y);
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let i = (y * self.width + x) * 4;
+                let dst = &mut pixels[i..i + 4];
+                let f = |d: u8, s: f32| {
+                    ((d as f32 / 255.0 * (1.0 - coverage) + s * coverage) * 255.0)
+                        .round()
+                        .clamp(0.0, 255.0) as u8
+                };
+                dst[0] = f(dst[0], color.r);
+                dst[1] = f(dst[1], color.g);
+                dst[2] = f(dst[2], color.b);
+                dst[3] = f(dst[3], color.a);
+            }
+        }
+    }
+
+    fn fill(&self, shape: &Shape, brush: &Brush) {
+        if !shape.is_fillable() {
+            return;
+        }
+        let transform = self.device_transform();
+        let figures = shape
+            .figures()
+            .iter()
+            .map(|figure| figure.transform(&transform))
+            .collect::<Vec<_>>();
+        let polygons = flatten(&figures, TOLERANCE)
+            .into_iter()
+            .map(|polyline| polyline.points)
+            .collect::<Vec<_>>();
+        let winding = match shape.fill_rule() {
+            FillRule::EvenOdd => Winding::EvenOdd,
+            FillRule::NonZero => Winding::NonZero,
+        };
+        self.fill_polygons(&polygons, winding, &Paint::new(brush));
+    }
+
+    fn stroke(&self, shape: &Shape, brush: &Brush, width: f32, style: Option<&StrokeStyle>) {
+        let transform = self.device_transform();
+        let props = style.map(|style| style.properties()).unwrap_or_default();
+        let polygons = stroke_polygons(&shape.figures(), width, &props, &transform, TOLERANCE);
+        self.fill_polygons(&polygons, Winding::NonZero, &Paint::new(brush));
+    }
+
+    #[cfg(windows)]
+    fn draw_text(&self, _layout: &TextLayout, _origin: Point<f32>, _brush: &Brush) {}
+
+    fn draw_image(
+        &self,
+        image: &Image,
+        dest_rect: Rect<f32>,
+        src_rect: Option<Rect<f32>>,
+        interpolation: Interpolation,
+    ) {
+        let size = image.size();
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        let src = src_rect.unwrap_or(Rect::new(
+            (0.0, 0.0),
+            (size.width as f32, size.height as f32),
+        ));
+        let transform = self.device_transform();
+        let ep = dest_rect.endpoint();
+        let polygon = [
+            dest_rect.origin,
+            Point::new(ep.x, dest_rect.origin.y),
+            ep,
+            Point::new(dest_rect.origin.x, ep.y),
+        ]
+        .map(|p| transform.transform_point(p))
+        .to_vec();
+        let paint = Paint::Image {
+            data: image
+                .data()
+                .convert(PixelFormat::Rgba8, AlphaMode::Premultiplied),
+            dest: dest_rect,
+            src,
+            nearest: interpolation == Interpolation::NearestNeighbor,
+        };
+        self.fill_polygons(&[polygon], Winding::NonZero, &paint);
+    }
+
+    fn push_clip(&self, rect: Rect<f32>) {
+        let transform = self.device_transform();
+        let ep = rect.endpoint();
+        let corners = [
+            rect.origin,
+            Point::new(ep.x, rect.origin.y),
+            ep,
+            Point::new(rect.origin.x, ep.y),
+        ]
+        .map(|p| transform.transform_point(p));
+        let mut clip = ClipRect {
+            left: f32::MAX,
+            top: f32::MAX,
+            right: f32::MIN,
+            bottom: f32::MIN,
+        };
+        for p in corners {
+            clip.left = clip.left.min(p.x);
+            clip.top = clip.top.min(p.y);
+            clip.right = clip.right.max(p.x);
+            clip.bottom = clip.bottom.max(p.y);
+        }
+        self.clips.borrow_mut().push(clip);
+    }
+
+    #[inline]
+    fn pop_clip(&self) {
+        self.clips.borrow_mut().pop();
+    }
+
+    #[inline]
+    fn translate(&self, point: Point<f32>) {
+        self.transform
+            .set(Transform::translation(point.x, point.y) * self.transform.get());
+    }
+
+    #[inline]
+    fn scale(&self, size: Size<f32>) {
+        self.transform
+            .set(Transform::scale(size.width, size.height) * self.transform.get());
+    }
+
+    #[inline]
+    fn concat(&self, transform: &Transform) {
+        self.transform.set(*transform * self.transform.get());
+    }
+
+    #[inline]
+    fn set_transform(&self, transform: &Transform) {
+        self.transform.set(*transform);
+    }
+
+    #[inline]
+    fn transform(&self) -> Transform {
+        self.transform.get()
+    }
+
+    #[inline]
+    fn reset_transform(&self) {
+        self.transform.set(Transform::identity());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(size: (u32, u32), f: impl FnOnce(&DrawCommand, &Factory)) -> ImageData {
+        let ctx = Context::new(Software::new()).unwrap();
+        let factory = ctx.create_factory();
+        let target = ctx.create_render_target(size).unwrap();
+        ctx.draw(&target, |cmd| f(cmd, &factory)).unwrap();
+        ctx.read_pixels(&target).unwrap()
+    }
+
+    fn pixel(image: &ImageData, x: u32, y: u32) -> [u8; 4] {
+        image.pixel(x, y).try_into().unwrap()
+    }
+
+    #[test]
+    fn fill_rect() {
+        let image = render((8, 8), |cmd, factory| {
+            cmd.clear((0.0, 0.0, 0.0, 1.0));
+            let brush = factory
+                .create_solid_color_brush((1.0, 0.0, 0.0, 1.0))
+                .unwrap();
+            cmd.fill(&Rect::new((2.0, 2.0), (4.0, 4.0)), &brush);
+        });
+        assert_eq!(pixel(&image, 0, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(&image, 2, 2), [255, 0, 0, 255]);
+        assert_eq!(pixel(&image, 5, 5), [255, 0, 0, 255]);
+        assert_eq!(pixel(&image, 6, 6), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn shapes() {
+        let path = PathData::builder()
+            .begin((2.0, 2.0))
+            .line_to((14.0, 2.0))
+            .line_to((14.0, 14.0))
+            .line_to((2.0, 14.0))
+            .end(FigureEnd::Closed)
+            .unwrap()
+            .close()
+            .unwrap();
+        let fills: [&dyn Fill; 5] = [
+            &Rect::new((2.0, 2.0), (12.0, 12.0)),
+            &Circle::new((8.0, 8.0), 6.0),
+            &Ellipse::new((8.0, 8.0), (6.0, 6.0)),
+            &RoundedRect::new(Rect::new((2.0, 2.0), (12.0, 12.0)), (2.0, 2.0)),
+            &FilledPath::from(path.clone()),
+        ];
+        for shape in fills {
+            let image = render((16, 16), |cmd, factory| {
+                let brush = factory
+                    .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                    .unwrap();
+                shape.fill(cmd.renderer, &brush);
+            });
+            assert_eq!(pixel(&image, 8, 8), [255, 255, 255, 255]);
+            assert_eq!(pixel(&image, 0, 8), [0, 0, 0, 0]);
+        }
+        let strokes: [&dyn Stroke; 7] = [
+            &Rect::new((2.0, 2.0), (12.0, 12.0)),
+            &Circle::new((8.0, 8.0), 6.0),
+            &Ellipse::new((8.0, 8.0), (6.0, 6.0)),
+            &RoundedRect::new(Rect::new((2.0, 2.0), (12.0, 12.0)), (2.0, 2.0)),
+            &line((2.0, 8.0), (14.0, 8.0)),
+            &FilledPath::from(path.clone()),
+            &HollowPath::from(path),
+        ];
+        for shape in strokes {
+            let image = render((16, 16), |cmd, factory| {
+                let brush = factory
+                    .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                    .unwrap();
+                shape.stroke(cmd.renderer, &brush, 2.0, None);
+            });
+            assert_eq!(pixel(&image, 2, 8), [255, 255, 255, 255]);
+            assert_eq!(pixel(&image, 8, 5), [0, 0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn fill_rule() {
+        for (fill_rule, center) in [
+            (FillRule::EvenOdd, [0, 0, 0, 0]),
+            (FillRule::NonZero, [255, 255, 255, 255]),
+        ] {
+            let image = render((8, 8), |cmd, factory| {
+                let brush = factory
+                    .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                    .unwrap();
+                let path = factory
+                    .create_filled_path()
+                    .unwrap()
+                    .fill_rule(fill_rule)
+                    .svg("M0 0 H8 V8 H0 Z M2 2 H6 V6 H2 Z")
+                    .unwrap()
+                    .close()
+                    .unwrap();
+                cmd.fill(&path, &brush);
+            });
+            assert_eq!(pixel(&image, 0, 0), [255, 255, 255, 255]);
+            assert_eq!(pixel(&image, 4, 4), center, "{:?}", fill_rule);
+        }
+    }
+
+    #[test]
+    fn transform_stack() {
+        let image = render((8, 8), |cmd, factory| {
+            let brush = factory
+                .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                .unwrap();
+            cmd.translate((1.0, 0.0));
+            cmd.with_transform(&Transform::translation(4.0, 0.0), |cmd| {
+                cmd.push_transform(&Transform::translation(0.0, 4.0));
+                cmd.translate((1.0, 1.0));
+                cmd.fill(&Rect::new((0.0, 0.0), (1.0, 1.0)), &brush);
+                cmd.pop_transform();
+                cmd.fill(&Rect::new((0.0, 0.0), (1.0, 1.0)), &brush);
+            });
+            cmd.fill(&Rect::new((0.0, 0.0), (1.0, 1.0)), &brush);
+        });
+        assert_eq!(pixel(&image, 6, 5), [255, 255, 255, 255]);
+        assert_eq!(pixel(&image, 5, 0), [255, 255, 255, 255]);
+        assert_eq!(pixel(&image, 1, 0), [255, 255, 255, 255]);
+        assert_eq!(pixel(&image, 0, 0), [0, 0, 0, 0]);
+
+        let ctx = Context::new(Software::new()).unwrap();
+        let target = ctx.create_render_target((1, 1)).unwrap();
+        let unbalanced = ctx.draw(&target, |cmd| {
+            cmd.push_transform(&Transform::scale(2.0, 2.0));
+            cmd.clear((1.0, 1.0, 1.0, 1.0));
+        });
+        assert_eq!(unbalanced, Err(Error::UnbalancedTransform));
+        assert_eq!(
+            pixel(&ctx.read_pixels(&target).unwrap(), 0, 0),
+            [255, 255, 255, 255]
+        );
+        let unbalanced = ctx.draw(&target, |cmd| cmd.pop_transform());
+        assert_eq!(unbalanced, Err(Error::UnbalancedTransform));
+        assert_eq!(
+            ctx.draw(&target, |cmd| cmd.transform()),
+            Ok(Transform::identity())
+        );
+    }
+
+    #[test]
+    fn geometry_group() {
+        let image = render((8, 8), |cmd, factory| {
+            let brush = factory
+                .create_solid_color_brush((1.0, 1.0, 1.0, 0.5))
+                .unwrap();
+            let group = GeometryGroup::new(FillRule::NonZero)
+                .with(&Rect::new((0.0, 0.0), (4.0, 4.0)))
+                .with(&Rect::new((2.0, 2.0), (4.0, 4.0)));
+            cmd.fill(&group, &brush);
+        });
+        assert_eq!(pixel(&image, 1, 1), pixel(&image, 3, 3));
+        assert_eq!(pixel(&image, 5, 5), pixel(&image, 3, 3));
+        assert_eq!(pixel(&image, 7, 7), [0, 0, 0, 0]);
+
+        let image = render((8, 8), |cmd, factory| {
+            let brush = factory
+                .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                .unwrap();
+            let donut = GeometryGroup::new(FillRule::EvenOdd)
+                .with(&Rect::new((0.0, 0.0), (8.0, 8.0)))
+                .with(&Rect::new((2.0, 2.0), (4.0, 4.0)));
+            cmd.fill(&donut, &brush);
+        });
+        assert_eq!(pixel(&image, 1, 1), [255, 255, 255, 255]);
+        assert_eq!(pixel(&image, 4, 4), [0, 0, 0, 0]);
+
+        let corner = PathData::builder()
+            .begin((10.0, 2.0))
+            .line_to((14.0, 2.0))
+            .line_to((14.0, 6.0))
+            .end(FigureEnd::Open)
+            .unwrap()
+            .close()
+            .unwrap();
+        let group = GeometryGroup::new(FillRule::NonZero)
+            .with(&Rect::new((2.0, 2.0), (4.0, 4.0)))
+            .with(&HollowPath::from(corner));
+        let white = [255, 255, 255, 255];
+        let image = render((16, 8), |cmd, factory| {
+            let brush = factory
+                .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                .unwrap();
+            cmd.stroke(&group, &brush, 2.0, None);
+        });
+        assert_eq!(pixel(&image, 2, 4), white);
+        assert_eq!(pixel(&image, 12, 1), white);
+        assert_eq!(pixel(&image, 13, 4), white);
+        assert_eq!(pixel(&image, 11, 3), [0, 0, 0, 0]);
+        assert_eq!(pixel(&image, 12, 4), [0, 0, 0, 0]);
+        let image = render((16, 8), |cmd, factory| {
+            let brush = factory
+                .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                .unwrap();
+            cmd.fill(&group, &brush);
+        });
+        assert_eq!(pixel(&image, 4, 4), white);
+        assert_eq!(pixel(&image, 13, 3), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn offscreen_target() {
+        let ctx = Context::new(Software::new()).unwrap();
+        let factory = ctx.create_factory();
+        let target = ctx
+            .create_offscreen_target((4, 4), PixelFormat::Bgra8, AlphaMode::Premultiplied)
+            .unwrap();
+        let brush = factory
+            .create_solid_color_brush((1.0, 0.5, 0.0, 1.0))
+            .unwrap();
+        ctx.draw(&target, |cmd| {
+            cmd.fill(&Rect::new((0.0, 0.0), (2.0, 4.0)), &brush);
+        })
+        .unwrap();
+        let image = ctx.read_pixels(&target).unwrap();
+        assert_eq!(image.format, PixelFormat::Bgra8);
+        assert_eq!(target.physical_size(), Size::new(4, 4));
+        assert_eq!(pixel(&image, 0, 0), [0, 128, 255, 255]);
+        assert_eq!(pixel(&image, 3, 0), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn antialiased_edge() {
+        let image = render((4, 1), |cmd, factory| {
+            let brush = factory
+                .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                .unwrap();
+            cmd.fill(&Rect::new((0.0, 0.0), (1.5, 1.0)), &brush);
+        });
+        assert_eq!(pixel(&image, 0, 0), [255, 255, 255, 255]);
+        assert_eq!(pixel(&image, 1, 0), [128, 128, 128, 128]);
+        assert_eq!(pixel(&image, 2, 0), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn fill_circle() {
+        let image = render((20, 20), |cmd, factory| {
+            let brush = factory
+                .create_solid_color_brush((0.0, 0.0, 1.0, 1.0))
+                .unwrap();
+            cmd.fill(&Circle::new((10.0, 10.0), 5.0), &brush);
+        });
+        assert_eq!(pixel(&image, 10, 10), [0, 0, 255, 255]);
+        assert_eq!(pixel(&image, 1, 1), [0, 0, 0, 0]);
+        let total: u32 = image.bytes.chunks(4).map(|p| p[3] as u32).sum();
+        let area = total as f32 / 255.0;
+        assert!((area - std::f32::consts::PI * 25.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn stroke_line() {
+        let image = render((10, 10), |cmd, factory| {
+            let brush = factory
+                .create_solid_color_brush((0.0, 1.0, 0.0, 1.0))
+                .unwrap();
+            cmd.stroke(&line((0.0, 5.0), (10.0, 5.0)), &brush, 2.0, None);
+        });
+        assert_eq!(pixel(&image, 5, 3), [0, 0, 0, 0]);
+        assert_eq!(pixel(&image, 5, 4), [0, 255, 0, 255]);
+        assert_eq!(pixel(&image, 5, 5), [0, 255, 0, 255]);
+        assert_eq!(pixel(&image, 5, 6), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn stroke_caps() {
+        let render_cap = |cap| {
+            render((10, 4), |cmd, factory| {
+                let brush = factory
+                    .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                    .unwrap();
+                let style = factory
+                    .create_stroke_style(&StrokeStyleProperties {
+                        start_cap: cap,
+                        end_cap: cap,
+                        ..Default::default()
+                    })
+                    .unwrap();
+                cmd.stroke(&line((3.0, 2.0), (7.0, 2.0)), &brush, 2.0, Some(&style));
+            })
+        };
+        let flat = render_cap(CapStyle::Flat);
+        assert_eq!(pixel(&flat, 2, 1)[3], 0);
+        assert_eq!(pixel(&flat, 3, 1)[3], 255);
+        let square = render_cap(CapStyle::Square);
+        assert_eq!(pixel(&square, 2, 1)[3], 255);
+        assert_eq!(pixel(&square, 7, 1)[3], 255);
+        assert_eq!(pixel(&square, 8, 1)[3], 0);
+    }
+
+    #[test]
+    fn stroke_dashes() {
+        let image = render((16, 2), |cmd, factory| {
+            let brush = factory
+                .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                .unwrap();
+            let style = factory
+                .create_stroke_style(&StrokeStyleProperties {
+                    dash: Some(Dash {
+                        style: DashStyle::Dash,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .unwrap();
+            cmd.stroke(&line((0.0, 1.0), (16.0, 1.0)), &brush, 2.0, Some(&style));
+        });
+        let row = (0..16).map(|x| pixel(&image, x, 0)[3]).collect::<Vec<_>>();
+        assert_eq!(
+            row,
+            [255, 255, 255, 255, 0, 0, 0, 0, 255, 255, 255, 255, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn stroke_rect_miter() {
+        let image = render((10, 10), |cmd, factory| {
+            let brush = factory
+                .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                .unwrap();
+            cmd.stroke(&Rect::new((2.0, 2.0), (6.0, 6.0)), &brush, 2.0, None);
+        });
+        assert_eq!(pixel(&image, 1, 1)[3], 255);
+        assert_eq!(pixel(&image, 8, 8)[3], 255);
+        assert_eq!(pixel(&image, 5, 5)[3], 0);
+        assert_eq!(pixel(&image, 0, 0)[3], 0);
+    }
+
+    #[test]
+    fn linear_gradient() {
+        let image = render((10, 1), |cmd, factory| {
+            let stops = factory
+                .create_gradient_stop_collection(
+                    GradientMode::Clamp,
+                    &[(0.0, (0.0, 0.0, 0.0, 1.0)), (1.0, (1.0, 1.0, 1.0, 1.0))],
+                )
+                .unwrap();
+            let brush = factory
+                .create_linear_gradient_brush((0.0, 0.0), (10.0, 0.0), &stops)
+                .unwrap();
+            cmd.fill(&Rect::new((0.0, 0.0), (10.0, 1.0)), &brush);
+        });
+        assert_eq!(pixel(&image, 0, 0), [13, 13, 13, 255]);
+        assert_eq!(pixel(&image, 9, 0), [242, 242, 242, 255]);
+    }
+
+    #[test]
+    fn radial_gradient() {
+        let image = render((21, 21), |cmd, factory| {
+            let stops = factory
+                .create_gradient_stop_collection(
+                    GradientMode::Clamp,
+                    &[(0.0, (1.0, 1.0, 1.0, 1.0)), (1.0, (0.0, 0.0, 0.0, 1.0))],
+                )
+                .unwrap();
+            let brush = factory
+                .create_radial_gradient_brush(
+                    Ellipse::new((10.5, 10.5), (10.0, 10.0)),
+                    (0.0, 0.0),
+                    &stops,
+                )
+                .unwrap();
+            cmd.fill(&Rect::new((0.0, 0.0), (21.0, 21.0)), &brush);
+        });
+        assert_eq!(pixel(&image, 10, 10), [255, 255, 255, 255]);
+        assert_eq!(pixel(&image, 15, 10), [128, 128, 128, 255]);
+        assert_eq!(pixel(&image, 0, 10), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn clip_and_transform() {
+        let image = render((10, 10), |cmd, factory| {
+            let brush = factory
+                .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                .unwrap();
+            cmd.translate((2.0, 2.0));
+            cmd.clip(Rect::new((0.0, 0.0), (4.0, 4.0)), |cmd| {
+                cmd.fill(&Rect::new((-2.0, -2.0), (10.0, 10.0)), &brush);
+            });
+        });
+        assert_eq!(pixel(&image, 1, 1)[3], 0);
+        assert_eq!(pixel(&image, 2, 2)[3], 255);
+        assert_eq!(pixel(&image, 5, 5)[3], 255);
+        assert_eq!(pixel(&image, 6, 6)[3], 0);
+    }
+
+    #[test]
+    fn read_pixels() {
+        let image = render((3, 2), |cmd, _| cmd.clear((1.0, 0.0, 0.0, 0.5)));
+        assert_eq!(image.width, 3);
+        assert_eq!(image.height, 2);
+        assert_eq!(image.stride, 12);
+        assert_eq!(image.format, PixelFormat::Rgba8);
+        assert_eq!(image.bytes.len(), 24);
+        assert_eq!(image.row(1), [128, 0, 0, 128].repeat(3));
+    }
+
+    #[test]
+    fn even_odd_path() {
+        let image = render((10, 10), |cmd, factory| {
+            let brush = factory
+                .create_solid_color_brush((1.0, 1.0, 1.0, 1.0))
+                .unwrap();
+            let path = factory
+                .create_filled_path()
+                .unwrap()
+                .begin((0.0, 0.0))
+                .lines(&[(10.0, 0.0).into(), (10.0, 10.0).into(), (0.0, 10.0).into()])
+                .end(FigureEnd::Closed)
+                .unwrap()
+                .begin((3.0, 3.0))
+                .lines(&[(7.0, 3.0).into(), (7.0, 7.0).into(), (3.0, 7.0).into()])
+                .end(FigureEnd::Closed)
+                .unwrap()
+                .close()
+                .unwrap();
+            cmd.fill(&path, &brush);
+        });
+        assert_eq!(pixel(&image, 1, 1)[3], 255);
+        assert_eq!(pixel(&image, 5, 5)[3], 0);
+    }
+
+    #[test]
+    fn rgba16f_is_quantised() {
+        let ctx = Context::new(Software::new()).unwrap();
+        let target = ctx
+            .create_offscreen_target((1, 1), PixelFormat::Rgba16F, AlphaMode::Premultiplied)
+            .unwrap();
+        ctx.draw(&target, |cmd| cmd.clear((0.3, 0.3, 0.3, 1.0)))
+            .unwrap();
+        let image = ctx.read_pixels(&target).unwrap();
+        assert_eq!(image.format, PixelFormat::Rgba16F);
+        let [r, _, _, a] = PixelFormat::Rgba16F.load(image.pixel(0, 0));
+        assert_eq!(a, 1.0);
+        assert!((r - 0.3).abs() > 1e-3);
+        assert!((r - 77.0 / 255.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn draw_image() {
+        let image = Image::new(ImageData {
+            width: 2,
+            height: 1,
+            stride: 8,
+            format: PixelFormat::Rgba8,
+            alpha_mode: AlphaMode::Premultiplied,
+            bytes: vec![255, 0, 0, 255, 0, 255, 0, 255],
+        });
+        let image = render((8, 4), |cmd, _| {
+            cmd.draw_image(
+                &image,
+                Rect::new((0.0, 0.0), (4.0, 2.0)),
+                None,
+                Interpolation::NearestNeighbor,
+            );
+            cmd.draw_image(
+                &image,
+                Rect::new((4.0, 2.0), (4.0, 2.0)),
+                Some(Rect::new((1.0, 0.0), (1.0, 1.0))),
+                Interpolation::Linear,
+            );
+        });
+        assert_eq!(pixel(&image, 0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(&image, 1, 1), [255, 0, 0, 255]);
+        assert_eq!(pixel(&image, 3, 1), [0, 255, 0, 255]);
+        assert_eq!(pixel(&image, 7, 3), [0, 255, 0, 255]);
+        assert_eq!(pixel(&image, 4, 0), [0, 0, 0, 0]);
+        assert_eq!(pixel(&image, 0, 3), [0, 0, 0, 0]);
+    }
+}
@@ -1,16 +1,13 @@
-use crate::*;
-use windows::Win32::Graphics::Direct2D::*;
-
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(u32)]
 pub enum CapStyle {
-    Flat = D2D1_CAP_STYLE_FLAT.0,
-    Square = D2D1_CAP_STYLE_SQUARE.0,
-    Round = D2D1_CAP_STYLE_ROUND.0,
-    Triangle = D2D1_CAP_STYLE_TRIANGLE.0,
+    Flat = 0,
+    Square = 1,
+    Round = 2,
+    Triangle = 3,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum LineJoin {
     Miter,
     Bevel,
@@ -18,7 +15,21 @@ pub enum LineJoin {
     MiterOrBevel(f32),
 }
 
-#[derive(Clone, Copy, Debug)]
+// The limit of `LineJoin::Miter`, shared by every backend so that sharp joins are cut off at the
+// same length everywhere.
+pub(crate) const MITER_LIMIT: f32 = 10.0;
+
+impl LineJoin {
+    #[inline]
+    pub(crate) fn miter_limit(&self) -> f32 {
+        match self {
+            Self::MiterOrBevel(limit) => limit.max(1.0),
+            _ => MITER_LIMIT,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum DashStyle<'a> {
     Solid,
     Dash,
@@ -28,7 +39,7 @@ pub enum DashStyle<'a> {
     Custom(&'a [f32]),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Dash<'a> {
     pub cap: CapStyle,
     pub style: DashStyle<'a>,
@@ -46,7 +57,7 @@ impl<'a> Default for Dash<'a> {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct StrokeStyleProperties<'a> {
     pub start_cap: CapStyle,
     pub end_cap: CapStyle,
@@ -66,46 +77,67 @@ impl<'a> Default for StrokeStyleProperties<'a> {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct StrokeStyle(ID2D1StrokeStyle);
+#[derive(Clone, Debug)]
+pub struct StrokeStyle {
+    start_cap: CapStyle,
+    end_cap: CapStyle,
+    line_join: LineJoin,
+    dash: Option<Dash<'static>>,
+    dashes: Vec<f32>,
+    #[cfg(windows)]
+    pub(crate) handle:
+        crate::d2d1::DeviceCache<windows::Win32::Graphics::Direct2D::ID2D1StrokeStyle>,
+}
 
 impl StrokeStyle {
-    pub(crate) fn new(factory: &ID2D1Factory6, props: &StrokeStyleProperties) -> Result<Self> {
-        let (line_join, miter_limit) = match props.line_join {
-            LineJoin::Miter => (D2D1_LINE_JOIN_MITER, 1.0),
-            LineJoin::Bevel => (D2D1_LINE_JOIN_BEVEL, 1.0),
-            LineJoin::Round => (D2D1_LINE_JOIN_ROUND, 1.0),
-            LineJoin::MiterOrBevel(miter_limit) => (D2D1_LINE_JOIN_MITER_OR_BEVEL, miter_limit),
+    pub(crate) fn new(props: &StrokeStyleProperties) -> Self {
+        let dash = props.dash.as_ref().map(|dash| Dash {
+            cap: dash.cap,
+            style: match dash.style {
+                DashStyle::Solid => DashStyle::Solid,
+                DashStyle::Dash => DashStyle::Dash,
+                DashStyle::Dot => DashStyle::Dot,
+                DashStyle::DashDot => DashStyle::DashDot,
+                DashStyle::DashDotDot => DashStyle::DashDotDot,
+                DashStyle::Custom(_) => DashStyle::Custom(&[]),
+            },
+            offset: dash.offset,
+        });
+        let dashes = match props.dash.as_ref().map(|dash| dash.style) {
+            Some(DashStyle::Custom(dashes)) => dashes.to_vec(),
+            _ => vec![],
         };
-        let (dash_cap, dash_style, dash_offset, dashes) = match props.dash.as_ref() {
-            Some(dash) => {
-                let cap = D2D1_CAP_STYLE(dash.cap as u32);
-                let (style, dashes): (_, Option<&[f32]>) = match dash.style {
-                    DashStyle::Solid => (D2D1_DASH_STYLE_SOLID, None),
-                    DashStyle::Dash => (D2D1_DASH_STYLE_DASH, None),
-                    DashStyle::Dot => (D2D1_DASH_STYLE_DOT, None),
-                    DashStyle::DashDot => (D2D1_DASH_STYLE_DASH_DOT, None),
-                    DashStyle::DashDotDot => (D2D1_DASH_STYLE_DASH_DOT_DOT, None),
-                    DashStyle::Custom(dashes) => (D2D1_DASH_STYLE_CUSTOM, Some(dashes)),
-                };
-                (cap, style, dash.offset, dashes)
-            }
-            None => (D2D1_CAP_STYLE_FLAT, D2D1_DASH_STYLE_SOLID, 0.0, None),
-        };
-        let props = D2D1_STROKE_STYLE_PROPERTIES {
-            startCap: D2D1_CAP_STYLE(props.start_cap as _),
-            endCap: D2D1_CAP_STYLE(props.end_cap as _),
-            dashCap: dash_cap,
-            lineJoin: line_join,
-            miterLimit: miter_limit,
-            dashStyle: dash_style,
-            dashOffset: dash_offset,
-        };
-        let stroke_style = unsafe { factory.CreateStrokeStyle(&props, dashes)? };
-        Ok(StrokeStyle(stroke_style))
+        Self {
+            start_cap: props.start_cap,
+            end_cap: props.end_cap,
+            line_join: props.line_join,
+            dash,
+            dashes,
+            #[cfg(windows)]
+            handle: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn properties(&self) -> StrokeStyleProperties<'_> {
+        StrokeStyleProperties {
+            start_cap: self.start_cap,
+            end_cap: self.end_cap,
+            line_join: self.line_join,
+            dash: self.dash.as_ref().map(|dash| Dash {
+                style: match dash.style {
+                    DashStyle::Custom(_) => DashStyle::Custom(&self.dashes),
+                    style => style,
+                },
+                ..dash.clone()
+            }),
+        }
     }
+}
 
-    pub(crate) fn handle(&self) -> &ID2D1StrokeStyle {
-        &self.0
+impl PartialEq for StrokeStyle {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.properties() == other.properties()
     }
 }
@@ -0,0 +1,413 @@
+use crate::flatten::{flatten, Polyline, TOLERANCE};
+use crate::*;
+
+const EPSILON: f32 = 1e-5;
+
+pub(crate) type Polygon = Vec<Point<f32>>;
+
+#[inline]
+fn add(a: Point<f32>, b: Point<f32>) -> Point<f32> {
+    Point::new(a.x + b.x, a.y + b.y)
+}
+
+#[inline]
+fn sub(a: Point<f32>, b: Point<f32>) -> Point<f32> {
+    Point::new(a.x - b.x, a.y - b.y)
+}
+
+#[inline]
+fn mul(a: Point<f32>, s: f32) -> Point<f32> {
+    Point::new(a.x * s, a.y * s)
+}
+
+#[inline]
+fn dot(a: Point<f32>, b: Point<f32>) -> f32 {
+    a.x * b.x + a.y * b.y
+}
+
+#[inline]
+fn cross(a: Point<f32>, b: Point<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+#[inline]
+fn perp(a: Point<f32>) -> Point<f32> {
+    Point::new(-a.y, a.x)
+}
+
+#[inline]
+fn length(a: Point<f32>) -> f32 {
+    a.x.hypot(a.y)
+}
+
+#[inline]
+fn direction(from: Point<f32>, to: Point<f32>) -> Point<f32> {
+    let d = sub(to, from);
+    mul(d, length(d).recip())
+}
+
+pub(crate) fn signed_area(polygon: &[Point<f32>]) -> f32 {
+    let mut area = 0.0;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        area += cross(*a, b);
+    }
+    area * 0.5
+}
+
+pub(crate) fn dash_pattern(style: &StrokeStyleProperties) -> Option<(Vec<f32>, f32)> {
+    let dash = style.dash.as_ref()?;
+    let pattern = match dash.style {
+        DashStyle::Solid => return None,
+        DashStyle::Dash => vec![2.0, 2.0],
+        DashStyle::Dot => vec![0.0, 2.0],
+        DashStyle::DashDot => vec![2.0, 2.0, 0.0, 2.0],
+        DashStyle::DashDotDot => vec![2.0, 2.0, 0.0, 2.0, 0.0, 2.0],
+        DashStyle::Custom(dashes) => dashes.iter().map(|d| d.max(0.0)).collect(),
+    };
+    if pattern.iter().sum::<f32>() <= 0.0 {
+        return None;
+    }
+    Some((pattern, dash.offset))
+}
+
+// The line caps that SVG and PDF can draw.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+// The one line cap that SVG and PDF can draw this stroke with, or None if it has to be outlined.
+// Both formats have a single cap for every end and no triangle cap, and they bevel miter joins
+// past the limit where the other backends clip them.
+pub(crate) fn native_cap(figures: &[PathFigure], style: &StrokeStyleProperties) -> Option<LineCap> {
+    let mut caps = vec![];
+    if figures.iter().any(|figure| figure.end == FigureEnd::Open) {
+        caps.extend([style.start_cap, style.end_cap]);
+    }
+    if let (Some(dash), Some(_)) = (style.dash.as_ref(), dash_pattern(style)) {
+        caps.push(dash.cap);
+    }
+    let cap = caps.first().copied().unwrap_or(CapStyle::Flat);
+    if caps.iter().any(|&c| c != cap) || exceeds_miter_limit(figures, style) {
+        return None;
+    }
+    match cap {
+        CapStyle::Flat => Some(LineCap::Butt),
+        CapStyle::Round => Some(LineCap::Round),
+        CapStyle::Square => Some(LineCap::Square),
+        CapStyle::Triangle => None,
+    }
+}
+
+fn exceeds_miter_limit(figures: &[PathFigure], style: &StrokeStyleProperties) -> bool {
+    if style.line_join != LineJoin::Miter {
+        return false;
+    }
+    // The miter length over the stroke width is 2 / |n0 + n1|, and |n0 + n1|² = 2 + 2 d0·d1.
+    let limit = style.line_join.miter_limit();
+    let min = 4.0 / (limit * limit);
+    flatten(figures, TOLERANCE).iter().any(|polyline| {
+        let points = dedup(&polyline.points, polyline.closed);
+        let n = points.len();
+        if n < 3 {
+            return false;
+        }
+        let joins = if polyline.closed { 0..n } else { 1..n - 1 };
+        joins.into_iter().any(|i| {
+            let d0 = direction(points[(i + n - 1) % n], points[i]);
+            let d1 = direction(points[i], points[(i + 1) % n]);
+            cross(d0, d1).abs() > EPSILON && 2.0 + 2.0 * dot(d0, d1) < min
+        })
+    })
+}
+
+fn dedup(points: &[Point<f32>], closed: bool) -> Vec<Point<f32>> {
+    let mut ret: Vec<Point<f32>> = Vec::with_capacity(points.len());
+    for &point in points {
+        if ret
+            .last()
+            .is_none_or(|last| length(sub(point, *last)) > EPSILON)
+        {
+            ret.push(point);
+        }
+    }
+    if closed && ret.len() > 1 && length(sub(ret[0], ret[ret.len() - 1])) <= EPSILON {
+        ret.pop();
+    }
+    ret
+}
+
+struct Dash {
+    points: Vec<Point<f32>>,
+    start_cap: CapStyle,
+    end_cap: CapStyle,
+}
+
+fn split_dashes(
+    points: &[Point<f32>],
+    closed: bool,
+    pattern: &[f32],
+    offset: f32,
+    caps: (CapStyle, CapStyle, CapStyle),
+) -> Vec<Dash> {
+    let (start_cap, end_cap, dash_cap) = if closed {
+        (caps.2, caps.2, caps.2)
+    } else {
+        caps
+    };
+    let total: f32 = pattern.iter().sum();
+    let mut index = 0;
+    let mut remaining = offset.rem_euclid(total);
+    while remaining > 0.0 && remaining >= pattern[index] {
+        remaining -= pattern[index];
+        index = (index + 1) % pattern.len();
+    }
+    remaining = pattern[index] - remaining;
+    let mut dashes = vec![];
+    let mut current: Option<Dash> = None;
+    let edge_count = if closed {
+        points.len()
+    } else {
+        points.len() - 1
+    };
+    let finish = |dash: Option<Dash>, dir: Point<f32>, dashes: &mut Vec<Dash>| {
+        if let Some(mut dash) = dash {
+            if dash.points.len() == 1 {
+                let p = dash.points[0];
+                dash.points.push(add(p, mul(dir, EPSILON * 10.0)));
+            }
+            dashes.push(dash);
+        }
+    };
+    for i in 0..edge_count {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        let len = length(sub(b, a));
+        let dir = direction(a, b);
+        let mut t = 0.0;
+        loop {
+            let on = index % 2 == 0;
+            if on && current.is_none() {
+                current = Some(Dash {
+                    points: vec![add(a, mul(dir, t))],
+                    start_cap: if i == 0 && t == 0.0 {
+                        start_cap
+                    } else {
+                        dash_cap
+                    },
+                    end_cap: dash_cap,
+                });
+            }
+            let step = remaining.min(len - t);
+            t += step;
+            remaining -= step;
+            if on && step > 0.0 {
+                if let Some(dash) = current.as_mut() {
+                    dash.points.push(add(a, mul(dir, t)));
+                }
+            }
+            if remaining > 0.0 {
+                break;
+            }
+            if on {
+                finish(current.take(), dir, &mut dashes);
+            }
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+            if t >= len && remaining > 0.0 {
+                break;
+            }
+        }
+    }
+    if let Some(dash) = current.as_mut() {
+        dash.end_cap = end_cap;
+    }
+    let last_dir = direction(points[edge_count - 1], points[edge_count % points.len()]);
+    finish(current, last_dir, &mut dashes);
+    dashes
+}
+
+pub(crate) struct Stroker {
+    half_width: f32,
+    line_join: LineJoin,
+    tolerance: f32,
+    polygons: Vec<Polygon>,
+}
+
+impl Stroker {
+    pub(crate) fn new(width: f32, line_join: LineJoin, tolerance: f32) -> Self {
+        Self {
+            half_width: width * 0.5,
+            line_join,
+            tolerance,
+            polygons: vec![],
+        }
+    }
+
+    fn push(&mut self, mut polygon: Polygon) {
+        let area = signed_area(&polygon);
+        if area.abs() <= EPSILON * EPSILON {
+            return;
+        }
+        if area < 0.0 {
+            polygon.reverse();
+        }
+        self.polygons.push(polygon);
+    }
+
+    fn circle(&mut self, center: Point<f32>) {
+        let r = self.half_width;
+        let step = 2.0 * (1.0 - (self.tolerance / r).min(1.0)).acos();
+        let n = ((std::f32::consts::TAU / step).ceil() as usize).clamp(8, 256);
+        let polygon = (0..n)
+            .map(|i| {
+                let theta = std::f32::consts::TAU * i as f32 / n as f32;
+                Point::new(center.x + r * theta.cos(), center.y + r * theta.sin())
+            })
+            .collect();
+        self.push(polygon);
+    }
+
+    fn cap(&mut self, p: Point<f32>, dir: Point<f32>, cap: CapStyle) {
+        let h = self.half_width;
+        let n = mul(perp(dir), h);
+        let d = mul(dir, h);
+        match cap {
+            CapStyle::Flat => {}
+            CapStyle::Square => self.push(vec![
+                add(p, n),
+                add(add(p, n), d),
+                add(sub(p, n), d),
+                sub(p, n),
+            ]),
+            CapStyle::Round => self.circle(p),
+            CapStyle::Triangle => self.push(vec![add(p, n), add(p, d), sub(p, n)]),
+        }
+    }
+
+    fn join(&mut self, p: Point<f32>, d0: Point<f32>, d1: Point<f32>) {
+        let c = cross(d0, d1);
+        if c.abs() <= EPSILON && dot(d0, d1) > 0.0 {
+            return;
+        }
+        let h = self.half_width;
+        let s = if c > 0.0 { -h } else { h };
+        let n0 = perp(d0);
+        let n1 = perp(d1);
+        let a = add(p, mul(n0, s));
+        let b = add(p, mul(n1, s));
+        let limit = match self.line_join {
+            LineJoin::Bevel => {
+                self.push(vec![p, a, b]);
+                return;
+            }
+            LineJoin::Round => {
+                self.circle(p);
+                return;
+            }
+            LineJoin::Miter | LineJoin::MiterOrBevel(_) => self.line_join.miter_limit(),
+        };
+        let sum = add(n0, n1);
+        let sum_len = length(sum);
+        if sum_len <= EPSILON {
+            self.push(vec![p, a, b]);
+            return;
+        }
+        let ratio = 2.0 / sum_len;
+        if ratio <= limit {
+            let m = add(p, mul(sum, s * 2.0 / (sum_len * sum_len)));
+            self.push(vec![p, a, m, b]);
+            return;
+        }
+        if let LineJoin::MiterOrBevel(_) = self.line_join {
+            self.push(vec![p, a, b]);
+            return;
+        }
+        let u = mul(sum, s.signum() / sum_len);
+        let clip = limit * h;
+        let t0 = (clip - dot(sub(a, p), u)) / dot(d0, u);
+        let t1 = (clip - dot(sub(b, p), u)) / -dot(d1, u);
+        if !t0.is_finite() || !t1.is_finite() {
+            self.push(vec![p, a, b]);
+            return;
+        }
+        self.push(vec![p, a, add(a, mul(d0, t0)), sub(b, mul(d1, t1)), b]);
+    }
+
+    fn polyline(&mut self, points: &[Point<f32>], closed: bool, caps: (CapStyle, CapStyle)) {
+        let points = dedup(points, closed);
+        let n = points.len();
+        if n < 2 {
+            return;
+        }
+        let edge_count = if closed { n } else { n - 1 };
+        let dirs = (0..edge_count)
+            .map(|i| direction(points[i], points[(i + 1) % n]))
+            .collect::<Vec<_>>();
+        for (i, dir) in dirs.iter().enumerate() {
+            let a = points[i];
+            let b = points[(i + 1) % n];
+            let offset = mul(perp(*dir), self.half_width);
+            self.push(vec![
+                add(a, offset),
+                add(b, offset),
+                sub(b, offset),
+                sub(a, offset),
+            ]);
+        }
+        if closed {
+            for i in 0..n {
+                self.join(points[i], dirs[(i + edge_count - 1) % edge_count], dirs[i]);
+            }
+        } else {
+            for i in 1..n - 1 {
+                self.join(points[i], dirs[i - 1], dirs[i]);
+            }
+            self.cap(points[0], mul(dirs[0], -1.0), caps.0);
+            self.cap(points[n - 1], dirs[edge_count - 1], caps.1);
+        }
+    }
+
+    pub(crate) fn stroke(
+        mut self,
+        polylines: &[Polyline],
+        style: &StrokeStyleProperties,
+    ) -> Vec<Polygon> {
+        if self.half_width <= 0.0 {
+            return vec![];
+        }
+        let dash = dash_pattern(style).map(|(pattern, offset)| {
+            let width = self.half_width * 2.0;
+            (
+                pattern.iter().map(|d| d * width).collect::<Vec<_>>(),
+                offset * width,
+                style.dash.as_ref().map_or(CapStyle::Flat, |dash| dash.cap),
+            )
+        });
+        for polyline in polylines {
+            let points = dedup(&polyline.points, polyline.closed);
+            if points.len() < 2 {
+                continue;
+            }
+            match dash.as_ref() {
+                Some((pattern, offset, dash_cap)) => {
+                    let dashes = split_dashes(
+                        &points,
+                        polyline.closed,
+                        pattern,
+                        *offset,
+                        (style.start_cap, style.end_cap, *dash_cap),
+                    );
+                    for dash in dashes {
+                        self.polyline(&dash.points, false, (dash.start_cap, dash.end_cap));
+                    }
+                }
+                None => self.polyline(&points, polyline.closed, (style.start_cap, style.end_cap)),
+            }
+        }
+        self.polygons
+    }
+}
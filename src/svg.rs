@@ -0,0 +1,972 @@
+use crate::flatten::TOLERANCE;
+use crate::stroker::{dash_pattern, native_cap, LineCap};
+use crate::*;
+use std::fmt::Write;
+
+pub fn document(size: impl Into<Size<f32>>, f: impl FnOnce(&DrawCommand)) -> String {
+    DisplayList::record(f).to_svg(size)
+}
+
+impl DisplayList {
+    pub fn to_svg(&self, size: impl Into<Size<f32>>) -> String {
+        let mut writer = Writer::new(size.into());
+        for command in self {
+            writer.command(command);
+        }
+        writer.finish()
+    }
+}
+
+pub(crate) fn path_data(figures: &[PathFigure]) -> String {
+    let mut d = String::new();
+    for figure in figures {
+        if !d.is_empty() {
+            d.push(' ');
+        }
+        write!(d, "M{} {}", figure.begin.x, figure.begin.y).unwrap();
+        for segment in &figure.segments {
+            match segment {
+                PathSegment::Line(p) => write!(d, " L{} {}", p.x, p.y),
+                PathSegment::QuadraticBezier(s) => {
+                    write!(d, " Q{} {} {} {}", s.ctrl.x, s.ctrl.y, s.to.x, s.to.y)
+                }
+                PathSegment::CubicBezier(s) => write!(
+                    d,
+                    " C{} {} {} {} {} {}",
+                    s.c0.x, s.c0.y, s.c1.x, s.c1.y, s.to.x, s.to.y
+                ),
+                PathSegment::Arc(s) => write!(
+                    d,
+                    " A{} {} {} {} {} {} {}",
+                    s.radius.x,
+                    s.radius.y,
+                    s.rotation,
+                    s.large_arc as u32,
+                    s.sweep as u32,
+                    s.to.x,
+                    s.to.y
+                ),
+            }
+            .unwrap();
+        }
+        if figure.end == FigureEnd::Closed {
+            d.push_str(" Z");
+        }
+    }
+    d
+}
+
+struct PathDataParser<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl PathDataParser<'_> {
+    #[inline]
+    fn error<T>(&self, message: impl Into<String>) -> Result<T> {
+        Err(Error::InvalidPathData {
+            position: self.position,
+            message: message.into(),
+        })
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn skip_separator(&mut self) {
+        self.skip_whitespace();
+        if self.peek() == Some(b',') {
+            self.position += 1;
+            self.skip_whitespace();
+        }
+    }
+
+    fn has_number(&mut self) -> bool {
+        self.skip_separator();
+        self.peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, b'+' | b'-' | b'.'))
+    }
+
+    fn number(&mut self) -> Result<f32> {
+        self.skip_separator();
+        let start = self.position;
+        let digits = |parser: &mut Self| {
+            let start = parser.position;
+            while parser.peek().is_some_and(|c| c.is_ascii_digit()) {
+                parser.position += 1;
+            }
+            parser.position > start
+        };
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.position += 1;
+        }
+        let mut valid = digits(self);
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            valid |= digits(self);
+        }
+        if !valid {
+            self.position = start;
+            return self.error("expected a number");
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let mantissa_end = self.position;
+            self.position += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.position += 1;
+            }
+            if !digits(self) {
+                self.position = mantissa_end;
+            }
+        }
+        let text = std::str::from_utf8(&self.data[start..self.position]).unwrap_or_default();
+        match text.parse::<f32>() {
+            Ok(v) if v.is_finite() => Ok(v),
+            _ => {
+                self.position = start;
+                self.error("number out of range")
+            }
+        }
+    }
+
+    fn flag(&mut self) -> Result<bool> {
+        self.skip_separator();
+        match self.peek() {
+            Some(b'0') => {
+                self.position += 1;
+                Ok(false)
+            }
+            Some(b'1') => {
+                self.position += 1;
+                Ok(true)
+            }
+            _ => self.error("expected a flag"),
+        }
+    }
+
+    #[inline]
+    fn point(&mut self) -> Result<Point<f32>> {
+        Ok(Point::new(self.number()?, self.number()?))
+    }
+}
+
+pub(crate) fn parse_path_data(d: &str) -> Result<Vec<PathFigure>> {
+    let mut parser = PathDataParser {
+        data: d.as_bytes(),
+        position: 0,
+    };
+    let mut figures = vec![];
+    let mut figure: Option<PathFigure> = None;
+    let mut current = Point::new(0.0, 0.0);
+    let mut start = current;
+    let mut command = None;
+    let mut last_ctrl: Option<(u8, Point<f32>)> = None;
+    loop {
+        parser.skip_whitespace();
+        let Some(c) = parser.peek() else {
+            break;
+        };
+        let position = parser.position;
+        let cmd = if c.is_ascii_alphabetic() {
+            parser.position += 1;
+            c
+        } else {
+            match command {
+                Some(b'M') if parser.has_number() => b'L',
+                Some(b'm') if parser.has_number() => b'l',
+                Some(cmd) if !matches!(cmd, b'Z' | b'z') && parser.has_number() => cmd,
+                _ => return parser.error(format!("unexpected character '{}'", c as char)),
+            }
+        };
+        if command.is_none() && !matches!(cmd, b'M' | b'm') {
+            parser.position = position;
+            return parser.error("path data must begin with a moveto");
+        }
+        let relative = cmd.is_ascii_lowercase();
+        let offset = |p: Point<f32>, current: Point<f32>| {
+            if relative {
+                Point::new(current.x + p.x, current.y + p.y)
+            } else {
+                p
+            }
+        };
+        let reflect = |kind: u8| match last_ctrl {
+            Some((k, ctrl)) if k == kind => {
+                Point::new(current.x * 2.0 - ctrl.x, current.y * 2.0 - ctrl.y)
+            }
+            _ => current,
+        };
+        let upper = cmd.to_ascii_uppercase();
+        if !matches!(upper, b'M' | b'Z') && figure.is_none() {
+            figure = Some(PathFigure::new(start));
+        }
+        let mut ctrl = None;
+        let segment = match upper {
+            b'M' => {
+                let p = offset(parser.point()?, current);
+                figures.extend(figure.take());
+                figure = Some(PathFigure::new(p));
+                start = p;
+                current = p;
+                None
+            }
+            b'Z' => {
+                if let Some(mut f) = figure.take() {
+                    f.end = FigureEnd::Closed;
+                    figures.push(f);
+                }
+                current = start;
+                None
+            }
+            b'L' => Some(PathSegment::Line(offset(parser.point()?, current))),
+            b'H' => {
+                let x = parser.number()?;
+                let x = if relative { current.x + x } else { x };
+                Some(PathSegment::Line(Point::new(x, current.y)))
+            }
+            b'V' => {
+                let y = parser.number()?;
+                let y = if relative { current.y + y } else { y };
+                Some(PathSegment::Line(Point::new(current.x, y)))
+            }
+            b'C' | b'S' => {
+                let c0 = if upper == b'C' {
+                    offset(parser.point()?, current)
+                } else {
+                    reflect(b'C')
+                };
+                let c1 = offset(parser.point()?, current);
+                let to = offset(parser.point()?, current);
+                ctrl = Some((b'C', c1));
+                Some(PathSegment::CubicBezier(CubicBezierSegment { c0, c1, to }))
+            }
+            b'Q' | b'T' => {
+                let c = if upper == b'Q' {
+                    offset(parser.point()?, current)
+                } else {
+                    reflect(b'Q')
+                };
+                let to = offset(parser.point()?, current);
+                ctrl = Some((b'Q', c));
+                Some(PathSegment::QuadraticBezier(QuadraticBezierSegment {
+                    ctrl: c,
+                    to,
+                }))
+            }
+            b'A' => {
+                let rx = parser.number()?;
+                let ry = parser.number()?;
+                let rotation = parser.number()?;
+                let large_arc = parser.flag()?;
+                let sweep = if parser.flag()? {
+                    SweepDirection::Clockwise
+                } else {
+                    SweepDirection::CounterClockwise
+                };
+                let to = offset(parser.point()?, current);
+                Some(PathSegment::Arc(ArcSegment {
+                    to,
+                    radius: Vector::new(rx, ry),
+                    rotation,
+                    sweep,
+                    large_arc,
+                }))
+            }
+            _ => {
+                parser.position = position;
+                return parser.error(format!("unknown command '{}'", cmd as char));
+            }
+        };
+        if let (Some(segment), Some(f)) = (segment, figure.as_mut()) {
+            current = segment.end_point();
+            f.segments.push(segment);
+        }
+        last_ctrl = ctrl;
+        command = Some(cmd);
+    }
+    figures.extend(figure);
+    Ok(figures)
+}
+
+#[cfg(windows)]
+fn escape(text: &str) -> String {
+    let mut s = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => s.push_str("&amp;"),
+            '<' => s.push_str("&lt;"),
+            '>' => s.push_str("&gt;"),
+            '"' => s.push_str("&quot;"),
+            c => s.push(c),
+        }
+    }
+    s
+}
+
+fn color(color: Rgba<f32>) -> (String, f32) {
+    let c = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    (
+        format!("#{:02x}{:02x}{:02x}", c(color.r), c(color.g), c(color.b)),
+        color.a.clamp(0.0, 1.0),
+    )
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut s = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(TABLE[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+struct Writer {
+    size: Size<f32>,
+    defs: String,
+    body: String,
+    next_id: usize,
+    transform: Transform,
+    clip_depth: usize,
+}
+
+impl Writer {
+    fn new(size: Size<f32>) -> Self {
+        Self {
+            size,
+            defs: String::new(),
+            body: String::new(),
+            next_id: 0,
+            transform: Transform::identity(),
+            clip_depth: 0,
+        }
+    }
+
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}{}", prefix, self.next_id)
+    }
+
+    fn transform_attr(&self) -> String {
+        let t = &self.transform;
+        if *t == Transform::identity() {
+            String::new()
+        } else {
+            format!(
+                r#" transform="matrix({} {} {} {} {} {})""#,
+                t.m11, t.m12, t.m21, t.m22, t.m31, t.m32
+            )
+        }
+    }
+
+    fn gradient_stops(&mut self, stops: &GradientStopCollection) {
+        for stop in stops.stops() {
+            let (c, a) = color(stop.color);
+            write!(
+                self.defs,
+                r#"<stop offset="{}" stop-color="{}""#,
+                stop.position, c
+            )
+            .unwrap();
+            if a < 1.0 {
+                write!(self.defs, r#" stop-opacity="{}""#, a).unwrap();
+            }
+            self.defs.push_str("/>");
+        }
+    }
+
+    fn paint(&mut self, brush: &Brush, attr: &str) -> String {
+        let spread = |mode: GradientMode| match mode {
+            GradientMode::Clamp => "pad",
+            GradientMode::Mirror => "reflect",
+            GradientMode::Wrap => "repeat",
+        };
+        match brush {
+            Brush::SolidColor(brush) => {
+                let (c, a) = color(brush.color());
+                if a < 1.0 {
+                    format!(r#" {attr}="{c}" {attr}-opacity="{a}""#)
+                } else {
+                    format!(r#" {attr}="{c}""#)
+                }
+            }
+            Brush::LinearGradient(brush) => {
+                let id = self.id("gradient");
+                let (start, end) = (brush.start(), brush.end());
+                write!(
+                    self.defs,
+                    r#"<linearGradient id="{}" gradientUnits="userSpaceOnUse" x1="{}" y1="{}" x2="{}" y2="{}" spreadMethod="{}">"#,
+                    id,
+                    start.x,
+                    start.y,
+                    end.x,
+                    end.y,
+                    spread(brush.stops().mode())
+                )
+                .unwrap();
+                self.gradient_stops(brush.stops());
+                self.defs.push_str("</linearGradient>\n");
+                format!(r##" {attr}="url(#{id})""##)
+            }
+            Brush::RadialGradient(brush) => {
+                let id = self.id("gradient");
+                let ellipse = brush.ellipse();
+                let offset = brush.offset();
+                write!(
+                    self.defs,
+                    r#"<radialGradient id="{}" gradientUnits="userSpaceOnUse" cx="0" cy="0" r="1" fx="{}" fy="{}" gradientTransform="matrix({} 0 0 {} {} {})" spreadMethod="{}">"#,
+                    id,
+                    offset.x / ellipse.radius.x,
+                    offset.y / ellipse.radius.y,
+                    ellipse.radius.x,
+                    ellipse.radius.y,
+                    ellipse.center.x,
+                    ellipse.center.y,
+                    spread(brush.stops().mode())
+                )
+                .unwrap();
+                self.gradient_stops(brush.stops());
+                self.defs.push_str("</radialGradient>\n");
+                format!(r##" {attr}="url(#{id})""##)
+            }
+        }
+    }
+
+    fn element(shape: &Shape) -> String {
+        match shape {
+            Shape::Rect(rect) => format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}""#,
+                rect.origin.x, rect.origin.y, rect.size.width, rect.size.height
+            ),
+            Shape::Circle(circle) => format!(
+                r#"<circle cx="{}" cy="{}" r="{}""#,
+                circle.center.x, circle.center.y, circle.radius
+            ),
+            Shape::Ellipse(ellipse) => format!(
+                r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}""#,
+                ellipse.center.x, ellipse.center.y, ellipse.radius.x, ellipse.radius.y
+            ),
+            Shape::RoundedRect(rrect) => format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" ry="{}""#,
+                rrect.rect.origin.x,
+                rrect.rect.origin.y,
+                rrect.rect.size.width,
+                rrect.rect.size.height,
+                rrect.radius.x,
+                rrect.radius.y
+            ),
+            Shape::Line(line) => format!(
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}""#,
+                line.0.x, line.0.y, line.1.x, line.1.y
+            ),
+            Shape::FilledPath(path) => {
+                let fill_rule = match path.path_data().fill_rule() {
+                    FillRule::EvenOdd => "evenodd",
+                    FillRule::NonZero => "nonzero",
+                };
+                format!(
+                    r#"<path d="{}" fill-rule="{}""#,
+                    path_data(path.path_data().figures()),
+                    fill_rule
+                )
+            }
+            Shape::HollowPath(path) => {
+                format!(r#"<path d="{}""#, path_data(path.path_data().figures()))
+            }
+        }
+    }
+
+    fn command(&mut self, command: &DisplayCommand) {
+        match command {
+            DisplayCommand::Clear(c) => {
+                if self.clip_depth == 0 {
+                    self.body.clear();
+                }
+                let (c, a) = color(*c);
+                if a > 0.0 {
+                    write!(
+                        self.body,
+                        r#"<rect x="0" y="0" width="{}" height="{}" fill="{}""#,
+                        self.size.width, self.size.height, c
+                    )
+                    .unwrap();
+                    if a < 1.0 {
+                        write!(self.body, r#" fill-opacity="{}""#, a).unwrap();
+                    }
+                    self.body.push_str("/>\n");
+                }
+            }
+            DisplayCommand::Fill { shape, brush } => {
+                if !shape.is_fillable() {
+                    return;
+                }
+                let paint = self.paint(brush, "fill");
+                let transform = self.transform_attr();
+                writeln!(
+                    self.body,
+                    "{}{}{}/>",
+                    Self::element(shape),
+                    paint,
+                    transform
+                )
+                .unwrap();
+            }
+            DisplayCommand::Stroke {
+                shape,
+                brush,
+                width,
+                style,
+            } => {
+                let props = style
+                    .as_ref()
+                    .map(|style| style.properties())
+                    .unwrap_or_default();
+                let Some(cap) = native_cap(&shape.figures(), &props) else {
+                    let scale = self.transform.max_scale();
+                    if scale > 0.0 {
+                        let outline = shape.widen(*width, &props, TOLERANCE / scale);
+                        self.command(&DisplayCommand::Fill {
+                            shape: Shape::FilledPath(outline),
+                            brush: brush.clone(),
+                        });
+                    }
+                    return;
+                };
+                let cap = match cap {
+                    LineCap::Butt => "butt",
+                    LineCap::Square => "square",
+                    LineCap::Round => "round",
+                };
+                let paint = self.paint(brush, "stroke");
+                let transform = self.transform_attr();
+                let join = match props.line_join {
+                    LineJoin::Miter | LineJoin::MiterOrBevel(_) => "miter",
+                    LineJoin::Bevel => "bevel",
+                    LineJoin::Round => "round",
+                };
+                write!(
+                    self.body,
+                    r#"{} fill="none"{} stroke-width="{}" stroke-linecap="{}" stroke-linejoin="{}""#,
+                    Self::element(shape),
+                    paint,
+                    width,
+                    cap,
+                    join
+                )
+                .unwrap();
+                if join == "miter" {
+                    write!(
+                        self.body,
+                        r#" stroke-miterlimit="{}""#,
+                        props.line_join.miter_limit()
+                    )
+                    .unwrap();
+                }
+                if let Some((pattern, offset)) = dash_pattern(&props) {
+                    let dashes = pattern
+                        .iter()
+                        .map(|d| (d * width).to_string())
+                        .collect::<Vec<_>>();
+                    write!(
+                        self.body,
+                        r#" stroke-dasharray="{}" stroke-dashoffset="{}""#,
+                        dashes.join(" "),
+                        offset * width
+                    )
+                    .unwrap();
+                }
+                writeln!(self.body, "{}/>", transform).unwrap();
+            }
+            #[cfg(windows)]
+            DisplayCommand::DrawText {
+                layout,
+                origin,
+                brush,
+            } => self.text(layout, *origin, brush),
+            DisplayCommand::DrawImage {
+                image,
+                dest_rect,
+                src_rect,
+                interpolation,
+            } => self.image(image, *dest_rect, *src_rect, *interpolation),
+            DisplayCommand::PushClip(rect) => {
+                let id = self.id("clip");
+                let rect = self.transform.transform_rect(rect);
+                writeln!(
+                    self.defs,
+                    r#"<clipPath id="{}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+                    id, rect.origin.x, rect.origin.y, rect.size.width, rect.size.height
+                )
+                .unwrap();
+                writeln!(self.body, r##"<g clip-path="url(#{})">"##, id).unwrap();
+                self.clip_depth += 1;
+            }
+            DisplayCommand::PopClip => {
+                if self.clip_depth > 0 {
+                    self.body.push_str("</g>\n");
+                    self.clip_depth -= 1;
+                }
+            }
+            DisplayCommand::Translate(point) => {
+                self.transform = Transform::translation(point.x, point.y) * self.transform;
+            }
+            DisplayCommand::Scale(size) => {
+                self.transform = Transform::scale(size.width, size.height) * self.transform;
+            }
+            DisplayCommand::Concat(transform) => self.transform = *transform * self.transform,
+            DisplayCommand::SetTransform(transform) => self.transform = *transform,
+            DisplayCommand::ResetTransform => self.transform = Transform::identity(),
+        }
+    }
+
+    #[cfg(windows)]
+    fn text(&mut self, layout: &TextLayout, origin: Point<f32>, brush: &Brush) {
+        let Ok(lines) = layout.line_metrics() else {
+            return;
+        };
+        let format = layout.format();
+        let family = format.font_family_name().unwrap_or_default();
+        let paint = self.paint(brush, "fill");
+        let transform = self.transform_attr();
+        write!(
+            self.body,
+            r#"<text font-family="{}" font-size="{}" xml:space="preserve"{}{}>"#,
+            escape(&family),
+            format.font_size(),
+            paint,
+            transform
+        )
+        .unwrap();
+        let text = layout.text().encode_utf16().collect::<Vec<_>>();
+        let mut position = 0;
+        for line in lines {
+            let len = line.length - line.newline_length;
+            let Ok(p) = layout.text_position_to_point(position, false) else {
+                break;
+            };
+            let end = (position + len).min(text.len());
+            let content = String::from_utf16_lossy(&text[position.min(end)..end]);
+            write!(
+                self.body,
+                r#"<tspan x="{}" y="{}">{}</tspan>"#,
+                origin.x + p.x,
+                origin.y + p.y + line.baseline,
+                escape(&content)
+            )
+            .unwrap();
+            position += line.length;
+        }
+        self.body.push_str("</text>\n");
+    }
+
+    fn image(
+        &mut self,
+        image: &Image,
+        dest_rect: Rect<f32>,
+        src_rect: Option<Rect<f32>>,
+        interpolation: Interpolation,
+    ) {
+        let Ok(png) = image.read_pixels().and_then(|data| data.to_png()) else {
+            return;
+        };
+        let size = image.size();
+        let rendering = match interpolation {
+            Interpolation::NearestNeighbor => r#" image-rendering="optimizeSpeed""#,
+            _ => "",
+        };
+        let href = format!("data:image/png;base64,{}", base64(&png));
+        let transform = self.transform_attr();
+        let d = dest_rect;
+        match src_rect {
+            None => writeln!(
+                self.body,
+                r#"<image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none"{} xlink:href="{}"{}/>"#,
+                d.origin.x, d.origin.y, d.size.width, d.size.height, rendering, href, transform
+            ),
+            Some(s) => writeln!(
+                self.body,
+                r#"<g{}><svg x="{}" y="{}" width="{}" height="{}" viewBox="{} {} {} {}" preserveAspectRatio="none"><image width="{}" height="{}"{} xlink:href="{}"/></svg></g>"#,
+                transform,
+                d.origin.x,
+                d.origin.y,
+                d.size.width,
+                d.size.height,
+                s.origin.x,
+                s.origin.y,
+                s.size.width,
+                s.size.height,
+                size.width,
+                size.height,
+                rendering,
+                href
+            ),
+        }
+        .unwrap();
+    }
+
+    fn finish(mut self) -> String {
+        for _ in 0..self.clip_depth {
+            self.body.push_str("</g>\n");
+        }
+        let mut svg = String::new();
+        writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+            self.size.width, self.size.height
+        )
+        .unwrap();
+        if !self.defs.is_empty() {
+            svg.push_str("<defs>\n");
+            svg.push_str(&self.defs);
+            svg.push_str("</defs>\n");
+        }
+        svg.push_str(&self.body);
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes() {
+        let factory = Context::new(Software::new()).unwrap().create_factory();
+        let brush = factory
+            .create_solid_color_brush((1.0, 0.0, 0.0, 0.5))
+            .unwrap();
+        let path = factory
+            .create_hollow_path()
+            .unwrap()
+            .begin((0.0, 0.0))
+            .quadratic_bezier_to((1.0, 1.0), (2.0, 0.0))
+            .cubic_bezier_to((3.0, 1.0), (4.0, 1.0), (5.0, 0.0))
+            .end(FigureEnd::Closed)
+            .unwrap()
+            .close()
+            .unwrap();
+        let svg = document((10.0, 10.0), |cmd| {
+            cmd.clear((1.0, 1.0, 1.0, 1.0));
+            cmd.translate((1.0, 2.0));
+            cmd.fill(&Rect::new((0.0, 0.0), (4.0, 3.0)), &brush);
+            cmd.stroke(&path, &brush, 2.0, None);
+        });
+        assert!(svg.contains(r##"<rect x="0" y="0" width="10" height="10" fill="#ffffff"/>"##));
+        assert!(svg.contains(
+            r##"<rect x="0" y="0" width="4" height="3" fill="#ff0000" fill-opacity="0.5" transform="matrix(1 0 0 1 1 2)"/>"##
+        ));
+        assert!(svg.contains(r#"d="M0 0 Q1 1 2 0 C3 1 4 1 5 0 Z""#));
+        assert!(svg.contains(r#"stroke-width="2" stroke-linecap="butt" stroke-linejoin="miter" stroke-miterlimit="10""#));
+    }
+
+    #[test]
+    fn gradients_and_clips() {
+        let factory = Context::new(Software::new()).unwrap().create_factory();
+        let stops = factory
+            .create_gradient_stop_collection(
+                GradientMode::Mirror,
+                &[(0.0, (0.0, 0.0, 0.0, 1.0)), (1.0, (1.0, 1.0, 1.0, 1.0))],
+            )
+            .unwrap();
+        let brush = factory
+            .create_linear_gradient_brush((0.0, 0.0), (10.0, 0.0), &stops)
+            .unwrap();
+        let style = factory
+            .create_stroke_style(&StrokeStyleProperties {
+                dash: Some(Dash {
+                    style: DashStyle::Dash,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .unwrap();
+        let svg = document((10.0, 10.0), |cmd| {
+            cmd.clip(Rect::new((1.0, 1.0), (5.0, 5.0)), |cmd| {
+                cmd.stroke(&line((0.0, 0.0), (10.0, 10.0)), &brush, 1.5, Some(&style));
+            });
+        });
+        assert!(svg.contains(r##"spreadMethod="reflect"><stop offset="0" stop-color="#000000"/><stop offset="1" stop-color="#ffffff"/></linearGradient>"##));
+        assert!(svg.contains(
+            r#"<clipPath id="clip1"><rect x="1" y="1" width="5" height="5"/></clipPath>"#
+        ));
+        assert!(svg.contains(r##"<g clip-path="url(#clip1)">"##));
+        assert!(svg.contains(r##"stroke="url(#gradient2)""##));
+        assert!(svg.contains(r#"stroke-dasharray="3 3" stroke-dashoffset="0""#));
+        assert!(svg.trim_end().ends_with("</g>\n</svg>"));
+    }
+
+    #[test]
+    fn parse_path_data() {
+        let data = "M10 10h5v5H10z m1,1 l2-2 c1 1 2 1 3 0 s2-1 3 0 Q20 20 25 25 t5 0 A5 5 30 1 0 40 40 a1 1 0 00 1.5.5"
+            .parse::<PathData>()
+            .unwrap();
+        let figures = data.figures();
+        assert_eq!(figures.len(), 2);
+        assert_eq!(figures[0].begin, Point::new(10.0, 10.0));
+        assert_eq!(
+            figures[0].segments,
+            [(15.0, 10.0), (15.0, 15.0), (10.0, 15.0)].map(|p| PathSegment::Line(p.into()))
+        );
+        assert_eq!(figures[0].end, FigureEnd::Closed);
+        let f = &figures[1];
+        assert_eq!(f.begin, Point::new(11.0, 11.0));
+        assert_eq!(f.end, FigureEnd::Open);
+        assert_eq!(f.segments[0], PathSegment::Line(Point::new(13.0, 9.0)));
+        assert_eq!(
+            f.segments[2],
+            PathSegment::CubicBezier(CubicBezierSegment {
+                c0: Point::new(17.0, 8.0),
+                c1: Point::new(18.0, 8.0),
+                to: Point::new(19.0, 9.0),
+            })
+        );
+        assert_eq!(
+            f.segments[4],
+            PathSegment::QuadraticBezier(QuadraticBezierSegment {
+                ctrl: Point::new(30.0, 30.0),
+                to: Point::new(30.0, 25.0),
+            })
+        );
+        assert_eq!(
+            f.segments[5],
+            PathSegment::Arc(ArcSegment {
+                to: Point::new(40.0, 40.0),
+                radius: Vector::new(5.0, 5.0),
+                rotation: 30.0,
+                sweep: SweepDirection::CounterClockwise,
+                large_arc: true,
+            })
+        );
+        assert_eq!(f.segments[6].end_point(), Point::new(41.5, 40.5));
+        assert_eq!(data.to_string().parse::<PathData>().unwrap(), data);
+        let path = Context::new(Software::new())
+            .unwrap()
+            .create_factory()
+            .create_filled_path()
+            .unwrap()
+            .svg("M0 0 L1 0 L1 1 Z")
+            .unwrap()
+            .close()
+            .unwrap();
+        assert_eq!(path.path_data().to_string(), "M0 0 L1 0 L1 1 Z");
+    }
+
+    #[test]
+    fn parse_path_data_errors() {
+        let position = |d: &str| match d.parse::<PathData>() {
+            Err(Error::InvalidPathData { position, .. }) => position,
+            r => panic!("{:?}", r),
+        };
+        assert_eq!(position("L0 0"), 0);
+        assert_eq!(position("M0 0 L1"), 7);
+        assert_eq!(position("M0 0 X1 1"), 5);
+        assert_eq!(position("M0 0 A1 1 0 2 0 1 1"), 12);
+        assert_eq!(position("M0 0 Z 1"), 7);
+        assert_eq!(position("M0 0 L1 1e40"), 8);
+        assert!("".parse::<PathData>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn caps() {
+        let factory = Context::new(Software::new()).unwrap().create_factory();
+        let brush = factory
+            .create_solid_color_brush((0.0, 0.0, 1.0, 1.0))
+            .unwrap();
+        let stroke = |start_cap, end_cap| {
+            let style = factory
+                .create_stroke_style(&StrokeStyleProperties {
+                    start_cap,
+                    end_cap,
+                    ..Default::default()
+                })
+                .unwrap();
+            document((10.0, 10.0), |cmd| {
+                cmd.stroke(&line((1.0, 5.0), (9.0, 5.0)), &brush, 2.0, Some(&style));
+            })
+        };
+        let svg = stroke(CapStyle::Square, CapStyle::Square);
+        assert!(svg.contains(r#"<line x1="1" y1="5" x2="9" y2="5" fill="none""#));
+        assert!(svg.contains(r#"stroke-linecap="square""#));
+        for (start_cap, end_cap) in [
+            (CapStyle::Flat, CapStyle::Round),
+            (CapStyle::Triangle, CapStyle::Triangle),
+        ] {
+            let svg = stroke(start_cap, end_cap);
+            assert!(!svg.contains("stroke-linecap"));
+            assert!(svg.contains(r##"<path d="M"##));
+            assert!(svg.contains(r##"fill-rule="nonzero" fill="#0000ff"/>"##));
+        }
+
+        let spike = HollowPath::from(
+            PathData::builder()
+                .begin((1.0, 1.0))
+                .line_to((9.0, 5.0))
+                .line_to((1.0, 2.0))
+                .end(FigureEnd::Open)
+                .unwrap()
+                .close()
+                .unwrap(),
+        );
+        let stroke = |line_join| {
+            let style = factory
+                .create_stroke_style(&StrokeStyleProperties {
+                    line_join,
+                    ..Default::default()
+                })
+                .unwrap();
+            document((10.0, 10.0), |cmd| {
+                cmd.stroke(&spike, &brush, 1.0, Some(&style));
+            })
+        };
+        assert!(!stroke(LineJoin::Miter).contains("stroke-linejoin"));
+        assert!(stroke(LineJoin::MiterOrBevel(10.0)).contains(r#"stroke-linejoin="miter""#));
+        let svg = document((10.0, 10.0), |cmd| {
+            cmd.stroke(&Rect::new((1.0, 1.0), (8.0, 8.0)), &brush, 1.0, None);
+        });
+        assert!(svg.contains(r#"stroke-linejoin="miter""#));
+    }
+
+    #[test]
+    fn image() {
+        let image = Image::new(ImageData {
+            width: 2,
+            height: 1,
+            stride: 8,
+            format: PixelFormat::Rgba8,
+            alpha_mode: AlphaMode::Premultiplied,
+            bytes: vec![255, 0, 0, 255, 0, 255, 0, 255],
+        });
+        let svg = document((10.0, 10.0), |cmd| {
+            cmd.draw_image(
+                &image,
+                Rect::new((1.0, 2.0), (4.0, 2.0)),
+                None,
+                Interpolation::NearestNeighbor,
+            );
+        });
+        assert!(svg.contains(
+            r#"<image x="1" y="2" width="4" height="2" preserveAspectRatio="none" image-rendering="optimizeSpeed" xlink:href="data:image/png;base64,iVBORw0KGgo"#
+        ));
+    }
+}
@@ -0,0 +1,375 @@
+use crate::flatten::flatten;
+use crate::*;
+use std::collections::HashMap;
+
+// Inversions of the edge order smaller than this, relative to their x, are rounding noise.
+const EPSILON: f64 = 1e-9;
+
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Mesh {
+    pub vertices: Vec<Point<f32>>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    #[inline]
+    pub fn triangles(&self) -> impl Iterator<Item = [Point<f32>; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|t| {
+            [
+                self.vertices[t[0] as usize],
+                self.vertices[t[1] as usize],
+                self.vertices[t[2] as usize],
+            ]
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Edge {
+    top: (f64, f64),
+    bottom: (f64, f64),
+    winding: i32,
+}
+
+impl Edge {
+    #[inline]
+    fn x_at(&self, y: f64) -> f64 {
+        if y >= self.bottom.1 {
+            return self.bottom.0;
+        }
+        let t = (y - self.top.1) / (self.bottom.1 - self.top.1);
+        self.top.0 + (self.bottom.0 - self.top.0) * t
+    }
+}
+
+struct Builder {
+    mesh: Mesh,
+    indices: HashMap<(u32, u32), u32>,
+}
+
+impl Builder {
+    fn vertex(&mut self, x: f64, y: f64) -> u32 {
+        let p = Point::new(x as f32, y as f32);
+        *self
+            .indices
+            .entry((p.x.to_bits(), p.y.to_bits()))
+            .or_insert_with(|| {
+                self.mesh.vertices.push(p);
+                self.mesh.vertices.len() as u32 - 1
+            })
+    }
+
+    #[inline]
+    fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        if a != b && b != c && c != a {
+            self.mesh.indices.extend([a, b, c]);
+        }
+    }
+
+    fn chain(&mut self, xs: &[f64], y: f64, left: f64, right: f64) -> Vec<u32> {
+        let start = xs.partition_point(|&x| x <= left);
+        let end = xs.partition_point(|&x| x < right).max(start);
+        let mut chain = vec![self.vertex(left, y)];
+        chain.extend(xs[start..end].iter().map(|&x| self.vertex(x, y)));
+        chain.push(self.vertex(right, y));
+        chain.dedup();
+        chain
+    }
+
+    // The top and bottom sides of the trapezoid contain every point where an edge touches them,
+    // so that neighbouring slabs share their vertices and the mesh has no T-junctions.
+    fn trapezoid(&mut self, slab: &Slab, left: &Edge, right: &Edge) {
+        let top = self.chain(&slab.top, slab.y0, left.x_at(slab.y0), right.x_at(slab.y0));
+        let bottom = self.chain(
+            &slab.bottom,
+            slab.y1,
+            left.x_at(slab.y1),
+            right.x_at(slab.y1),
+        );
+        let (mut i, mut j) = (0, 0);
+        while i + 1 < top.len() || j + 1 < bottom.len() {
+            let x = |i: u32| self.mesh.vertices[i as usize].x;
+            let advance_top =
+                j + 1 == bottom.len() || (i + 1 < top.len() && x(top[i + 1]) <= x(bottom[j + 1]));
+            if advance_top {
+                self.triangle(top[i], top[i + 1], bottom[j]);
+                i += 1;
+            } else {
+                self.triangle(top[i], bottom[j + 1], bottom[j]);
+                j += 1;
+            }
+        }
+    }
+}
+
+struct Slab {
+    y0: f64,
+    y1: f64,
+    top: Vec<f64>,
+    bottom: Vec<f64>,
+}
+
+fn sorted_xs(xs: impl Iterator<Item = f64>) -> Vec<f64> {
+    let mut xs = xs.collect::<Vec<_>>();
+    xs.sort_by(|a, b| a.total_cmp(b));
+    xs.dedup();
+    xs
+}
+
+pub(crate) fn tessellate(figures: &[PathFigure], fill_rule: FillRule, tolerance: f32) -> Mesh {
+    let mut edges = vec![];
+    for polyline in flatten(figures, tolerance) {
+        let points = &polyline.points;
+        for i in 0..points.len() {
+            let a = points[i];
+            let b = points[(i + 1) % points.len()];
+            let (a, b) = ((a.x as f64, a.y as f64), (b.x as f64, b.y as f64));
+            if a.1 < b.1 {
+                edges.push(Edge {
+                    top: a,
+                    bottom: b,
+                    winding: 1,
+                });
+            } else if a.1 > b.1 {
+                edges.push(Edge {
+                    top: b,
+                    bottom: a,
+                    winding: -1,
+                });
+            }
+        }
+    }
+    edges.sort_by(|a, b| a.top.1.total_cmp(&b.top.1));
+    let mut ys = edges
+        .iter()
+        .flat_map(|edge| [edge.top.1, edge.bottom.1])
+        .collect::<Vec<_>>();
+    ys.sort_by(|a, b| b.total_cmp(a));
+    ys.dedup();
+    let mut builder = Builder {
+        mesh: Mesh::default(),
+        indices: HashMap::new(),
+    };
+    // The sweep walks down the slabs between consecutive event heights, keeping the edges that
+    // span the current slab sorted by x at its middle. Only neighbours in that order are tested
+    // for crossings: if no neighbours swap at either side of the slab, no edges do, otherwise the
+    // slab is cut at the crossing and the rest is left to the next one.
+    let mut active: Vec<&Edge> = vec![];
+    let mut next = 0;
+    let mut top = vec![];
+    while let Some(y0) = ys.pop() {
+        let Some(&end) = ys.last() else {
+            break;
+        };
+        top.clear();
+        top.extend(active.iter().map(|edge| edge.x_at(y0)));
+        active.retain(|edge| edge.bottom.1 > y0);
+        while next < edges.len() && edges[next].top.1 <= y0 {
+            active.push(&edges[next]);
+            next += 1;
+        }
+        top.extend(active.iter().map(|edge| edge.x_at(y0)));
+        let mut y1 = end;
+        loop {
+            let mid = (y0 + y1) / 2.0;
+            active.sort_by(|a, b| a.x_at(mid).total_cmp(&b.x_at(mid)));
+            let mut split = y1;
+            for pair in active.windows(2) {
+                let gap = pair[1].x_at(mid) - pair[0].x_at(mid);
+                for y in [y0, y1] {
+                    let (l, r) = (pair[0].x_at(y), pair[1].x_at(y));
+                    if r - l < -EPSILON * (1.0 + l.abs().max(r.abs())) {
+                        let y = mid + (y - mid) * (gap / (gap - (r - l)));
+                        if y > y0 && y < split {
+                            split = y;
+                        }
+                    }
+                }
+            }
+            if split >= y1 {
+                break;
+            }
+            y1 = split;
+        }
+        if y1 < end {
+            ys.push(y1);
+        }
+        let mut bottom = active.iter().map(|edge| edge.x_at(y1)).collect::<Vec<_>>();
+        bottom.extend(
+            edges[next..]
+                .iter()
+                .take_while(|edge| edge.top.1 <= y1)
+                .map(|edge| edge.top.0),
+        );
+        let slab = Slab {
+            y0,
+            y1,
+            top: sorted_xs(top.drain(..)),
+            bottom: sorted_xs(bottom.into_iter()),
+        };
+        let mut winding = 0;
+        let mut left = None;
+        for edge in &active {
+            winding += edge.winding;
+            let inside = match fill_rule {
+                FillRule::EvenOdd => winding % 2 != 0,
+                FillRule::NonZero => winding != 0,
+            };
+            match (left, inside) {
+                (None, true) => left = Some(*edge),
+                (Some(l), false) => {
+                    builder.trapezoid(&slab, l, edge);
+                    left = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    builder.mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::polygon_figure;
+    use std::collections::HashSet;
+
+    fn cross(o: Point<f32>, a: Point<f32>, b: Point<f32>) -> f32 {
+        (a.x - o.x) * (b.y - o.y) - (b.x - o.x) * (a.y - o.y)
+    }
+
+    fn assert_no_t_junctions(mesh: &Mesh) {
+        let mut edges = HashSet::new();
+        for t in mesh.indices.chunks_exact(3) {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                edges.insert((a.min(b) as usize, a.max(b) as usize));
+            }
+        }
+        for (a, b) in edges {
+            let (p, q) = (mesh.vertices[a], mesh.vertices[b]);
+            if p.y != q.y {
+                continue;
+            }
+            let (left, right) = (p.x.min(q.x), p.x.max(q.x));
+            assert!(
+                !mesh
+                    .vertices
+                    .iter()
+                    .any(|v| v.y == p.y && v.x > left && v.x < right),
+                "a vertex lies on {:?}-{:?}",
+                p,
+                q
+            );
+        }
+    }
+
+    fn area(mesh: &Mesh) -> f32 {
+        mesh.triangles()
+            .map(|[a, b, c]| cross(a, b, c) as f64 / 2.0)
+            .sum::<f64>() as f32
+    }
+
+    #[test]
+    fn rect() {
+        let mesh = Rect::new((0.0, 0.0), (10.0, 5.0)).tessellate(0.02);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices.len(), 6);
+        assert_eq!(area(&mesh), 50.0);
+        assert!(mesh
+            .triangles()
+            .all(|[a, b, c]| (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y) > 0.0));
+    }
+
+    #[test]
+    fn fill_rule() {
+        let path = |rule| {
+            PathData::builder()
+                .begin((0.0, 0.0))
+                .line_to((30.0, 0.0))
+                .line_to((30.0, 30.0))
+                .line_to((0.0, 30.0))
+                .end(FigureEnd::Closed)
+                .unwrap()
+                .begin((10.0, 10.0))
+                .line_to((20.0, 10.0))
+                .line_to((20.0, 20.0))
+                .line_to((10.0, 20.0))
+                .end(FigureEnd::Closed)
+                .unwrap()
+                .fill_rule(rule)
+                .close()
+                .unwrap()
+        };
+        let even_odd = FilledPath::from(path(FillRule::EvenOdd)).tessellate(0.02);
+        let non_zero = FilledPath::from(path(FillRule::NonZero)).tessellate(0.02);
+        assert_eq!(area(&even_odd), 800.0);
+        assert_eq!(area(&non_zero), 900.0);
+        assert_no_t_junctions(&even_odd);
+        assert_no_t_junctions(&non_zero);
+        for [a, b, c] in even_odd.triangles() {
+            let center = Point::new((a.x + b.x + c.x) / 3.0, (a.y + b.y + c.y) / 3.0);
+            assert!(!Rect::new((10.0, 10.0), (10.0, 10.0)).fill_contains(center, 0.02));
+        }
+    }
+
+    #[test]
+    fn self_intersecting() {
+        let bowtie = PathData::builder()
+            .begin((0.0, 0.0))
+            .line_to((10.0, 10.0))
+            .line_to((10.0, 0.0))
+            .line_to((0.0, 10.0))
+            .end(FigureEnd::Closed)
+            .unwrap()
+            .close()
+            .unwrap();
+        let mesh = FilledPath::from(bowtie).tessellate(0.02);
+        assert!((area(&mesh) - 50.0).abs() < 1e-4);
+        let circle = Circle::new((0.0, 0.0), 10.0).tessellate(0.01);
+        let expected = std::f32::consts::PI * 100.0;
+        assert!((area(&circle) - expected).abs() / expected < 0.01);
+        assert_no_t_junctions(&mesh);
+        assert_no_t_junctions(&circle);
+        assert!(FilledPath::from(PathData::new())
+            .tessellate(0.02)
+            .is_empty());
+    }
+
+    #[test]
+    fn many_segments() {
+        let circle = |cx: f32, cy: f32| {
+            let points = (0..1000)
+                .map(|i| {
+                    let angle = i as f32 / 1000.0 * std::f32::consts::TAU;
+                    Point::new(cx + 40.0 * angle.cos(), cy + 40.0 * angle.sin())
+                })
+                .collect::<Vec<_>>();
+            polygon_figure(&points)
+        };
+        let figures = vec![circle(0.0, 0.0), circle(30.0, 0.0), circle(15.0, 25.0)];
+        let single = FilledPath::from(PathData::from(vec![figures[0].clone()])).tessellate(0.02);
+        let expected = 500.0 * 1600.0 * (std::f32::consts::TAU / 1000.0).sin();
+        assert!((area(&single) - expected).abs() / expected < 1e-4);
+        for rule in [FillRule::EvenOdd, FillRule::NonZero] {
+            let mut data = PathData::from(figures.clone());
+            data.set_fill_rule(rule);
+            let path = FilledPath::from(data);
+            let mesh = path.tessellate(0.02);
+            for x in -10..10 {
+                for y in -10..10 {
+                    let p = Point::new(x as f32 * 6.1 + 15.3, y as f32 * 6.1 + 10.7);
+                    let inside = mesh.triangles().any(|[a, b, c]| {
+                        cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+                    });
+                    assert_eq!(inside, path.fill_contains(p, 0.02), "{:?} {:?}", rule, p);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use crate::*;
+use std::path::{Path, PathBuf};
+
+pub const UPDATE_GOLDEN: &str = "MLTG_UPDATE_GOLDEN";
+
+pub fn render(size: impl Into<Size<u32>>, f: impl FnOnce(&DrawCommand)) -> Result<ImageData> {
+    let ctx = Context::new(Software::new())?;
+    let target = ctx.create_render_target(size)?;
+    ctx.draw(&target, f)?;
+    ctx.read_pixels(&target)
+}
+
+pub fn load_png(path: impl AsRef<Path>) -> Result<ImageData> {
+    let file = std::fs::File::open(path).map_err(|e| Error::Codec(e.to_string()))?;
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| Error::Codec(e.to_string()))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|e| Error::Codec(e.to_string()))?;
+    let pixels = buffer[..info.buffer_size()].chunks_exact(info.line_size);
+    let mut bytes = Vec::with_capacity((info.width * info.height * 4) as usize);
+    for row in pixels {
+        match info.color_type {
+            png::ColorType::Rgba => bytes.extend_from_slice(row),
+            png::ColorType::Rgb => row
+                .chunks_exact(3)
+                .for_each(|p| bytes.extend_from_slice(&[p[0], p[1], p[2], 255])),
+            png::ColorType::GrayscaleAlpha => row
+                .chunks_exact(2)
+                .for_each(|p| bytes.extend_from_slice(&[p[0], p[0], p[0], p[1]])),
+            png::ColorType::Grayscale => row
+                .iter()
+                .for_each(|&p| bytes.extend_from_slice(&[p, p, p, 255])),
+            png::ColorType::Indexed => return Err(Error::Unsupported),
+        }
+    }
+    // PNG stores straight alpha while `ImageData` is premultiplied.
+    for p in bytes.chunks_exact_mut(4) {
+        let a = p[3] as u32;
+        for c in &mut p[..3] {
+            *c = ((*c as u32 * a + 127) / 255) as u8;
+        }
+    }
+    Ok(ImageData {
+        width: info.width,
+        height: info.height,
+        stride: info.width * 4,
+        format: PixelFormat::Rgba8,
+        alpha_mode: AlphaMode::Premultiplied,
+        bytes,
+    })
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Comparison {
+    pub mismatched_pixels: usize,
+    pub max_difference: u8,
+    pub diff: ImageData,
+}
+
+pub fn compare(actual: &ImageData, expected: &ImageData, tolerance: u8) -> Comparison {
+    let width = actual.width.max(expected.width);
+    let height = actual.height.max(expected.height);
+    let actual_rgba = actual.to_straight_rgba();
+    let expected_rgba = expected.to_straight_rgba();
+    let at = |data: &[u8], image: &ImageData, x: u32, y: u32| {
+        (x < image.width && y < image.height).then(|| {
+            let i = ((y * image.width + x) * 4) as usize;
+            [data[i], data[i + 1], data[i + 2], data[i + 3]]
+        })
+    };
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0;
+    let mut bytes = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let a = at(&actual_rgba, actual, x, y);
+            let e = at(&expected_rgba, expected, x, y);
+            let difference = match (a, e) {
+                (Some(a), Some(e)) => a
+                    .iter()
+                    .zip(e.iter())
+                    .map(|(a, e)| a.abs_diff(*e))
+                    .max()
+                    .unwrap_or(0),
+                _ => 255,
+            };
+            max_difference = max_difference.max(difference);
+            if difference > tolerance || a.is_none() || e.is_none() {
+                mismatched_pixels += 1;
+                bytes.extend_from_slice(&[255, 0, 0, 255]);
+            } else {
+                let p = a.unwrap_or_default();
+                let luma = (p[0] as u32 * 3 + p[1] as u32 * 6 + p[2] as u32) / 10;
+                let v = (255 - (255 - luma) * p[3] as u32 / 255 / 4) as u8;
+                bytes.extend_from_slice(&[v, v, v, 255]);
+            }
+        }
+    }
+    Comparison {
+        mismatched_pixels,
+        max_difference,
+        diff: ImageData {
+            width,
+            height,
+            stride: width * 4,
+            format: PixelFormat::Rgba8,
+            alpha_mode: AlphaMode::Premultiplied,
+            bytes,
+        },
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Golden {
+    path: PathBuf,
+    tolerance: u8,
+    max_mismatched_pixels: usize,
+}
+
+impl Golden {
+    #[inline]
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            tolerance: 0,
+            max_mismatched_pixels: 0,
+        }
+    }
+
+    #[inline]
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    #[inline]
+    pub fn max_mismatched_pixels(mut self, count: usize) -> Self {
+        self.max_mismatched_pixels = count;
+        self
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn artifact(&self, kind: &str) -> PathBuf {
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.path.with_file_name(format!("{}.{}.png", stem, kind))
+    }
+
+    pub fn verify(&self, actual: &ImageData) -> std::result::Result<(), String> {
+        let write = |path: &Path, data: &ImageData| {
+            data.to_png()
+                .map_err(|e| e.to_string())
+                .and_then(|png| std::fs::write(path, png).map_err(|e| e.to_string()))
+                .map_err(|e| format!("{}: {}", path.display(), e))
+        };
+        let artifacts = ["actual", "expected", "diff"].map(|kind| self.artifact(kind));
+        if std::env::var_os(UPDATE_GOLDEN).is_some() {
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            return write(&self.path, actual);
+        }
+        let expected = match load_png(&self.path) {
+            Ok(expected) => expected,
+            Err(e) => {
+                write(&artifacts[0], actual)?;
+                return Err(format!(
+                    "cannot load golden image {} ({}); actual image written to {}, set {}=1 to create it",
+                    self.path.display(),
+                    e,
+                    artifacts[0].display(),
+                    UPDATE_GOLDEN
+                ));
+            }
+        };
+        let comparison = compare(actual, &expected, self.tolerance);
+        if comparison.mismatched_pixels <= self.max_mismatched_pixels {
+            for path in &artifacts {
+                let _ = std::fs::remove_file(path);
+            }
+            return Ok(());
+        }
+        write(&artifacts[0], actual)?;
+        write(&artifacts[1], &expected)?;
+        write(&artifacts[2], &comparison.diff)?;
+        Err(format!(
+            "{}: {} pixels differ by more than {} (budget {}, max difference {}, actual {}x{}, expected {}x{}); see {}, {} and {}",
+            self.path.display(),
+            comparison.mismatched_pixels,
+            self.tolerance,
+            self.max_mismatched_pixels,
+            comparison.max_difference,
+            actual.width,
+            actual.height,
+            expected.width,
+            expected.height,
+            artifacts[0].display(),
+            artifacts[1].display(),
+            artifacts[2].display()
+        ))
+    }
+
+    #[track_caller]
+    pub fn assert(&self, actual: &ImageData) {
+        if let Err(e) = self.verify(actual) {
+            panic!("{}", e);
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_render(&self, size: impl Into<Size<u32>>, f: impl FnOnce(&DrawCommand)) {
+        let actual = render(size, f).unwrap();
+        self.assert(&actual);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn golden(name: &str) -> Golden {
+        Golden::new(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/golden")
+                .join(name),
+        )
+    }
+
+    #[test]
+    fn compare_tolerance() {
+        let solid = |c: u8| ImageData {
+            width: 2,
+            height: 2,
+            stride: 8,
+            format: PixelFormat::Rgba8,
+            alpha_mode: AlphaMode::Premultiplied,
+            bytes: [c, 0, 0, 255].repeat(4),
+        };
+        let mut actual = solid(100);
+        actual.bytes[0] = 110;
+        let comparison = compare(&actual, &solid(100), 10);
+        assert_eq!(comparison.mismatched_pixels, 0);
+        assert_eq!(comparison.max_difference, 10);
+        let comparison = compare(&actual, &solid(100), 9);
+        assert_eq!(comparison.mismatched_pixels, 1);
+        assert_eq!(comparison.diff.pixel(0, 0), [255, 0, 0, 255]);
+        let larger = render((3, 2), |_| {}).unwrap();
+        assert_eq!(compare(&larger, &solid(100), 255).mismatched_pixels, 2);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let image = render((8, 8), |cmd| {
+            let factory = Context::new(Software::new()).unwrap().create_factory();
+            let brush = factory
+                .create_solid_color_brush((0.2, 0.4, 0.6, 0.5))
+                .unwrap();
+            cmd.fill(&Circle::new((4.0, 4.0), 3.0), &brush);
+        })
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("mltg-round-trip-{}.png", std::process::id()));
+        std::fs::write(&path, image.to_png().unwrap()).unwrap();
+        let loaded = load_png(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let comparison = compare(&loaded, &image, 0);
+        assert_eq!(comparison.mismatched_pixels, 0);
+    }
+
+    #[test]
+    fn shapes() {
+        let factory = Context::new(Software::new()).unwrap().create_factory();
+        let red = factory
+            .create_solid_color_brush((1.0, 0.0, 0.0, 1.0))
+            .unwrap();
+        let blue = factory
+            .create_solid_color_brush((0.0, 0.0, 1.0, 0.5))
+            .unwrap();
+        let style = factory
+            .create_stroke_style(&StrokeStyleProperties {
+                start_cap: CapStyle::Round,
+                end_cap: CapStyle::Round,
+                line_join: LineJoin::Round,
+                ..Default::default()
+            })
+            .unwrap();
+        golden("shapes.png")
+            .tolerance(2)
+            .assert_render((64, 64), |cmd| {
+                cmd.clear((1.0, 1.0, 1.0, 1.0));
+                cmd.fill(&Rect::new((8.0, 8.0), (24.0, 16.0)), &red);
+                cmd.fill(&Circle::new((40.0, 40.0), 16.0), &blue);
+                cmd.stroke(&line((8.0, 56.0), (56.0, 32.0)), &red, 4.0, Some(&style));
+                cmd.stroke(
+                    &RoundedRect::new(Rect::new((36.0, 6.0), (22.0, 18.0)), (4.0, 4.0)),
+                    &blue,
+                    2.0,
+                    None,
+                );
+            });
+    }
+
+    #[test]
+    fn gradients() {
+        let factory = Context::new(Software::new()).unwrap().create_factory();
+        let stops = factory
+            .create_gradient_stop_collection(
+                GradientMode::Mirror,
+                &[(0.0, (1.0, 0.0, 0.0, 1.0)), (1.0, (0.0, 0.0, 1.0, 1.0))],
+            )
+            .unwrap();
+        let linear = factory
+            .create_linear_gradient_brush((0.0, 0.0), (16.0, 16.0), &stops)
+            .unwrap();
+        let radial = factory
+            .create_radial_gradient_brush(
+                Ellipse::new((48.0, 48.0), (12.0, 12.0)),
+                (4.0, -4.0),
+                &stops,
+            )
+            .unwrap();
+        golden("gradients.png")
+            .tolerance(2)
+            .assert_render((64, 64), |cmd| {
+                cmd.fill(&Rect::new((0.0, 0.0), (64.0, 32.0)), &linear);
+                cmd.clip(Rect::new((32.0, 32.0), (32.0, 32.0)), |cmd| {
+                    cmd.fill(&Rect::new((0.0, 0.0), (64.0, 64.0)), &radial);
+                });
+            });
+    }
+}
@@ -1,44 +1,49 @@
+#[cfg(windows)]
 use crate::*;
-use windows::core::{Interface, HSTRING};
-use windows::Win32::{Foundation::*, Graphics::Direct2D::*, Graphics::DirectWrite::*};
+#[cfg(windows)]
+use std::{cell::RefCell, ffi::c_void, rc::Rc};
+#[cfg(windows)]
+use windows::core::{implement, IUnknown, Interface, HSTRING};
+#[cfg(windows)]
+use windows::Win32::{Foundation::*, Graphics::DirectWrite::*};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 #[repr(i32)]
 pub enum FontWeight {
-    Thin = DWRITE_FONT_WEIGHT_THIN.0,
-    UltraLight = DWRITE_FONT_WEIGHT_ULTRA_LIGHT.0,
-    Light = DWRITE_FONT_WEIGHT_LIGHT.0,
-    SemiLight = DWRITE_FONT_WEIGHT_SEMI_LIGHT.0,
-    Regular = DWRITE_FONT_WEIGHT_REGULAR.0,
-    Medium = DWRITE_FONT_WEIGHT_MEDIUM.0,
-    SemiBold = DWRITE_FONT_WEIGHT_SEMI_BOLD.0,
-    Bold = DWRITE_FONT_WEIGHT_BOLD.0,
-    UltraBold = DWRITE_FONT_WEIGHT_ULTRA_BOLD.0,
-    Heavy = DWRITE_FONT_WEIGHT_HEAVY.0,
-    UltraBlack = DWRITE_FONT_WEIGHT_ULTRA_BLACK.0,
+    Thin = 100,
+    UltraLight = 200,
+    Light = 300,
+    SemiLight = 350,
+    Regular = 400,
+    Medium = 500,
+    SemiBold = 600,
+    Bold = 700,
+    UltraBold = 800,
+    Heavy = 900,
+    UltraBlack = 950,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 #[repr(i32)]
 pub enum FontStyle {
-    Normal = DWRITE_FONT_STYLE_NORMAL.0,
-    Oblique = DWRITE_FONT_STYLE_OBLIQUE.0,
-    Italic = DWRITE_FONT_STYLE_ITALIC.0,
+    Normal = 0,
+    Oblique = 1,
+    Italic = 2,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 #[repr(i32)]
 pub enum FontStretch {
-    Undefined = DWRITE_FONT_STRETCH_UNDEFINED.0,
-    UltraCondensed = DWRITE_FONT_STRETCH_ULTRA_CONDENSED.0,
-    ExtraCondensed = DWRITE_FONT_STRETCH_EXTRA_CONDENSED.0,
-    Condensed = DWRITE_FONT_STRETCH_CONDENSED.0,
-    SemiCondensed = DWRITE_FONT_STRETCH_SEMI_CONDENSED.0,
-    Medium = DWRITE_FONT_STRETCH_MEDIUM.0,
-    SemiExpanded = DWRITE_FONT_STRETCH_SEMI_EXPANDED.0,
-    Expanded = DWRITE_FONT_STRETCH_EXPANDED.0,
-    ExtraExpanded = DWRITE_FONT_STRETCH_EXTRA_EXPANDED.0,
-    UltraExpanded = DWRITE_FONT_STRETCH_ULTRA_EXPANDED.0,
+    Undefined = 0,
+    UltraCondensed = 1,
+    ExtraCondensed = 2,
+    Condensed = 3,
+    SemiCondensed = 4,
+    Medium = 5,
+    SemiExpanded = 6,
+    Expanded = 7,
+    ExtraExpanded = 8,
+    UltraExpanded = 9,
 }
 
 #[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
@@ -77,12 +82,13 @@ impl Default for TextStyle {
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(i32)]
 pub enum TextAlignment {
-    Leading = DWRITE_TEXT_ALIGNMENT_LEADING.0,
-    Center = DWRITE_TEXT_ALIGNMENT_CENTER.0,
-    Trailing = DWRITE_TEXT_ALIGNMENT_TRAILING.0,
-    Justified = DWRITE_TEXT_ALIGNMENT_JUSTIFIED.0,
+    Leading = 0,
+    Center = 2,
+    Trailing = 1,
+    Justified = 3,
 }
 
+#[cfg(windows)]
 impl From<DWRITE_TEXT_ALIGNMENT> for TextAlignment {
     #[inline]
     fn from(src: DWRITE_TEXT_ALIGNMENT) -> Self {
@@ -103,6 +109,7 @@ pub enum LineSpacingMethod {
     Proportional,
 }
 
+#[cfg(windows)]
 impl From<DWRITE_LINE_SPACING_METHOD> for LineSpacingMethod {
     fn from(src: DWRITE_LINE_SPACING_METHOD) -> Self {
         match src {
@@ -128,6 +135,15 @@ pub enum Font<'a, 'b> {
     Memory(&'a [u8], &'b str),
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LineMetrics {
+    pub length: usize,
+    pub trailing_whitespace_length: usize,
+    pub newline_length: usize,
+    pub height: f32,
+    pub baseline: f32,
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct HitTestResult {
     pub text_position: usize,
@@ -135,12 +151,14 @@ pub struct HitTestResult {
     pub trailing_hit: bool,
 }
 
+#[cfg(windows)]
 #[derive(Debug)]
 pub struct TextFormat {
     format: IDWriteTextFormat,
     _not_sync: std::cell::UnsafeCell<()>, // !Sync
 }
 
+#[cfg(windows)]
 impl TextFormat {
     pub(crate) fn new(
         factory: &IDWriteFactory6,
@@ -210,8 +228,222 @@ impl TextFormat {
             baseline
         })
     }
+
+    pub fn font_family_name(&self) -> Result<String> {
+        unsafe {
+            let len = self.format.GetFontFamilyNameLength() as usize;
+            let mut name = vec![0u16; len + 1];
+            self.format.GetFontFamilyName(&mut name)?;
+            Ok(String::from_utf16_lossy(&name[..len]))
+        }
+    }
+
+    #[inline]
+    pub fn font_size(&self) -> f32 {
+        unsafe { self.format.GetFontSize() }
+    }
+}
+
+#[cfg(windows)]
+pub(crate) fn font_file_data(face: &IDWriteFontFace) -> Result<Vec<u8>> {
+    unsafe {
+        let mut count = 0;
+        face.GetFiles(&mut count, None)?;
+        let mut files = vec![None; count as usize];
+        face.GetFiles(&mut count, Some(files.as_mut_ptr()))?;
+        let file = files
+            .into_iter()
+            .next()
+            .flatten()
+            .ok_or(Error::Unsupported)?;
+        let mut key = std::ptr::null_mut();
+        let mut key_size = 0;
+        file.GetReferenceKey(&mut key, &mut key_size)?;
+        let stream = file.GetLoader()?.CreateStreamFromKey(key, key_size)?;
+        let size = stream.GetFileSize()?;
+        let mut fragment = std::ptr::null_mut();
+        let mut context = std::ptr::null_mut();
+        stream.ReadFileFragment(&mut fragment, 0, size, &mut context)?;
+        let data = std::slice::from_raw_parts(fragment as *const u8, size as usize).to_vec();
+        stream.ReleaseFileFragment(context);
+        Ok(data)
+    }
+}
+
+#[cfg(windows)]
+pub(crate) fn font_face_name(face: &IDWriteFontFace) -> Result<String> {
+    unsafe {
+        let face: IDWriteFontFace3 = face.cast()?;
+        let mut strings = None;
+        let mut exists = BOOL::default();
+        face.GetInformationalStrings(
+            DWRITE_INFORMATIONAL_STRING_POSTSCRIPT_NAME,
+            Some(&mut strings),
+            &mut exists,
+        )?;
+        let strings = strings
+            .filter(|_| exists.as_bool())
+            .ok_or(Error::Unsupported)?;
+        let len = strings.GetStringLength(0)? as usize;
+        let mut name = vec![0u16; len + 1];
+        strings.GetString(0, &mut name)?;
+        Ok(String::from_utf16_lossy(&name[..len]))
+    }
+}
+
+// `text` holds the characters of the cluster starting at this glyph, and is empty for the
+// remaining glyphs of a cluster.
+#[cfg(windows)]
+#[derive(Clone, Debug)]
+pub(crate) struct Glyph {
+    pub(crate) index: u16,
+    pub(crate) position: Point<f32>,
+    pub(crate) text: String,
+}
+
+#[cfg(windows)]
+#[derive(Clone, Debug)]
+pub(crate) struct GlyphRun {
+    pub(crate) face: IDWriteFontFace,
+    pub(crate) size: f32,
+    pub(crate) glyphs: Vec<Glyph>,
+}
+
+#[cfg(windows)]
+unsafe fn raw_slice<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    if ptr.is_null() || len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ptr, len)
+    }
+}
+
+#[cfg(windows)]
+#[implement(IDWriteTextRenderer)]
+struct GlyphRunCollector {
+    runs: Rc<RefCell<Vec<GlyphRun>>>,
+}
+
+#[cfg(windows)]
+#[allow(non_snake_case)]
+impl IDWritePixelSnapping_Impl for GlyphRunCollector {
+    fn IsPixelSnappingDisabled(&self, _context: *const c_void) -> windows::core::Result<BOOL> {
+        Ok(true.into())
+    }
+
+    fn GetCurrentTransform(&self, _context: *const c_void) -> windows::core::Result<DWRITE_MATRIX> {
+        Ok(DWRITE_MATRIX {
+            m11: 1.0,
+            m22: 1.0,
+            ..Default::default()
+        })
+    }
+
+    fn GetPixelsPerDip(&self, _context: *const c_void) -> windows::core::Result<f32> {
+        Ok(1.0)
+    }
 }
 
+#[cfg(windows)]
+#[allow(non_snake_case)]
+impl IDWriteTextRenderer_Impl for GlyphRunCollector {
+    fn DrawGlyphRun(
+        &self,
+        _context: *const c_void,
+        x: f32,
+        y: f32,
+        _mode: DWRITE_MEASURING_MODE,
+        run: *const DWRITE_GLYPH_RUN,
+        description: *const DWRITE_GLYPH_RUN_DESCRIPTION,
+        _effect: &Option<IUnknown>,
+    ) -> windows::core::Result<()> {
+        let (run, description) = unsafe { (&*run, &*description) };
+        let Some(face) = run.fontFace.clone() else {
+            return Ok(());
+        };
+        let count = run.glyphCount as usize;
+        let len = description.stringLength as usize;
+        let (indices, advances, offsets, string, clusters) = unsafe {
+            (
+                raw_slice(run.glyphIndices, count),
+                raw_slice(run.glyphAdvances, count),
+                raw_slice(run.glyphOffsets, count),
+                raw_slice(description.string.0, len),
+                raw_slice(description.clusterMap, len),
+            )
+        };
+        let mut texts = vec![vec![]; count];
+        for (&unit, &glyph) in string.iter().zip(clusters) {
+            if let Some(text) = texts.get_mut(glyph as usize) {
+                text.push(unit);
+            }
+        }
+        // Right-to-left runs start at their right edge and advance leftwards.
+        let rtl = run.bidiLevel % 2 == 1;
+        let mut pen = x;
+        let mut glyphs = Vec::with_capacity(count);
+        for (i, &index) in indices.iter().enumerate() {
+            let advance = advances.get(i).copied().unwrap_or(0.0);
+            let offset = offsets.get(i).copied().unwrap_or_default();
+            let x = if rtl {
+                pen -= advance;
+                pen - offset.advanceOffset
+            } else {
+                let x = pen + offset.advanceOffset;
+                pen += advance;
+                x
+            };
+            glyphs.push(Glyph {
+                index,
+                position: Point::new(x, y - offset.ascenderOffset),
+                text: String::from_utf16_lossy(&texts[i]),
+            });
+        }
+        self.runs.borrow_mut().push(GlyphRun {
+            face,
+            size: run.fontEmSize,
+            glyphs,
+        });
+        Ok(())
+    }
+
+    fn DrawUnderline(
+        &self,
+        _context: *const c_void,
+        _x: f32,
+        _y: f32,
+        _underline: *const DWRITE_UNDERLINE,
+        _effect: &Option<IUnknown>,
+    ) -> windows::core::Result<()> {
+        Ok(())
+    }
+
+    fn DrawStrikethrough(
+        &self,
+        _context: *const c_void,
+        _x: f32,
+        _y: f32,
+        _strikethrough: *const DWRITE_STRIKETHROUGH,
+        _effect: &Option<IUnknown>,
+    ) -> windows::core::Result<()> {
+        Ok(())
+    }
+
+    fn DrawInlineObject(
+        &self,
+        _context: *const c_void,
+        _x: f32,
+        _y: f32,
+        _object: &Option<IDWriteInlineObject>,
+        _sideways: BOOL,
+        _rtl: BOOL,
+        _effect: &Option<IUnknown>,
+    ) -> windows::core::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
 impl PartialEq for TextFormat {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
@@ -219,8 +451,10 @@ impl PartialEq for TextFormat {
     }
 }
 
+#[cfg(windows)]
 impl Eq for TextFormat {}
 
+#[cfg(windows)]
 impl Clone for TextFormat {
     #[inline]
     fn clone(&self) -> Self {
@@ -231,15 +465,18 @@ impl Clone for TextFormat {
     }
 }
 
+#[cfg(windows)]
 #[derive(Debug)]
 pub struct TextLayout {
     layout: IDWriteTextLayout,
+    text: String,
     format: TextFormat,
     typography: IDWriteTypography,
     size: Size<f32>,
     _not_sync: std::cell::UnsafeCell<()>, // !Sync
 }
 
+#[cfg(windows)]
 impl TextLayout {
     pub(crate) fn new(
         factory: &IDWriteFactory6,
@@ -250,7 +487,7 @@ impl TextLayout {
     ) -> Result<Self> {
         let layout = unsafe {
             let text = text.encode_utf16().chain(Some(0)).collect::<Vec<_>>();
-            factory.CreateTextLayout(&text, &format.format, std::f32::MAX, std::f32::MAX)?
+            factory.CreateTextLayout(&text, &format.format, f32::MAX, f32::MAX)?
         };
         let typography = unsafe {
             let typography = factory.CreateTypography()?;
@@ -283,6 +520,7 @@ impl TextLayout {
         };
         Ok(Self {
             layout,
+            text: text.to_string(),
             typography,
             format: format.clone(),
             size: max_size,
@@ -290,6 +528,11 @@ impl TextLayout {
         })
     }
 
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
     #[inline]
     pub fn format(&self) -> &TextFormat {
         &self.format
@@ -369,12 +612,48 @@ impl TextLayout {
         Ok(point)
     }
 
+    pub fn line_metrics(&self) -> Result<Vec<LineMetrics>> {
+        unsafe {
+            let mut count = 0;
+            let _ = self.layout.GetLineMetrics(None, &mut count);
+            let mut metrics = vec![DWRITE_LINE_METRICS::default(); count as usize];
+            self.layout.GetLineMetrics(Some(&mut metrics), &mut count)?;
+            Ok(metrics
+                .iter()
+                .map(|m| LineMetrics {
+                    length: m.length as _,
+                    trailing_whitespace_length: m.trailingWhitespaceLength as _,
+                    newline_length: m.newlineLength as _,
+                    height: m.height,
+                    baseline: m.baseline,
+                })
+                .collect())
+        }
+    }
+
+    #[inline]
+    pub(crate) fn handle(&self) -> &IDWriteTextLayout {
+        &self.layout
+    }
+
+    // The shaped runs as DirectWrite draws them, including fallback fonts, with glyph origins
+    // on the baseline.
+    pub(crate) fn glyph_runs(&self, origin: Point<f32>) -> Result<Vec<GlyphRun>> {
+        let runs = Rc::new(RefCell::new(vec![]));
+        let renderer: IDWriteTextRenderer = GlyphRunCollector { runs: runs.clone() }.into();
+        unsafe {
+            self.layout.Draw(None, &renderer, origin.x, origin.y)?;
+        }
+        Ok(runs.take())
+    }
+
     #[inline]
     pub fn position(&self, pt: impl Into<Point<f32>>) -> (&Self, Point<f32>) {
         (self, pt.into())
     }
 }
 
+#[cfg(windows)]
 impl PartialEq for TextLayout {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
@@ -382,13 +661,16 @@ impl PartialEq for TextLayout {
     }
 }
 
+#[cfg(windows)]
 impl Eq for TextLayout {}
 
+#[cfg(windows)]
 impl Clone for TextLayout {
     #[inline]
     fn clone(&self) -> Self {
         Self {
             layout: self.layout.clone(),
+            text: self.text.clone(),
             format: self.format.clone(),
             typography: self.typography.clone(),
             size: self.size,
@@ -397,25 +679,18 @@ impl Clone for TextLayout {
     }
 }
 
+#[cfg(windows)]
 impl<T> Fill for (&TextLayout, T)
 where
     T: Into<Point<f32>> + Clone,
 {
     #[inline]
-    fn fill(&self, dc: &ID2D1DeviceContext5, brush: &ID2D1Brush) {
-        unsafe {
-            let point = self.1.clone().into();
-            dc.DrawTextLayout(
-                Wrapper(point).into(),
-                &self.0.layout,
-                brush,
-                D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT | D2D1_DRAW_TEXT_OPTIONS_CLIP,
-            );
-        }
+    fn fill(&self, renderer: &dyn Renderer, brush: &Brush) {
+        renderer.draw_text(self.0, self.1.clone().into(), brush);
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, windows))]
 mod tests {
     use super::*;
 
@@ -513,4 +788,43 @@ mod tests {
                 }
         );
     }
+
+    #[test]
+    fn glyph_runs() {
+        let factory =
+            unsafe { DWriteCreateFactory::<IDWriteFactory6>(DWRITE_FACTORY_TYPE_SHARED).unwrap() };
+        let loader = unsafe {
+            let loader = factory.CreateInMemoryFontFileLoader().unwrap();
+            factory.RegisterFontFileLoader(&loader).unwrap();
+            loader
+        };
+        let format = TextFormat::new(
+            &factory,
+            &loader,
+            Font::File(
+                std::path::Path::new(
+                    "./resources/Inconsolata/Inconsolata-VariableFont_wdth,wght.ttf",
+                ),
+                "Inconsolata",
+            ),
+            14.0,
+            None,
+            "",
+        )
+        .unwrap();
+        let layout =
+            TextLayout::new(&factory, "abcd", &format, TextAlignment::Leading, None).unwrap();
+        let runs = layout.glyph_runs(Point::new(10.0, 20.0)).unwrap();
+        let glyphs = runs.iter().flat_map(|run| &run.glyphs).collect::<Vec<_>>();
+        assert_eq!(glyphs.len(), 4);
+        assert!(runs.iter().all(|run| run.size == 14.0));
+        assert_eq!(
+            glyphs.iter().map(|g| g.text.as_str()).collect::<String>(),
+            "abcd"
+        );
+        assert!(glyphs.iter().all(|g| g.index != 0));
+        assert!(glyphs[0].position.x >= 10.0);
+        assert!(glyphs.windows(2).all(|g| g[0].position.x < g[1].position.x));
+        assert!(glyphs.iter().all(|g| g.position.y > 20.0));
+    }
 }
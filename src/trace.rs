@@ -0,0 +1,642 @@
+use crate::*;
+use std::sync::Mutex;
+
+const EPSILON: f32 = 1e-4;
+
+#[inline]
+fn approx_eq(a: f32, b: f32) -> bool {
+    (a - b).abs() <= EPSILON
+}
+
+#[inline]
+fn rect_approx_eq(a: &Rect<f32>, b: &Rect<f32>) -> bool {
+    approx_eq(a.origin.x, b.origin.x)
+        && approx_eq(a.origin.y, b.origin.y)
+        && approx_eq(a.size.width, b.size.width)
+        && approx_eq(a.size.height, b.size.height)
+}
+
+#[inline]
+fn color_approx_eq(a: Rgba<f32>, b: Rgba<f32>) -> bool {
+    approx_eq(a.r, b.r) && approx_eq(a.g, b.g) && approx_eq(a.b, b.b) && approx_eq(a.a, b.a)
+}
+
+fn intersect(a: &Rect<f32>, b: &Rect<f32>) -> Rect<f32> {
+    let (ae, be) = (a.endpoint(), b.endpoint());
+    let left = a.origin.x.max(b.origin.x);
+    let top = a.origin.y.max(b.origin.y);
+    let right = ae.x.min(be.x).max(left);
+    let bottom = ae.y.min(be.y).max(top);
+    Rect::new((left, top), (right - left, bottom - top))
+}
+
+// Every renderer call is an entry, state changes included. `transform`, `clip` and
+// `clip_depth` are the state after the command has been applied, with `clip` in device space.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TraceEntry {
+    pub command: DisplayCommand,
+    pub clip: Option<Rect<f32>>,
+    pub clip_depth: usize,
+    transform: Transform,
+}
+
+impl TraceEntry {
+    #[inline]
+    pub fn map_point(&self, point: impl Into<Point<f32>>) -> Point<f32> {
+        self.transform.transform_point(point.into())
+    }
+
+    #[inline]
+    pub fn map_rect(&self, rect: &Rect<f32>) -> Rect<f32> {
+        self.transform.transform_rect(rect)
+    }
+
+    #[inline]
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    #[inline]
+    pub fn shape(&self) -> Option<&Shape> {
+        match &self.command {
+            DisplayCommand::Fill { shape, .. } | DisplayCommand::Stroke { shape, .. } => {
+                Some(shape)
+            }
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn brush(&self) -> Option<&Brush> {
+        match &self.command {
+            DisplayCommand::Fill { brush, .. } | DisplayCommand::Stroke { brush, .. } => {
+                Some(brush)
+            }
+            #[cfg(windows)]
+            DisplayCommand::DrawText { brush, .. } => Some(brush),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn color(&self) -> Option<Rgba<f32>> {
+        match (&self.command, self.brush()) {
+            (DisplayCommand::Clear(color), _) => Some(*color),
+            (_, Some(Brush::SolidColor(brush))) => Some(brush.color()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_fill(&self) -> bool {
+        matches!(self.command, DisplayCommand::Fill { .. })
+    }
+
+    #[inline]
+    pub fn is_stroke(&self) -> bool {
+        matches!(self.command, DisplayCommand::Stroke { .. })
+    }
+
+    #[inline]
+    pub fn is_state_change(&self) -> bool {
+        matches!(
+            self.command,
+            DisplayCommand::PushClip(_)
+                | DisplayCommand::PopClip
+                | DisplayCommand::Translate(_)
+                | DisplayCommand::Scale(_)
+                | DisplayCommand::Concat(_)
+                | DisplayCommand::SetTransform(_)
+                | DisplayCommand::ResetTransform
+        )
+    }
+
+    pub fn device_rect(&self) -> Option<Rect<f32>> {
+        match self.shape()? {
+            Shape::Rect(rect) => Some(self.map_rect(rect)),
+            _ => None,
+        }
+    }
+
+    pub fn is_inside(&self, clip: &Rect<f32>) -> bool {
+        self.clip.is_some_and(|c| rect_approx_eq(&c, clip))
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct TraceLog {
+    entries: Vec<TraceEntry>,
+    clip_depth: usize,
+    unmatched_pops: Vec<usize>,
+}
+
+impl TraceLog {
+    #[inline]
+    pub fn record(f: impl FnOnce(&DrawCommand)) -> Self {
+        Self::from(&DisplayList::record(f))
+    }
+
+    #[inline]
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[inline]
+    pub fn fills(&self) -> impl Iterator<Item = &TraceEntry> {
+        self.entries.iter().filter(|e| e.is_fill())
+    }
+
+    #[inline]
+    pub fn strokes(&self) -> impl Iterator<Item = &TraceEntry> {
+        self.entries.iter().filter(|e| e.is_stroke())
+    }
+
+    #[inline]
+    pub fn find(&self, f: impl Fn(&TraceEntry) -> bool) -> Option<&TraceEntry> {
+        self.entries.iter().find(|e| f(e))
+    }
+
+    // Clips still pushed at the end of the log.
+    #[inline]
+    pub fn unbalanced_clips(&self) -> usize {
+        self.clip_depth
+    }
+
+    // Indices of `PopClip` entries that had no clip to pop.
+    #[inline]
+    pub fn unmatched_pops(&self) -> &[usize] {
+        &self.unmatched_pops
+    }
+
+    #[track_caller]
+    pub fn assert_any(&self, description: &str, f: impl Fn(&TraceEntry) -> bool) -> &TraceEntry {
+        match self.find(f) {
+            Some(entry) => entry,
+            None => panic!(
+                "no call matched: {}\ncalls: {:#?}",
+                description, self.entries
+            ),
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_none(&self, description: &str, f: impl Fn(&TraceEntry) -> bool) {
+        if let Some(entry) = self.find(f) {
+            panic!(
+                "unexpected call matched: {}\ncall: {:#?}",
+                description, entry
+            );
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_cleared(&self, color: impl Into<Rgba<f32>>) -> &TraceEntry {
+        let color = color.into();
+        self.assert_any(
+            &format!("clear with {:?}", color),
+            |e| matches!(e.command, DisplayCommand::Clear(c) if color_approx_eq(c, color)),
+        )
+    }
+
+    #[track_caller]
+    pub fn assert_clipped(&self, clip: impl Into<Rect<f32>>) -> &TraceEntry {
+        let clip = clip.into();
+        self.assert_any(&format!("push of device clip {:?}", clip), |e| {
+            matches!(e.command, DisplayCommand::PushClip(_)) && e.is_inside(&clip)
+        })
+    }
+
+    #[track_caller]
+    pub fn assert_filled_rect(
+        &self,
+        rect: impl Into<Rect<f32>>,
+        color: impl Into<Rgba<f32>>,
+    ) -> &TraceEntry {
+        let rect = rect.into();
+        let color = color.into();
+        self.assert_any(
+            &format!("fill of device rect {:?} with {:?}", rect, color),
+            |e| {
+                e.is_fill()
+                    && e.device_rect().is_some_and(|r| rect_approx_eq(&r, &rect))
+                    && e.color().is_some_and(|c| color_approx_eq(c, color))
+            },
+        )
+    }
+
+    #[track_caller]
+    pub fn assert_filled_rect_in_clip(
+        &self,
+        rect: impl Into<Rect<f32>>,
+        color: impl Into<Rgba<f32>>,
+        clip: impl Into<Rect<f32>>,
+    ) -> &TraceEntry {
+        let rect = rect.into();
+        let color = color.into();
+        let clip = clip.into();
+        self.assert_any(
+            &format!(
+                "fill of device rect {:?} with {:?} inside clip {:?}",
+                rect, color, clip
+            ),
+            |e| {
+                e.is_fill()
+                    && e.device_rect().is_some_and(|r| rect_approx_eq(&r, &rect))
+                    && e.color().is_some_and(|c| color_approx_eq(c, color))
+                    && e.is_inside(&clip)
+            },
+        )
+    }
+
+    #[track_caller]
+    pub fn assert_filled(&self, shape: &Shape, color: impl Into<Rgba<f32>>) -> &TraceEntry {
+        let color = color.into();
+        self.assert_any(&format!("fill of {:?} with {:?}", shape, color), |e| {
+            e.is_fill()
+                && e.shape() == Some(shape)
+                && e.color().is_some_and(|c| color_approx_eq(c, color))
+        })
+    }
+
+    #[track_caller]
+    pub fn assert_stroked(
+        &self,
+        shape: &Shape,
+        color: impl Into<Rgba<f32>>,
+        width: f32,
+    ) -> &TraceEntry {
+        let color = color.into();
+        self.assert_any(
+            &format!("stroke of {:?} with {:?} and width {}", shape, color, width),
+            |e| {
+                matches!(&e.command, DisplayCommand::Stroke { shape: s, width: w, .. } if s == shape && approx_eq(*w, width))
+                    && e.color().is_some_and(|c| color_approx_eq(c, color))
+            },
+        )
+    }
+
+    #[track_caller]
+    pub fn assert_balanced(&self) {
+        assert_eq!(
+            self.clip_depth, 0,
+            "{} clips were not popped",
+            self.clip_depth
+        );
+        assert!(
+            self.unmatched_pops.is_empty(),
+            "clips were popped without a push at entries {:?}",
+            self.unmatched_pops
+        );
+    }
+}
+
+impl From<&DisplayList> for TraceLog {
+    fn from(list: &DisplayList) -> Self {
+        let mut log = TraceLog::default();
+        let mut transform = Transform::identity();
+        let mut clips: Vec<Rect<f32>> = vec![];
+        for command in list {
+            match command {
+                DisplayCommand::PushClip(rect) => {
+                    let rect = transform.transform_rect(rect);
+                    let clip = match clips.last() {
+                        Some(last) => intersect(last, &rect),
+                        None => rect,
+                    };
+                    clips.push(clip);
+                }
+                DisplayCommand::PopClip if clips.pop().is_none() => {
+                    log.unmatched_pops.push(log.entries.len());
+                }
+                DisplayCommand::Translate(point) => {
+                    transform = Transform::translation(point.x, point.y) * transform;
+                }
+                DisplayCommand::Scale(size) => {
+                    transform = Transform::scale(size.width, size.height) * transform;
+                }
+                DisplayCommand::Concat(t) => transform = *t * transform,
+                DisplayCommand::SetTransform(t) => transform = *t,
+                DisplayCommand::ResetTransform => transform = Transform::identity(),
+                _ => {}
+            }
+            log.entries.push(TraceEntry {
+                command: command.clone(),
+                clip: clips.last().copied(),
+                clip_depth: clips.len(),
+                transform,
+            });
+        }
+        log.clip_depth = clips.len();
+        log
+    }
+}
+
+impl<'a> IntoIterator for &'a TraceLog {
+    type Item = &'a TraceEntry;
+    type IntoIter = std::slice::Iter<'a, TraceEntry>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+#[derive(Debug)]
+pub struct RenderTarget {
+    size: Size<u32>,
+    logs: Mutex<Vec<TraceLog>>,
+}
+
+impl RenderTarget {
+    #[inline]
+    pub fn logs(&self) -> Vec<TraceLog> {
+        self.logs.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    #[inline]
+    pub fn last_log(&self) -> Option<TraceLog> {
+        self.logs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .last()
+            .cloned()
+    }
+
+    #[inline]
+    pub fn take_logs(&self) -> Vec<TraceLog> {
+        std::mem::take(&mut *self.logs.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl Target for RenderTarget {
+    #[inline]
+    fn size(&self) -> Size<f32> {
+        Size::new(self.size.width as f32, self.size.height as f32)
+    }
+
+    #[inline]
+    fn physical_size(&self) -> Size<u32> {
+        self.size
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Trace;
+
+impl Trace {
+    #[inline]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Context<Trace> {
+    #[inline]
+    pub fn create_render_target(&self, size: impl Into<Size<u32>>) -> Result<RenderTarget> {
+        Ok(RenderTarget {
+            size: size.into(),
+            logs: Mutex::new(vec![]),
+        })
+    }
+}
+
+impl Backend for Trace {
+    type RenderTarget = RenderTarget;
+
+    #[inline]
+    fn create_factory(&self) -> Factory {
+        Factory {
+            #[cfg(windows)]
+            device: None,
+        }
+    }
+
+    #[inline]
+    fn set_dpi(&self, _dpi: f32) {}
+
+    #[inline]
+    fn create_offscreen_target(
+        &self,
+        size: Size<u32>,
+        _format: PixelFormat,
+        _alpha_mode: AlphaMode,
+    ) -> Result<Self::RenderTarget> {
+        Ok(RenderTarget {
+            size,
+            logs: Mutex::new(vec![]),
+        })
+    }
+
+    fn draw<R>(
+        &self,
+        target: &Self::RenderTarget,
+        f: impl FnOnce(&dyn Renderer) -> R,
+    ) -> Result<R> {
+        let recording = display_list::Recording::default();
+        let ret = f(&recording);
+        let log = TraceLog::from(&recording.finish());
+        target
+            .logs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(log);
+        Ok(ret)
+    }
+
+    #[inline]
+    fn read_pixels(&self, _target: &Self::RenderTarget) -> Result<ImageData> {
+        Err(Error::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace() {
+        let ctx = Context::new(Trace::new()).unwrap();
+        let factory = ctx.create_factory();
+        let red = factory
+            .create_solid_color_brush((1.0, 0.0, 0.0, 1.0))
+            .unwrap();
+        let target = ctx.create_render_target((100, 100)).unwrap();
+        ctx.draw(&target, |cmd| {
+            cmd.clear((0.0, 0.0, 0.0, 1.0));
+            cmd.translate((10.0, 20.0));
+            cmd.clip(Rect::new((0.0, 0.0), (50.0, 50.0)), |cmd| {
+                cmd.scale((2.0, 2.0));
+                cmd.clip(Rect::new((-10.0, 5.0), (10.0, 40.0)), |cmd| {
+                    cmd.fill(&Rect::new((1.0, 2.0), (3.0, 4.0)), &red);
+                });
+            });
+            cmd.stroke(&line((0.0, 0.0), (1.0, 1.0)), &red, 2.0, None);
+        })
+        .unwrap();
+        let log = target.last_log().unwrap();
+        assert_eq!(log.len(), 9);
+        assert_eq!(
+            log.entries().iter().filter(|e| e.is_state_change()).count(),
+            6
+        );
+        log.assert_balanced();
+        log.assert_clipped(Rect::new((10.0, 20.0), (50.0, 50.0)));
+        log.assert_clipped(Rect::new((10.0, 30.0), (0.0, 40.0)));
+        log.assert_any("translate", |e| {
+            e.command == DisplayCommand::Translate(Point::new(10.0, 20.0))
+        });
+        log.assert_cleared((0.0, 0.0, 0.0, 1.0));
+        let fill = log.assert_filled_rect_in_clip(
+            Rect::new((12.0, 24.0), (6.0, 8.0)),
+            (1.0, 0.0, 0.0, 1.0),
+            Rect::new((10.0, 30.0), (0.0, 40.0)),
+        );
+        assert_eq!(fill.clip_depth, 2);
+        assert_eq!(fill.map_point((0.0, 0.0)), Point::new(10.0, 20.0));
+        let stroke = log.assert_stroked(
+            &Shape::Line(line((0.0, 0.0), (1.0, 1.0))),
+            (1.0, 0.0, 0.0, 1.0),
+            2.0,
+        );
+        assert_eq!(stroke.clip, None);
+        log.assert_none("fill in blue", |e| {
+            e.color() == Some(Rgba::new(0.0, 0.0, 1.0, 1.0))
+        });
+    }
+
+    #[test]
+    fn shapes() {
+        let path = PathData::builder()
+            .begin((0.0, 0.0))
+            .line_to((4.0, 0.0))
+            .line_to((4.0, 4.0))
+            .end(FigureEnd::Closed)
+            .unwrap()
+            .close()
+            .unwrap();
+        let filled = FilledPath::from(path.clone());
+        let hollow = HollowPath::from(path);
+        let rect = Rect::new((1.0, 1.0), (2.0, 2.0));
+        let circle = Circle::new((4.0, 4.0), 2.0);
+        let ellipse = Ellipse::new((4.0, 4.0), (3.0, 2.0));
+        let rrect = RoundedRect::new(Rect::new((1.0, 1.0), (4.0, 4.0)), (1.0, 1.0));
+        let line = line((0.0, 0.0), (4.0, 4.0));
+        let log = TraceLog::record(|cmd| {
+            let brush = Brush::solid_color((1.0, 0.0, 0.0, 1.0));
+            cmd.fill(&rect, &brush);
+            cmd.fill(&circle, &brush);
+            cmd.fill(&ellipse, &brush);
+            cmd.fill(&rrect, &brush);
+            cmd.fill(&filled, &brush);
+            cmd.stroke(&rect, &brush, 1.0, None);
+            cmd.stroke(&circle, &brush, 1.0, None);
+            cmd.stroke(&ellipse, &brush, 1.0, None);
+            cmd.stroke(&rrect, &brush, 1.0, None);
+            cmd.stroke(&line, &brush, 1.0, None);
+            cmd.stroke(&filled, &brush, 1.0, None);
+            cmd.stroke(&hollow, &brush, 1.0, None);
+        });
+        let fills = [
+            Shape::Rect(rect),
+            Shape::Circle(circle),
+            Shape::Ellipse(ellipse),
+            Shape::RoundedRect(rrect),
+            Shape::FilledPath(filled.clone()),
+        ];
+        let strokes = [
+            Shape::Rect(rect),
+            Shape::Circle(circle),
+            Shape::Ellipse(ellipse),
+            Shape::RoundedRect(rrect),
+            Shape::Line(line),
+            Shape::FilledPath(filled),
+            Shape::HollowPath(hollow),
+        ];
+        assert_eq!(log.fills().count(), fills.len());
+        assert_eq!(log.strokes().count(), strokes.len());
+        for shape in &fills {
+            log.assert_filled(shape, (1.0, 0.0, 0.0, 1.0));
+        }
+        for shape in &strokes {
+            log.assert_stroked(shape, (1.0, 0.0, 0.0, 1.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn transform() {
+        let log = TraceLog::record(|cmd| {
+            let brush = Brush::solid_color((1.0, 0.0, 0.0, 1.0));
+            cmd.translate((10.0, 0.0));
+            let saved = cmd.transform();
+            cmd.rotate(90.0, (0.0, 0.0));
+            cmd.fill(&Rect::new((0.0, 0.0), (4.0, 2.0)), &brush);
+            cmd.set_transform(&saved);
+            cmd.skew(45.0, 0.0, (0.0, 0.0));
+            cmd.fill(&Rect::new((0.0, 0.0), (4.0, 2.0)), &brush);
+            cmd.concat(&Transform::scale(2.0, 2.0));
+            cmd.fill(&Rect::new((0.0, 0.0), (4.0, 2.0)), &brush);
+            assert_eq!(
+                cmd.transform(),
+                Transform::scale(2.0, 2.0)
+                    * Transform::skew(45.0, 0.0, (0.0, 0.0))
+                    * Transform::translation(10.0, 0.0)
+            );
+        });
+        assert_eq!(log.len(), 8);
+        assert!(matches!(
+            log.entries()[3].command,
+            DisplayCommand::SetTransform(_)
+        ));
+        let entries = log.fills().collect::<Vec<_>>();
+        let rotated = entries[0].map_rect(&Rect::new((0.0, 0.0), (4.0, 2.0)));
+        assert!((rotated.origin.x - 8.0).abs() < 1e-4, "{:?}", rotated);
+        assert!((rotated.endpoint().y - 4.0).abs() < 1e-4, "{:?}", rotated);
+        let skewed = entries[1].map_point((0.0, 2.0));
+        assert!((skewed.x - 12.0).abs() < 1e-4, "{:?}", skewed);
+        assert_eq!(
+            entries[2].transform(),
+            Transform::scale(2.0, 2.0) * entries[1].transform()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "no call matched")]
+    fn missing_call() {
+        let log = TraceLog::record(|cmd| {
+            cmd.translate((1.0, 1.0));
+        });
+        log.assert_filled_rect(Rect::new((0.0, 0.0), (1.0, 1.0)), (1.0, 1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn unbalanced_clips() {
+        let log = TraceLog::record(|cmd| {
+            cmd.pop_clip();
+            cmd.push_clip(Rect::new((0.0, 0.0), (1.0, 1.0)));
+            cmd.push_clip(Rect::new((0.0, 0.0), (1.0, 1.0)));
+            cmd.pop_clip();
+        });
+        assert_eq!(log.unmatched_pops(), &[0]);
+        assert_eq!(log.unbalanced_clips(), 1);
+        let balanced = TraceLog::record(|cmd| {
+            cmd.clip(Rect::new((0.0, 0.0), (1.0, 1.0)), |_| {});
+        });
+        balanced.assert_balanced();
+    }
+
+    #[test]
+    #[should_panic(expected = "popped without a push")]
+    fn unmatched_pop() {
+        TraceLog::record(|cmd| cmd.pop_clip()).assert_balanced();
+    }
+}
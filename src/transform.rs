@@ -0,0 +1,207 @@
+use crate::*;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Transform {
+    pub m11: f32,
+    pub m12: f32,
+    pub m21: f32,
+    pub m22: f32,
+    pub m31: f32,
+    pub m32: f32,
+}
+
+impl Transform {
+    #[inline]
+    pub fn identity() -> Self {
+        Self {
+            m11: 1.0,
+            m12: 0.0,
+            m21: 0.0,
+            m22: 1.0,
+            m31: 0.0,
+            m32: 0.0,
+        }
+    }
+
+    #[inline]
+    pub fn translation(x: f32, y: f32) -> Self {
+        Self {
+            m31: x,
+            m32: y,
+            ..Self::identity()
+        }
+    }
+
+    #[inline]
+    pub fn scale(x: f32, y: f32) -> Self {
+        Self {
+            m11: x,
+            m22: y,
+            ..Self::identity()
+        }
+    }
+
+    pub fn rotation(angle: f32, center: impl Into<Point<f32>>) -> Self {
+        let center = center.into();
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Self {
+            m11: cos,
+            m12: sin,
+            m21: -sin,
+            m22: cos,
+            m31: center.x - center.x * cos + center.y * sin,
+            m32: center.y - center.x * sin - center.y * cos,
+        }
+    }
+
+    pub fn skew(angle_x: f32, angle_y: f32, center: impl Into<Point<f32>>) -> Self {
+        let center = center.into();
+        let (tan_x, tan_y) = (angle_x.to_radians().tan(), angle_y.to_radians().tan());
+        Self {
+            m11: 1.0,
+            m12: tan_y,
+            m21: tan_x,
+            m22: 1.0,
+            m31: -center.y * tan_x,
+            m32: -center.x * tan_y,
+        }
+    }
+
+    #[inline]
+    pub fn then(&self, other: &Transform) -> Self {
+        *self * *other
+    }
+
+    #[inline]
+    pub fn is_identity(&self) -> bool {
+        *self == Self::identity()
+    }
+
+    #[inline]
+    pub fn determinant(&self) -> f32 {
+        self.m11 * self.m22 - self.m12 * self.m21
+    }
+
+    pub fn invert(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let inv = det.recip();
+        Some(Self {
+            m11: self.m22 * inv,
+            m12: -self.m12 * inv,
+            m21: -self.m21 * inv,
+            m22: self.m11 * inv,
+            m31: (self.m21 * self.m32 - self.m22 * self.m31) * inv,
+            m32: (self.m12 * self.m31 - self.m11 * self.m32) * inv,
+        })
+    }
+
+    #[inline]
+    pub fn transform_point(&self, point: Point<f32>) -> Point<f32> {
+        Point::new(
+            point.x * self.m11 + point.y * self.m21 + self.m31,
+            point.x * self.m12 + point.y * self.m22 + self.m32,
+        )
+    }
+
+    pub fn transform_rect(&self, rect: &Rect<f32>) -> Rect<f32> {
+        let ep = rect.endpoint();
+        let points = [
+            rect.origin,
+            Point::new(ep.x, rect.origin.y),
+            ep,
+            Point::new(rect.origin.x, ep.y),
+        ]
+        .map(|p| self.transform_point(p));
+        let (mut min, mut max) = (points[0], points[0]);
+        for p in &points[1..] {
+            min = Point::new(min.x.min(p.x), min.y.min(p.y));
+            max = Point::new(max.x.max(p.x), max.y.max(p.y));
+        }
+        Rect::new(min, Size::new(max.x - min.x, max.y - min.y))
+    }
+
+    #[inline]
+    pub(crate) fn max_scale(&self) -> f32 {
+        let a = self.m11 * self.m11 + self.m12 * self.m12;
+        let b = self.m21 * self.m21 + self.m22 * self.m22;
+        let c = self.m11 * self.m21 + self.m12 * self.m22;
+        let mean = (a + b) * 0.5;
+        let d = ((a - b) * 0.5).hypot(c);
+        (mean + d).sqrt()
+    }
+}
+
+impl Default for Transform {
+    #[inline]
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+// `a * b` applies `a` first and then `b`, as `Matrix3x2` does.
+impl std::ops::Mul for Transform {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            m11: self.m11 * rhs.m11 + self.m12 * rhs.m21,
+            m12: self.m11 * rhs.m12 + self.m12 * rhs.m22,
+            m21: self.m21 * rhs.m11 + self.m22 * rhs.m21,
+            m22: self.m21 * rhs.m12 + self.m22 * rhs.m22,
+            m31: self.m31 * rhs.m11 + self.m32 * rhs.m21 + rhs.m31,
+            m32: self.m31 * rhs.m12 + self.m32 * rhs.m22 + rhs.m32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_point(actual: Point<f32>, expected: (f32, f32)) {
+        assert!(
+            (actual.x - expected.0).abs() < 1e-4 && (actual.y - expected.1).abs() < 1e-4,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn rotation() {
+        let t = Transform::rotation(90.0, (10.0, 10.0));
+        assert_point(t.transform_point(Point::new(10.0, 10.0)), (10.0, 10.0));
+        assert_point(t.transform_point(Point::new(20.0, 10.0)), (10.0, 20.0));
+        let inv = t.invert().unwrap();
+        assert_point(inv.transform_point(Point::new(10.0, 20.0)), (20.0, 10.0));
+        assert_point(
+            t.then(&inv).transform_point(Point::new(3.0, 4.0)),
+            (3.0, 4.0),
+        );
+        assert!(Transform::scale(0.0, 1.0).invert().is_none());
+    }
+
+    #[test]
+    fn skew() {
+        let t = Transform::skew(45.0, 0.0, (0.0, 10.0));
+        assert_point(t.transform_point(Point::new(0.0, 10.0)), (0.0, 10.0));
+        assert_point(t.transform_point(Point::new(0.0, 20.0)), (10.0, 20.0));
+        let t = Transform::skew(0.0, 45.0, (0.0, 0.0));
+        assert_point(t.transform_point(Point::new(10.0, 0.0)), (10.0, 10.0));
+    }
+
+    #[test]
+    fn compose() {
+        let t = Transform::translation(10.0, 0.0).then(&Transform::scale(2.0, 3.0));
+        assert_point(t.transform_point(Point::new(1.0, 1.0)), (22.0, 3.0));
+        let r = Transform::rotation(90.0, (0.0, 0.0))
+            .transform_rect(&Rect::new((0.0, 0.0), (10.0, 5.0)));
+        assert_point(r.origin, (-5.0, 0.0));
+        assert_point(r.endpoint(), (0.0, 10.0));
+        assert!(Transform::default().is_identity());
+    }
+}
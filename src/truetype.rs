@@ -0,0 +1,335 @@
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use std::collections::BTreeSet;
+
+const KEEP_TABLES: [&[u8; 4]; 9] = [
+    b"cvt ", b"fpgm", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"prep",
+];
+
+#[inline]
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+#[inline]
+fn i16_at(data: &[u8], offset: usize) -> Option<i16> {
+    u16_at(data, offset).map(|v| v as i16)
+}
+
+#[inline]
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn write_font(tables: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let num_tables = tables.len() as u16;
+    let entry_selector = 15 - num_tables.leading_zeros() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+    let cff = tables
+        .iter()
+        .any(|(tag, _)| tag == b"CFF " || tag == b"CFF2");
+    let mut font = vec![];
+    font.extend_from_slice(if cff { b"OTTO" } else { b"\0\x01\0\0" });
+    font.extend_from_slice(&num_tables.to_be_bytes());
+    font.extend_from_slice(&search_range.to_be_bytes());
+    font.extend_from_slice(&entry_selector.to_be_bytes());
+    font.extend_from_slice(&(num_tables * 16 - search_range).to_be_bytes());
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, data) in tables {
+        font.extend_from_slice(tag);
+        font.extend_from_slice(&checksum(data).to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += (data.len() + 3) & !3;
+    }
+    let mut head_offset = 0;
+    for (tag, data) in tables {
+        if tag == b"head" {
+            head_offset = font.len();
+        }
+        font.extend_from_slice(data);
+        font.resize((font.len() + 3) & !3, 0);
+    }
+    let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&font));
+    font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    font
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct FontFile<'a> {
+    data: &'a [u8],
+    tables: Vec<([u8; 4], usize, usize)>,
+}
+
+impl<'a> FontFile<'a> {
+    pub(crate) fn parse(data: &'a [u8], index: u32) -> Option<Self> {
+        let mut offset = 0;
+        if data.get(0..4)? == b"ttcf" {
+            let count = u32_at(data, 8)?;
+            if index >= count {
+                return None;
+            }
+            offset = u32_at(data, 12 + index as usize * 4)? as usize;
+        }
+        let num_tables = u16_at(data, offset + 4)? as usize;
+        let mut tables = Vec::with_capacity(num_tables);
+        for i in 0..num_tables {
+            let record = offset + 12 + i * 16;
+            let tag: [u8; 4] = data.get(record..record + 4)?.try_into().ok()?;
+            let table_offset = u32_at(data, record + 8)? as usize;
+            let len = u32_at(data, record + 12)? as usize;
+            data.get(table_offset..table_offset + len)?;
+            tables.push((tag, table_offset, len));
+        }
+        let font = Self { data, tables };
+        font.table(b"head")?;
+        font.table(b"hhea")?;
+        font.table(b"maxp")?;
+        Some(font)
+    }
+
+    pub(crate) fn table(&self, tag: &[u8; 4]) -> Option<&'a [u8]> {
+        self.tables
+            .iter()
+            .find(|(t, _, _)| t == tag)
+            .map(|&(_, offset, len)| &self.data[offset..offset + len])
+    }
+
+    #[inline]
+    pub(crate) fn is_cff(&self) -> bool {
+        self.table(b"CFF ").is_some() || self.table(b"CFF2").is_some()
+    }
+
+    #[inline]
+    pub(crate) fn is_variable(&self) -> bool {
+        self.table(b"fvar").is_some()
+    }
+
+    fn head(&self) -> Option<Vec<u8>> {
+        let mut head = self.table(b"head")?.to_vec();
+        head.get_mut(8..12)?.copy_from_slice(&[0; 4]);
+        Some(head)
+    }
+
+    // The face with all of its tables, taken out of a collection if necessary.
+    pub(crate) fn face(&self) -> Option<Vec<u8>> {
+        let head = self.head()?;
+        let mut tables = self
+            .tables
+            .iter()
+            .map(|&(tag, offset, len)| match &tag {
+                b"head" => (tag, head.clone()),
+                _ => (tag, self.data[offset..offset + len].to_vec()),
+            })
+            .collect::<Vec<_>>();
+        tables.sort_by_key(|(tag, _)| *tag);
+        Some(write_font(&tables))
+    }
+
+    #[inline]
+    pub(crate) fn units_per_em(&self) -> u16 {
+        self.table(b"head")
+            .and_then(|head| u16_at(head, 18))
+            .unwrap_or(1000)
+    }
+
+    #[inline]
+    pub(crate) fn bbox(&self) -> [i16; 4] {
+        let head = self.table(b"head").unwrap_or_default();
+        [36, 38, 40, 42].map(|offset| i16_at(head, offset).unwrap_or(0))
+    }
+
+    #[inline]
+    pub(crate) fn ascender(&self) -> i16 {
+        self.table(b"hhea")
+            .and_then(|hhea| i16_at(hhea, 4))
+            .unwrap_or(0)
+    }
+
+    #[inline]
+    pub(crate) fn descender(&self) -> i16 {
+        self.table(b"hhea")
+            .and_then(|hhea| i16_at(hhea, 6))
+            .unwrap_or(0)
+    }
+
+    #[inline]
+    pub(crate) fn num_glyphs(&self) -> u16 {
+        self.table(b"maxp")
+            .and_then(|maxp| u16_at(maxp, 4))
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn advance(&self, glyph: u16) -> u16 {
+        let (Some(hhea), Some(hmtx)) = (self.table(b"hhea"), self.table(b"hmtx")) else {
+            return 0;
+        };
+        let metrics = u16_at(hhea, 34).unwrap_or(0);
+        if metrics == 0 {
+            return 0;
+        }
+        let index = glyph.min(metrics - 1) as usize;
+        u16_at(hmtx, index * 4).unwrap_or(0)
+    }
+
+    fn glyph_ranges(&self) -> Option<Vec<(usize, usize)>> {
+        let head = self.table(b"head")?;
+        let loca = self.table(b"loca")?;
+        let long = i16_at(head, 50)? != 0;
+        let offset = |i: usize| {
+            if long {
+                u32_at(loca, i * 4).map(|v| v as usize)
+            } else {
+                u16_at(loca, i * 2).map(|v| v as usize * 2)
+            }
+        };
+        (0..self.num_glyphs() as usize)
+            .map(|i| Some((offset(i)?, offset(i + 1)?)))
+            .collect()
+    }
+
+    fn components(glyph: &[u8]) -> Vec<u16> {
+        let mut components = vec![];
+        if glyph.len() < 10 || i16_at(glyph, 0).unwrap_or(0) >= 0 {
+            return components;
+        }
+        let mut offset = 10;
+        while let (Some(flags), Some(index)) = (u16_at(glyph, offset), u16_at(glyph, offset + 2)) {
+            components.push(index);
+            offset += 4;
+            offset += if flags & 0x0001 != 0 { 4 } else { 2 };
+            if flags & 0x0008 != 0 {
+                offset += 2;
+            } else if flags & 0x0040 != 0 {
+                offset += 4;
+            } else if flags & 0x0080 != 0 {
+                offset += 8;
+            }
+            if flags & 0x0020 == 0 {
+                break;
+            }
+        }
+        components
+    }
+
+    // Only static TrueType outlines are subset. CFF fonts have no `glyf`, and dropping
+    // `fvar`/`gvar`/`avar` from a variable font would leave glyphs that no longer match it.
+    pub(crate) fn subset(&self, glyphs: &BTreeSet<u16>) -> Option<Vec<u8>> {
+        if self.is_variable() {
+            return None;
+        }
+        let glyf = self.table(b"glyf")?;
+        let ranges = self.glyph_ranges()?;
+        let mut keep = BTreeSet::new();
+        let mut stack = glyphs
+            .iter()
+            .copied()
+            .chain(std::iter::once(0))
+            .collect::<Vec<_>>();
+        while let Some(glyph) = stack.pop() {
+            if (glyph as usize) >= ranges.len() || !keep.insert(glyph) {
+                continue;
+            }
+            let (start, end) = ranges[glyph as usize];
+            stack.extend(Self::components(glyf.get(start..end)?));
+        }
+        let mut new_glyf = vec![];
+        let mut new_loca = vec![];
+        for (i, &(start, end)) in ranges.iter().enumerate() {
+            new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+            if keep.contains(&(i as u16)) {
+                new_glyf.extend_from_slice(glyf.get(start..end)?);
+                new_glyf.resize((new_glyf.len() + 3) & !3, 0);
+            }
+        }
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        let mut head = self.head()?;
+        head.get_mut(50..52)?.copy_from_slice(&1u16.to_be_bytes());
+        let tables = KEEP_TABLES
+            .iter()
+            .filter_map(|&tag| {
+                let data = match tag {
+                    b"glyf" => new_glyf.clone(),
+                    b"loca" => new_loca.clone(),
+                    b"head" => head.clone(),
+                    _ => self.table(tag)?.to_vec(),
+                };
+                Some((*tag, data))
+            })
+            .collect::<Vec<_>>();
+        Some(write_font(&tables))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subset() {
+        let data =
+            std::fs::read("./resources/Inconsolata/static/Inconsolata/Inconsolata-Regular.ttf")
+                .unwrap();
+        let font = FontFile::parse(&data, 0).unwrap();
+        assert!(!font.is_cff());
+        assert!(!font.is_variable());
+        let ranges = font.glyph_ranges().unwrap();
+        let glyf = font.table(b"glyf").unwrap();
+        let used = ranges
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(start, end))| {
+                end > start && FontFile::components(&glyf[start..end]).is_empty()
+            })
+            .map(|(i, _)| i as u16)
+            .skip(10)
+            .take(3)
+            .collect::<BTreeSet<_>>();
+        let subset = font.subset(&used).unwrap();
+        assert!(subset.len() < data.len());
+        assert_eq!(checksum(&subset), 0xB1B0_AFBA);
+        let subset_font = FontFile::parse(&subset, 0).unwrap();
+        assert_eq!(subset_font.num_glyphs(), font.num_glyphs());
+        assert_eq!(subset_font.units_per_em(), font.units_per_em());
+        assert_eq!(subset_font.advance(3), font.advance(3));
+        let subset_glyf = subset_font.table(b"glyf").unwrap();
+        for (i, (start, end)) in subset_font.glyph_ranges().unwrap().into_iter().enumerate() {
+            let (orig_start, orig_end) = ranges[i];
+            if i == 0 || used.contains(&(i as u16)) {
+                assert_eq!(
+                    subset_glyf[start..start + orig_end - orig_start],
+                    glyf[orig_start..orig_end]
+                );
+            } else {
+                assert_eq!(start, end);
+            }
+        }
+    }
+
+    #[test]
+    fn variable() {
+        let data = std::fs::read("./resources/Inconsolata/Inconsolata-VariableFont_wdth,wght.ttf")
+            .unwrap();
+        let font = FontFile::parse(&data, 0).unwrap();
+        assert!(font.is_variable());
+        assert!(font.subset(&BTreeSet::from([3])).is_none());
+        let face = font.face().unwrap();
+        assert_eq!(checksum(&face), 0xB1B0_AFBA);
+        let face_font = FontFile::parse(&face, 0).unwrap();
+        for tag in [b"fvar", b"gvar", b"glyf", b"loca"] {
+            assert_eq!(face_font.table(tag), font.table(tag));
+        }
+    }
+}
@@ -1,28 +1,16 @@
+#[cfg(windows)]
 use raw_window_handle::RawWindowHandle;
-use windows::Win32::{Foundation::HWND, Graphics::Direct2D::Common::*};
-
-#[derive(Clone, Copy, Debug)]
-#[repr(transparent)]
-pub(crate) struct Wrapper<T>(pub(crate) T);
+#[cfg(windows)]
+use windows::Win32::Foundation::HWND;
 
 pub type Rgba<T> = gecl::Rgba<T>;
 
-impl From<Wrapper<Rgba<f32>>> for D2D1_COLOR_F {
-    #[inline]
-    fn from(src: Wrapper<Rgba<f32>>) -> Self {
-        Self {
-            r: src.0.r,
-            g: src.0.g,
-            b: src.0.b,
-            a: src.0.a,
-        }
-    }
-}
-
+#[cfg(windows)]
 pub trait WindowHandle {
     fn handle(&self) -> HWND;
 }
 
+#[cfg(windows)]
 impl WindowHandle for HWND {
     #[inline]
     fn handle(&self) -> HWND {
@@ -30,6 +18,7 @@ impl WindowHandle for HWND {
     }
 }
 
+#[cfg(windows)]
 impl WindowHandle for RawWindowHandle {
     #[inline]
     fn handle(&self) -> HWND {
@@ -38,6 +27,7 @@ impl WindowHandle for RawWindowHandle {
     }
 }
 
+#[cfg(windows)]
 impl WindowHandle for *const std::ffi::c_void {
     #[inline]
     fn handle(&self) -> HWND {
@@ -45,6 +35,7 @@ impl WindowHandle for *const std::ffi::c_void {
     }
 }
 
+#[cfg(windows)]
 impl WindowHandle for *mut std::ffi::c_void {
     #[inline]
     fn handle(&self) -> HWND {
@@ -52,6 +43,7 @@ impl WindowHandle for *mut std::ffi::c_void {
     }
 }
 
+#[cfg(windows)]
 impl WindowHandle for isize {
     #[inline]
     fn handle(&self) -> HWND {